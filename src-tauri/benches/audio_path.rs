@@ -0,0 +1,150 @@
+// Capture path cost per 10ms stereo 48kHz callback buffer: the previous
+// `Vec`-per-buffer channel path against the preallocated ring path.
+// Reports mean and tail latency plus heap allocations per buffer.
+// Run with `cargo bench --bench audio_path`.
+
+use crossbeam::channel::{bounded, Receiver, Sender};
+use rt_translator_lib::audio::ring::{sample_ring, RingReader, RingWriter};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const CHANNELS: usize = 2;
+const FRAMES: usize = 480;
+const ITERATIONS: usize = 200_000;
+
+/// System allocator that counts allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// The capture path, driven one callback buffer at a time.
+trait Path {
+    /// Device callback: hand the buffer to the processor thread.
+    fn callback(&mut self, samples: &[f32]);
+    /// Processor: forward to STT and the IPC bridge, then drain both like consumers.
+    fn process(&mut self) -> usize;
+}
+
+fn main() {
+    let buffer: Vec<f32> = (0..FRAMES * CHANNELS)
+        .map(|i| (i % 97) as f32 / 97.0)
+        .collect();
+    println!(
+        "{:<10} {:>9} {:>10} {:>9} {:>16} {:>13}",
+        "path", "mean ns", "p99.9 ns", "max ns", "callback allocs", "total allocs"
+    );
+    bench("channels", &mut ChannelPath::new(), &buffer);
+    bench("rings", &mut RingPath::new(), &buffer);
+}
+
+fn bench(name: &str, path: &mut impl Path, buffer: &[f32]) {
+    let mut times = Vec::with_capacity(ITERATIONS);
+    let mut callback_allocs = 0;
+    let total_before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        path.callback(buffer);
+        callback_allocs += ALLOCATIONS.load(Ordering::Relaxed) - before;
+        black_box(path.process());
+        times.push(start.elapsed().as_nanos() as u64);
+    }
+    let total_allocs = ALLOCATIONS.load(Ordering::Relaxed) - total_before;
+    times.sort_unstable();
+    let mean = times.iter().sum::<u64>() as f64 / ITERATIONS as f64;
+    println!(
+        "{:<10} {:>9.0} {:>10} {:>9} {:>16.2} {:>13.2}",
+        name,
+        mean,
+        times[ITERATIONS * 999 / 1000],
+        times[ITERATIONS - 1],
+        callback_allocs as f64 / ITERATIONS as f64,
+        total_allocs as f64 / ITERATIONS as f64,
+    );
+}
+
+/// Callback allocates a buffer, processor clones it for STT and collects bytes.
+struct ChannelPath {
+    mic: (Sender<Vec<f32>>, Receiver<Vec<f32>>),
+    stt: (Sender<Vec<f32>>, Receiver<Vec<f32>>),
+    out: (Sender<Vec<u8>>, Receiver<Vec<u8>>),
+}
+
+impl ChannelPath {
+    fn new() -> Self {
+        Self {
+            mic: bounded(100),
+            stt: bounded(100),
+            out: bounded(100),
+        }
+    }
+}
+
+impl Path for ChannelPath {
+    fn callback(&mut self, samples: &[f32]) {
+        let _ = self.mic.0.try_send(samples.to_vec());
+    }
+
+    fn process(&mut self) -> usize {
+        let samples = self.mic.1.recv().unwrap();
+        let _ = self.stt.0.try_send(samples.clone());
+        let bytes: Vec<u8> = samples.iter().flat_map(|&s| s.to_le_bytes()).collect();
+        let _ = self.out.0.try_send(bytes);
+        self.stt.1.recv().unwrap().len() + self.out.1.recv().unwrap().len()
+    }
+}
+
+/// Callback writes into a ring, processor reads into a reused block and forwards it
+/// to the STT ring; only the IPC byte buffer (owned by the receiver) is allocated.
+struct RingPath {
+    mic: (RingWriter, RingReader),
+    stt: (RingWriter, RingReader),
+    out: (Sender<Vec<u8>>, Receiver<Vec<u8>>),
+    block: Vec<f32>,
+    stt_block: Vec<f32>,
+}
+
+impl RingPath {
+    fn new() -> Self {
+        Self {
+            mic: sample_ring(FRAMES * 4, CHANNELS as u16),
+            stt: sample_ring(FRAMES * 4, CHANNELS as u16),
+            out: bounded(100),
+            block: vec![0.0; FRAMES * CHANNELS],
+            stt_block: vec![0.0; FRAMES * CHANNELS],
+        }
+    }
+}
+
+impl Path for RingPath {
+    fn callback(&mut self, samples: &[f32]) {
+        self.mic.0.push_fn(samples.len(), |i| samples[i]);
+    }
+
+    fn process(&mut self) -> usize {
+        let n = self.mic.1.pop(&mut self.block);
+        self.stt.0.push(&self.block[..n]);
+        let mut bytes = vec![0u8; n * 4];
+        for (dst, s) in bytes.chunks_exact_mut(4).zip(&self.block[..n]) {
+            dst.copy_from_slice(&s.to_le_bytes());
+        }
+        let _ = self.out.0.try_send(bytes);
+        self.stt.1.pop(&mut self.stt_block) + self.out.1.recv().unwrap().len()
+    }
+}
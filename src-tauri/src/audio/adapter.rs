@@ -0,0 +1,137 @@
+use crate::audio::resampler::AudioResampler;
+use crate::audio::types::SourceFormat;
+use anyhow::Result;
+
+/// Frames per resampler call (10ms at 48kHz).
+const CHUNK_FRAMES: usize = 480;
+
+/// Streaming downmix of interleaved audio to mono, resampled to a target rate.
+pub struct MonoConverter {
+    channels: usize,
+    resampler: Option<AudioResampler>,
+    pending: Vec<f32>,
+    out: Vec<f32>,
+}
+
+impl MonoConverter {
+    pub fn new(from: SourceFormat, to_rate: u32) -> Result<Self> {
+        let resampler = if from.sample_rate != to_rate {
+            Some(AudioResampler::new(
+                from.sample_rate,
+                to_rate,
+                1,
+                CHUNK_FRAMES,
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            channels: from.channels.max(1) as usize,
+            resampler,
+            pending: Vec::with_capacity(CHUNK_FRAMES * 2),
+            out: Vec::with_capacity(CHUNK_FRAMES),
+        })
+    }
+
+    /// Convert interleaved samples, handing each converted block to `emit`.
+    /// Resampled output lags input by up to one chunk. Buffers are reused, so
+    /// steady-state conversion does not allocate.
+    pub fn process_with(&mut self, interleaved: &[f32], mut emit: impl FnMut(&[f32])) {
+        let ch = self.channels;
+        let mono = interleaved
+            .chunks_exact(ch)
+            .map(|frame| frame.iter().sum::<f32>() / ch as f32);
+        let Some(ref mut rs) = self.resampler else {
+            self.out.clear();
+            self.out.extend(mono);
+            emit(&self.out);
+            return;
+        };
+
+        self.pending.extend(mono);
+        let chunk = rs.input_frames_next();
+        let mut used = 0;
+        while self.pending.len() - used >= chunk {
+            self.out.clear();
+            if let Err(e) = rs.process_into(&self.pending[used..used + chunk], &mut self.out) {
+                tracing::warn!("Resample error: {}", e);
+            }
+            used += chunk;
+            emit(&self.out);
+        }
+        self.pending.drain(..used);
+    }
+
+    /// Convert interleaved samples to a new buffer (see `process_with`).
+    pub fn process(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        self.process_with(interleaved, |block| out.extend_from_slice(block));
+        out
+    }
+}
+
+/// Converts a stream to another rate / channel count (via mono), e.g. so a
+/// replacement device can feed a pipeline started with the old device's format.
+pub struct FormatAdapter {
+    mono: MonoConverter,
+    out_channels: usize,
+    out: Vec<f32>,
+}
+
+impl FormatAdapter {
+    pub fn new(from: SourceFormat, to: SourceFormat) -> Result<Self> {
+        Ok(Self {
+            mono: MonoConverter::new(from, to.sample_rate)?,
+            out_channels: to.channels.max(1) as usize,
+            out: Vec::new(),
+        })
+    }
+
+    /// Convert interleaved samples, handing each converted block to `emit`.
+    pub fn process_with(&mut self, interleaved: &[f32], mut emit: impl FnMut(&[f32])) {
+        let (out, channels) = (&mut self.out, self.out_channels);
+        self.mono.process_with(interleaved, |mono| {
+            out.clear();
+            for &s in mono {
+                out.extend(std::iter::repeat_n(s, channels));
+            }
+            emit(out);
+        });
+    }
+
+    pub fn process(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        self.process_with(interleaved, |block| out.extend_from_slice(block));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sample_rate: u32, channels: u16) -> SourceFormat {
+        SourceFormat {
+            sample_rate,
+            channels,
+        }
+    }
+
+    #[test]
+    fn same_rate_downmixes_and_duplicates() {
+        let mut adapter = FormatAdapter::new(format(16000, 2), format(16000, 3)).unwrap();
+        let out = adapter.process(&[0.2, 0.4, 0.0, 1.0]);
+        assert_eq!(out.len(), 6);
+        assert!((out[0] - 0.3).abs() < 1e-6 && (out[2] - 0.3).abs() < 1e-6);
+        assert!((out[3] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn resamples_to_target_rate() {
+        let mut adapter = FormatAdapter::new(format(44100, 1), format(48000, 2)).unwrap();
+        // 1s in, ~1s out minus the resampler's buffering
+        let out: usize = (0..100).map(|_| adapter.process(&[0.1; 441]).len()).sum();
+        assert!(out > 2 * 46000 && out <= 2 * 48000, "out {}", out);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::audio::adapter::MonoConverter;
+use crate::audio::dsp::{DspChain, DspConfig};
+use crate::audio::noise_floor::MIN_FLOOR;
+use crate::audio::types::SourceFormat;
+use crate::audio::vad::compute_rms;
+use anyhow::Result;
+use serde::Serialize;
+
+/// 10ms at 16kHz, the VAD frame size.
+const FRAME_SIZE: usize = 160;
+/// Frames ignored while the DSP chain (noise suppressor) settles.
+const WARMUP_FRAMES: usize = 50;
+
+/// Background levels measured from a stretch of room tone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RoomTone {
+    pub noise_floor: f32,
+    pub rms_threshold: f32,
+}
+
+impl RoomTone {
+    /// Floor at the median frame RMS. The threshold sits `margin` above it and
+    /// never below the loudest 5% of frames, so room noise bursts stay silence.
+    pub fn from_frame_rms(mut rms: Vec<f32>, margin: f32) -> Option<Self> {
+        if rms.is_empty() {
+            return None;
+        }
+        rms.sort_by(f32::total_cmp);
+        let noise_floor = rms[rms.len() / 2].max(MIN_FLOOR);
+        let p95 = rms[(rms.len() * 95 / 100).min(rms.len() - 1)];
+        Some(Self {
+            noise_floor,
+            rms_threshold: (noise_floor * margin).max(p95),
+        })
+    }
+}
+
+/// Measures room tone from raw capture audio, processed the way the STT
+/// pipeline processes it before the VAD (16kHz mono, then the DSP chain).
+pub struct RoomToneMeter {
+    converter: MonoConverter,
+    dsp: DspChain,
+    frame_buf: Vec<f32>,
+    frame_rms: Vec<f32>,
+    skipped: usize,
+}
+
+impl RoomToneMeter {
+    pub fn new(format: SourceFormat, dsp: &DspConfig) -> Result<Self> {
+        Ok(Self {
+            converter: MonoConverter::new(format, 16000)?,
+            dsp: DspChain::new(dsp, 16000),
+            frame_buf: Vec::with_capacity(FRAME_SIZE * 4),
+            frame_rms: Vec::new(),
+            skipped: 0,
+        })
+    }
+
+    /// Feed interleaved samples in the capture format.
+    pub fn push(&mut self, raw: &[f32]) {
+        self.frame_buf.extend(self.converter.process(raw));
+        while self.frame_buf.len() >= FRAME_SIZE {
+            let mut frame: Vec<f32> = self.frame_buf.drain(..FRAME_SIZE).collect();
+            self.dsp.process(&mut frame);
+            if self.skipped < WARMUP_FRAMES {
+                self.skipped += 1;
+            } else {
+                self.frame_rms.push(compute_rms(&frame));
+            }
+        }
+    }
+
+    /// Room tone measured so far, excluding warm-up.
+    pub fn measured_ms(&self) -> u64 {
+        self.frame_rms.len() as u64 * 10
+    }
+
+    pub fn finish(self, margin: f32) -> Option<RoomTone> {
+        RoomTone::from_frame_rms(self.frame_rms, margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_clears_room_noise() {
+        // Steady hiss at 0.01 with occasional 0.05 bumps (6% of frames)
+        let rms: Vec<f32> = (0..100)
+            .map(|i| if i % 16 == 0 { 0.05 } else { 0.01 })
+            .collect();
+        let tone = RoomTone::from_frame_rms(rms, 3.0).unwrap();
+        assert_eq!(tone.noise_floor, 0.01);
+        assert_eq!(tone.rms_threshold, 0.05);
+        assert!(RoomTone::from_frame_rms(Vec::new(), 3.0).is_none());
+    }
+
+    #[test]
+    fn meter_measures_stereo_48k_after_warmup() {
+        let format = SourceFormat {
+            sample_rate: 48000,
+            channels: 2,
+        };
+        let dsp = DspConfig {
+            high_pass: false,
+            noise_suppression: false,
+            ..DspConfig::default()
+        };
+        let mut meter = RoomToneMeter::new(format, &dsp).unwrap();
+        // 2s of constant 0.004 on both channels
+        for _ in 0..200 {
+            meter.push(&[0.004; 960]);
+        }
+        assert!(meter.measured_ms() >= 1400);
+        let tone = meter.finish(3.0).unwrap();
+        assert!((tone.noise_floor - 0.004).abs() < 5e-4);
+        assert!((tone.rms_threshold - 0.012).abs() < 1.5e-3);
+    }
+}
@@ -1,81 +1,304 @@
-#![allow(deprecated)] // cpal 0.17 deprecates name() in favor of description()
-
-use crate::audio::device;
-use anyhow::{Context, Result};
-use crossbeam::channel::{bounded, Receiver, Sender};
-use cpal::traits::{DeviceTrait, StreamTrait};
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::audio::channel_map::ChannelMap;
+use crate::audio::events::CaptureEvent;
+use crate::audio::health::{HealthMonitor, RingStats, SourceMonitor};
+use crate::audio::levels::{AudioLevels, LevelMeter};
+use crate::audio::live_source::{LiveSource, LiveSources};
+use crate::audio::mic_source::MicSource;
+use crate::audio::mixer::{apply_gain, LoopbackMixer, MixConfig};
+use crate::audio::recorder::{meeting_recording_path, WavRecorder};
+use crate::audio::ring::{sample_ring, RingReader, RingWriter};
+use crate::audio::source::AudioSource;
+use crate::audio::types::{DeviceSelection, DroppedFrames, SourceFormat, SourceRole};
+use crate::audio::watchdog::{SystemDevices, Target, Watchdog};
+use anyhow::Result;
+use arc_swap::ArcSwapOption;
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often `CaptureEvent::Levels` is sent (20 Hz).
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often source and STT ring health (stalls, drops, backlog) is checked.
+const HEALTH_INTERVAL: Duration = Duration::from_millis(250);
+/// Audio each source ring holds before the source starts dropping.
+const RING_SECONDS: usize = 2;
+/// Audio a recording ring holds while its recorder thread is busy on disk.
+const RECORDING_RING_SECONDS: usize = 4;
+/// Processor block size (10ms); blocks are preallocated once per capture.
+const BLOCKS_PER_SECOND: usize = 100;
 
 /// Manages dual-stream audio capture (mic + system loopback).
-/// Audio callbacks run on dedicated OS threads; data flows through crossbeam channels.
+/// Sources run on dedicated OS threads and write into preallocated sample rings,
+/// so nothing on the path from device callback to STT allocates or locks; STT and
+/// recording rings are swapped in atomically, and recordings are written to disk
+/// on their own threads.
 pub struct AudioCaptureManager {
-    mic_tx: Sender<Vec<f32>>,
-    mic_rx: Receiver<Vec<f32>>,
-    #[cfg(windows)]
-    loopback_tx: Sender<Vec<u8>>,
-    #[cfg(windows)]
-    loopback_rx: Receiver<Vec<u8>>,
-    output_tx: Option<Sender<Vec<u8>>>,
-    stt_tx: Arc<Mutex<Option<Sender<Vec<f32>>>>>,
+    mic_ring: Option<RingWriter>,
+    loopback_ring: Option<RingWriter>,
+    mix_config: MixConfig,
+    mic_channel_map: ChannelMap,
+    output: Arc<ArcSwapOption<RingWriter>>,
+    events_tx: Sender<CaptureEvent>,
+    events_rx: Receiver<CaptureEvent>,
+    stt_tx: Arc<ArcSwapOption<RingWriter>>,
+    stt_loopback_tx: Arc<ArcSwapOption<RingWriter>>,
+    stt_dropped: Arc<AtomicU64>,
+    output_dropped: Arc<AtomicU64>,
+    recording: Arc<RecordingTaps>,
+    recorder_threads: Mutex<Vec<JoinHandle<()>>>,
     is_running: Arc<AtomicBool>,
-    mic_stream: Option<cpal::Stream>,
+    sources: Arc<Mutex<LiveSources>>,
     mic_sample_rate: u32,
     mic_channels: u16,
+    mic_realtime: bool,
+    loopback_format: Option<SourceFormat>,
+    devices: Arc<Mutex<DeviceSelection>>,
 }
 
 impl AudioCaptureManager {
     pub fn new() -> Self {
-        let (mic_tx, mic_rx) = bounded(100);
-        #[cfg(windows)]
-        let (loopback_tx, loopback_rx) = bounded(100);
+        let (events_tx, events_rx) = bounded(32);
 
         Self {
-            mic_tx,
-            mic_rx,
-            #[cfg(windows)]
-            loopback_tx,
-            #[cfg(windows)]
-            loopback_rx,
-            output_tx: None,
-            stt_tx: Arc::new(Mutex::new(None)),
+            mic_ring: None,
+            loopback_ring: None,
+            mix_config: MixConfig::default(),
+            mic_channel_map: ChannelMap::default(),
+            output: Arc::new(ArcSwapOption::empty()),
+            events_tx,
+            events_rx,
+            stt_tx: Arc::new(ArcSwapOption::empty()),
+            stt_loopback_tx: Arc::new(ArcSwapOption::empty()),
+            stt_dropped: Arc::new(AtomicU64::new(0)),
+            output_dropped: Arc::new(AtomicU64::new(0)),
+            recording: Arc::new(RecordingTaps::default()),
+            recorder_threads: Mutex::new(Vec::new()),
             is_running: Arc::new(AtomicBool::new(false)),
-            mic_stream: None,
+            sources: Arc::new(Mutex::new(LiveSources::default())),
             mic_sample_rate: 0,
             mic_channels: 0,
+            mic_realtime: true,
+            loopback_format: None,
+            devices: Arc::new(Mutex::new(DeviceSelection::default())),
+        }
+    }
+
+    /// Start capturing from live devices. The mixed stream is only forwarded once
+    /// `set_output` asks for it.
+    /// `requested` picks devices by `DeviceInfo.id`; `None` entries fall back to the OS default.
+    /// A watchdog rebuilds streams that die (e.g. an unplugged headset) on the requested
+    /// device or the new default, keeping the original format so downstream is unaffected.
+    pub fn start(&mut self, requested: &DeviceSelection) -> Result<()> {
+        let mic = MicSource::open(requested.mic.as_deref())?
+            .with_channel_map(self.mic_channel_map.clone())?;
+        let loopback = open_system_loopback(requested.loopback.as_deref())?;
+        self.start_with_sources(Box::new(mic), loopback)?;
+
+        let mut targets = Vec::new();
+        if let Some(ref ring) = self.mic_ring {
+            targets.push(Target {
+                role: SourceRole::Mic,
+                ring: ring.clone(),
+                format: self.mic_source_format(),
+                requested: requested.mic.clone(),
+            });
+        }
+        if let (Some(format), Some(ring)) = (self.loopback_format, &self.loopback_ring) {
+            targets.push(Target {
+                role: SourceRole::Loopback,
+                ring: ring.clone(),
+                format,
+                requested: requested.loopback.clone(),
+            });
         }
+        let watchdog = Watchdog::new(
+            Box::new(SystemDevices {
+                mic_channels: self.mic_channel_map.clone(),
+            }),
+            self.sources.clone(),
+            self.devices.clone(),
+            self.events_tx.clone(),
+            self.is_running.clone(),
+            targets,
+        );
+        if let Err(e) = watchdog.spawn() {
+            self.stop();
+            return Err(e);
+        }
+        Ok(())
     }
 
-    /// Start capturing audio. Output bytes (PCM f32 LE) are sent to `output_tx`.
-    pub fn start(&mut self, output_tx: Sender<Vec<u8>>) -> Result<()> {
+    /// Start capturing from arbitrary sources (e.g. a `FileSource` replay).
+    /// The loopback source, if any, is mixed into `mic` exactly as for live capture.
+    pub fn start_with_sources(
+        &mut self,
+        mic: Box<dyn AudioSource>,
+        loopback: Option<Box<dyn AudioSource>>,
+    ) -> Result<()> {
         self.is_running.store(true, Ordering::SeqCst);
-        self.output_tx = Some(output_tx);
+        self.loopback_format = None;
+        self.loopback_ring = None;
+        self.stt_dropped.store(0, Ordering::Relaxed);
+        self.output_dropped.store(0, Ordering::Relaxed);
+
+        let format = mic.format();
+        let (mic_ring, mic_reader) = source_ring(format);
+        self.mic_ring = Some(mic_ring.clone());
+        let mic = match LiveSource::start(mic, mic_ring, format) {
+            Ok(mic) => mic,
+            Err(e) => {
+                self.is_running.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        };
+        self.mic_sample_rate = format.sample_rate;
+        self.mic_channels = format.channels;
+        self.mic_realtime = mic.is_realtime();
+        let mut devices = DeviceSelection {
+            mic: Some(mic.id()),
+            loopback: None,
+        };
+        self.lock_sources().mic = Some(mic);
+
+        let mut loopback_reader = None;
+        if let Some(source) = loopback {
+            let format = source.format();
+            let (ring, reader) = source_ring(format);
+            self.loopback_ring = Some(ring.clone());
+            loopback_reader = Some(reader);
+            let source = match LiveSource::start(source, ring, format) {
+                Ok(source) => source,
+                Err(e) => {
+                    self.stop();
+                    return Err(e);
+                }
+            };
+            self.loopback_format = Some(format);
+            devices.loopback = Some(source.id());
+            self.lock_sources().loopback = Some(source);
+        }
+        if let Ok(mut guard) = self.devices.lock() {
+            *guard = devices;
+        }
+
+        self.start_processor_thread(mic_reader, loopback_reader)?;
+        Ok(())
+    }
 
-        self.start_mic_stream()?;
+    /// Forward the mixed stream (interleaved f32, mic format) to `tx`, replacing any
+    /// previous output. Realtime audio it has no room for is dropped and counted as
+    /// `output` drops. Nothing is forwarded until this is called.
+    pub fn set_output(&self, tx: RingWriter) {
+        self.output.store(Some(Arc::new(tx)));
+    }
 
-        #[cfg(windows)]
-        self.start_wasapi_loopback()?;
+    /// Stop forwarding the mixed stream.
+    pub fn clear_output(&self) {
+        self.output.store(None);
+    }
 
-        #[cfg(not(windows))]
-        tracing::warn!("System audio loopback not implemented on this platform");
+    /// Receiver for capture notifications (levels, ...). Ends once the manager is dropped.
+    pub fn events(&self) -> Receiver<CaptureEvent> {
+        self.events_rx.clone()
+    }
 
-        self.start_processor_thread()?;
-        Ok(())
+    /// Set per-source gains used when mixing loopback into the mic stream.
+    /// Takes effect on the next `start`.
+    pub fn set_mix_config(&mut self, config: MixConfig) {
+        self.mix_config = config;
     }
 
-    /// Set a sender for forwarding raw f32 audio to the STT pipeline.
-    /// Thread-safe: the processor thread sees updates dynamically via Arc<Mutex>.
-    pub fn set_stt_sender(&self, tx: Sender<Vec<f32>>) {
-        if let Ok(mut guard) = self.stt_tx.lock() {
-            *guard = Some(tx);
+    /// Select which mic channels are captured (all by default). Takes effect on the next `start`.
+    pub fn set_mic_channels(&mut self, map: ChannelMap) {
+        self.mic_channel_map = map;
+    }
+
+    /// Set a ring for forwarding the mic alone (echo-cancelled, mic format) to STT.
+    /// The processor thread picks it up on its next block without locking.
+    pub fn set_stt_sender(&self, tx: RingWriter) {
+        self.stt_tx.store(Some(Arc::new(tx)));
+    }
+
+    /// Set a ring for forwarding the loopback to STT as mono at the mic rate, one
+    /// frame per mic frame, so both lanes share a timeline. Nothing is sent without
+    /// a loopback source (see `has_loopback`).
+    pub fn set_loopback_stt_sender(&self, tx: RingWriter) {
+        self.stt_loopback_tx.store(Some(Arc::new(tx)));
+    }
+
+    /// Remove the mic STT sender if it still feeds `rx`, keeping one attached since
+    /// (e.g. by a meeting that started meanwhile).
+    pub fn clear_stt_sender_for(&self, rx: &RingReader) {
+        let current = self.stt_tx.load();
+        if current.as_ref().is_some_and(|tx| tx.feeds(rx)) {
+            self.stt_tx.compare_and_swap(&current, None);
         }
     }
 
-    /// Remove the STT sender (stops forwarding audio to STT).
+    /// Remove the STT senders (stops forwarding audio to STT).
     pub fn clear_stt_sender(&self) {
-        if let Ok(mut guard) = self.stt_tx.lock() {
-            *guard = None;
+        self.stt_tx.store(None);
+        self.stt_loopback_tx.store(None);
+    }
+
+    /// Whether system loopback is captured alongside the mic.
+    pub fn has_loopback(&self) -> bool {
+        self.loopback_format.is_some()
+    }
+
+    /// Record each source, pre-mix and in its native format, to
+    /// `meeting-<id>-mic.wav` / `meeting-<id>-loopback.wav` under `dir`.
+    pub fn start_source_recording(&self, dir: &Path, meeting_id: i64) -> Result<()> {
+        self.stop_source_recording();
+        // Formats the sources were started with; replacement devices are adapted to them
+        let mut sources = Vec::new();
+        if self.is_active() {
+            sources.push((SourceRole::Mic, self.mic_source_format()));
+        }
+        if let Some(format) = self.loopback_format {
+            sources.push((SourceRole::Loopback, format));
+        }
+        let mut recorders = Vec::new();
+        for (role, format) in sources {
+            let path = meeting_recording_path(dir, meeting_id, Some(role.as_str()));
+            recorders.push((role, WavRecorder::create(&path, format)?, format));
+        }
+
+        let mut threads = self.lock_recorder_threads();
+        for (role, recorder, format) in recorders {
+            let (tx, thread) = spawn_recorder(recorder, format)?;
+            self.recording.tap(role).store(Some(Arc::new(tx)));
+            threads.push(thread);
+        }
+        Ok(())
+    }
+
+    /// Finalize any per-source recordings, once their threads have written what
+    /// the processor sent them.
+    pub fn stop_source_recording(&self) {
+        self.recording.mic.store(None);
+        self.recording.loopback.store(None);
+        for thread in std::mem::take(&mut *self.lock_recorder_threads()) {
+            let _ = thread.join();
+        }
+    }
+
+    /// Devices currently captured (defaults resolved to concrete IDs). Follows
+    /// the watchdog when it moves a stream to another device.
+    pub fn devices(&self) -> DeviceSelection {
+        self.devices.lock().map(|d| d.clone()).unwrap_or_default()
+    }
+
+    /// Frames dropped since capture started because a consumer fell behind.
+    pub fn dropped_frames(&self) -> DroppedFrames {
+        DroppedFrames {
+            mic: self.mic_ring.as_ref().map_or(0, RingWriter::dropped_frames),
+            loopback: self.loopback_ring.as_ref().map_or(0, RingWriter::dropped_frames),
+            stt: self.stt_dropped.load(Ordering::Relaxed),
+            output: self.output_dropped.load(Ordering::Relaxed),
         }
     }
 
@@ -87,91 +310,78 @@ impl AudioCaptureManager {
     /// Stop all capture streams and processor thread.
     pub fn stop(&mut self) {
         self.is_running.store(false, Ordering::SeqCst);
-        // Drop the cpal stream to release audio device resources
-        if let Some(stream) = self.mic_stream.take() {
-            drop(stream);
+        let sources = std::mem::take(&mut *self.lock_sources());
+        for source in [sources.mic, sources.loopback].into_iter().flatten() {
+            source.stop();
         }
-        self.output_tx = None;
+        self.clear_output();
         self.clear_stt_sender();
+        self.stop_source_recording();
         tracing::info!("Audio capture stopped");
     }
 
-    fn start_mic_stream(&mut self) -> Result<()> {
-        let device = device::get_default_input_device()?;
-        let config = device
-            .default_input_config()
-            .context("Failed to get default input config")?;
-
-        self.mic_sample_rate = config.sample_rate();
-        self.mic_channels = config.channels();
-
-        tracing::info!(
-            "Mic: {} ch, {}Hz, {:?}",
-            config.channels(),
-            config.sample_rate(),
-            config.sample_format()
-        );
-
-        let tx = self.mic_tx.clone();
-        let stream = device.build_input_stream(
-            &config.into(),
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                if tx.try_send(data.to_vec()).is_err() {
-                    tracing::warn!("Mic buffer full, dropping frame");
-                }
-            },
-            |err| tracing::error!("Mic stream error: {}", err),
-            None,
-        )?;
-
-        stream.play()?;
-        self.mic_stream = Some(stream);
-        tracing::info!("Mic stream started");
-        Ok(())
+    fn mic_source_format(&self) -> SourceFormat {
+        SourceFormat {
+            sample_rate: self.mic_sample_rate,
+            channels: self.mic_channels,
+        }
     }
 
-    #[cfg(windows)]
-    fn start_wasapi_loopback(&self) -> Result<()> {
-        let tx = self.loopback_tx.clone();
-        let is_running = self.is_running.clone();
-
-        thread::Builder::new()
-            .name("wasapi-loopback".to_string())
-            .spawn(move || {
-                if let Err(e) = wasapi_loopback_thread(tx, is_running) {
-                    tracing::error!("WASAPI loopback error: {}", e);
-                }
-            })?;
+    fn lock_sources(&self) -> std::sync::MutexGuard<'_, LiveSources> {
+        self.sources.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
-        tracing::info!("WASAPI loopback thread started");
-        Ok(())
+    fn lock_recorder_threads(&self) -> std::sync::MutexGuard<'_, Vec<JoinHandle<()>>> {
+        self.recorder_threads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
     }
 
-    fn start_processor_thread(&self) -> Result<()> {
-        let mic_rx = self.mic_rx.clone();
-        let output_tx = self
-            .output_tx
-            .clone()
-            .context("Output channel not set")?;
+    fn start_processor_thread(
+        &self,
+        mic_reader: RingReader,
+        loopback_reader: Option<RingReader>,
+    ) -> Result<()> {
+        let mic_input = MicInput {
+            rx: mic_reader,
+            format: self.mic_source_format(),
+            realtime: self.mic_realtime,
+            gain: self.mix_config.mic_gain,
+        };
         let stt_tx = self.stt_tx.clone();
+        let stt_loopback_tx = self.stt_loopback_tx.clone();
+        let recording = self.recording.clone();
+        let events_tx = self.events_tx.clone();
         let is_running = self.is_running.clone();
-
-        #[cfg(windows)]
-        let _loopback_rx = self.loopback_rx.clone();
+        let loopback = self
+            .loopback_format
+            .zip(loopback_reader)
+            .map(|(format, rx)| LoopbackInput {
+                rx,
+                format,
+                mix_config: self.mix_config,
+            });
+
+        let outputs = ProcessorOutputs {
+            output: self.output.clone(),
+            stt_tx,
+            stt_loopback_tx,
+            recording,
+            events_tx,
+            stt_dropped: self.stt_dropped.clone(),
+            output_dropped: self.output_dropped.clone(),
+        };
 
         thread::Builder::new()
             .name("audio-processor".to_string())
             .spawn(move || {
-                processor_thread(mic_rx, output_tx, stt_tx, is_running);
+                processor_thread(mic_input, loopback, outputs, is_running);
             })?;
 
         tracing::info!("Audio processor thread started");
         Ok(())
     }
-}
 
-// Re-export mic_format for use in commands
-impl AudioCaptureManager {
     /// Check if capture is active.
     pub fn is_active(&self) -> bool {
         self.is_running.load(Ordering::SeqCst)
@@ -184,109 +394,330 @@ impl Default for AudioCaptureManager {
     }
 }
 
-/// Forward mic PCM data as raw f32 LE bytes to the output channel,
-/// and optionally fork a copy of f32 samples to the STT pipeline.
-/// `stt_tx` is behind Arc<Mutex> so set_stt_sender/clear_stt_sender are visible dynamically.
-fn processor_thread(
-    mic_rx: Receiver<Vec<f32>>,
-    output_tx: Sender<Vec<u8>>,
-    stt_tx: Arc<Mutex<Option<Sender<Vec<f32>>>>>,
-    is_running: Arc<AtomicBool>,
-) {
-    while is_running.load(Ordering::SeqCst) {
-        match mic_rx.recv_timeout(std::time::Duration::from_millis(50)) {
-            Ok(samples) => {
-                // Fork: send f32 copy to STT pipeline if connected
-                if let Ok(guard) = stt_tx.lock() {
-                    if let Some(ref tx) = *guard {
-                        let _ = tx.try_send(samples.clone());
-                    }
-                }
+/// Ring holding `RING_SECONDS` of a source's audio.
+fn source_ring(format: SourceFormat) -> (RingWriter, RingReader) {
+    sample_ring(format.sample_rate as usize * RING_SECONDS, format.channels)
+}
 
-                // Convert to LE bytes for IPC bridge
-                let bytes: Vec<u8> =
-                    samples.iter().flat_map(|&s| s.to_le_bytes()).collect();
+/// Open the platform's system-audio loopback source, if it has one.
+#[cfg(windows)]
+pub(crate) fn open_system_loopback(device_id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>> {
+    let source = crate::audio::wasapi_loopback::WasapiLoopbackSource::open(device_id)?;
+    Ok(Some(Box::new(source)))
+}
 
-                if output_tx.try_send(bytes).is_err() {
-                    tracing::warn!("Output buffer full, dropping frame");
-                }
-            }
-            Err(crossbeam::channel::RecvTimeoutError::Timeout) => continue,
-            Err(crossbeam::channel::RecvTimeoutError::Disconnected) => break,
+/// Open the platform's system-audio loopback source, if it has one.
+/// No sound server is not fatal unless a loopback device was explicitly requested.
+#[cfg(target_os = "linux")]
+pub(crate) fn open_system_loopback(device_id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>> {
+    match crate::audio::pulse_loopback::PulseLoopbackSource::open(device_id) {
+        Ok(source) => Ok(Some(Box::new(source))),
+        Err(e) if device_id.is_some() => Err(e),
+        Err(e) => {
+            tracing::warn!("System audio loopback unavailable: {}", e);
+            Ok(None)
         }
     }
-    tracing::info!("Audio processor thread exiting");
 }
 
-/// WASAPI loopback capture thread (Windows only).
-/// Gets the default Render device, initializes for Capture direction → WASAPI auto-sets loopback.
-#[cfg(windows)]
-fn wasapi_loopback_thread(
-    tx: Sender<Vec<u8>>,
-    is_running: Arc<AtomicBool>,
-) -> Result<()> {
-    use std::collections::VecDeque;
-    use wasapi::*;
+/// Open the platform's system-audio loopback source, if it has one.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn open_system_loopback(device_id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>> {
+    if device_id.is_some() {
+        tracing::warn!("Ignoring loopback device selection on this platform");
+    }
+    tracing::warn!("System audio loopback not implemented on this platform");
+    Ok(None)
+}
+
+/// Rings the processor thread copies each source into, before mixing, while it
+/// is being recorded.
+#[derive(Default)]
+struct RecordingTaps {
+    mic: ArcSwapOption<RingWriter>,
+    loopback: ArcSwapOption<RingWriter>,
+}
+
+impl RecordingTaps {
+    fn tap(&self, role: SourceRole) -> &ArcSwapOption<RingWriter> {
+        match role {
+            SourceRole::Mic => &self.mic,
+            SourceRole::Loopback => &self.loopback,
+        }
+    }
+}
 
-    // initialize_mta returns HRESULT; .ok() converts to WasapiRes
-    initialize_mta()
-        .ok()
-        .map_err(|e| anyhow::anyhow!("COM init failed: {}", e))?;
+/// Start a thread writing what arrives on the returned ring to `recorder`, until
+/// the ring's writers are dropped; then it finalizes the file.
+fn spawn_recorder(
+    mut recorder: WavRecorder,
+    format: SourceFormat,
+) -> Result<(RingWriter, JoinHandle<()>)> {
+    let (tx, mut rx) = sample_ring(
+        format.sample_rate as usize * RECORDING_RING_SECONDS,
+        format.channels,
+    );
+    let mut block = vec![0.0; block_len(format) * 10];
+    let thread = thread::Builder::new()
+        .name("audio-recorder".to_string())
+        .spawn(move || {
+            loop {
+                let n = match rx.recv_timeout(&mut block, Duration::from_secs(1)) {
+                    Ok(n) => n,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Err(e) = recorder.write(&block[..n]) {
+                    tracing::error!("Recording {:?} failed, stopping it: {}", recorder.path(), e);
+                    return;
+                }
+            }
+            if rx.dropped_frames() > 0 {
+                tracing::warn!(
+                    "Recording {:?} is missing {} frames the disk could not keep up with",
+                    recorder.path(),
+                    rx.dropped_frames()
+                );
+            }
+            if let Err(e) = recorder.finish() {
+                tracing::error!("{}", e);
+            }
+        })?;
+    Ok((tx, thread))
+}
 
-    let enumerator = DeviceEnumerator::new()?;
-    let device = enumerator.get_default_device(&Direction::Render)?;
-    let mut audio_client = device.get_iaudioclient()?;
+/// Primary stream handed to the processor thread.
+struct MicInput {
+    rx: RingReader,
+    format: SourceFormat,
+    realtime: bool,
+    gain: f32,
+}
 
-    // Get device's native format
-    let mix_format = device.get_device_format()?;
-    tracing::info!("WASAPI loopback format: {:?}", mix_format);
+/// Loopback stream handed to the processor thread for mixing.
+struct LoopbackInput {
+    rx: RingReader,
+    format: SourceFormat,
+    mix_config: MixConfig,
+}
 
-    let (def_time, _min_time) = audio_client.get_device_period()?;
+/// Where the processor thread sends its results.
+struct ProcessorOutputs {
+    output: Arc<ArcSwapOption<RingWriter>>,
+    stt_tx: Arc<ArcSwapOption<RingWriter>>,
+    stt_loopback_tx: Arc<ArcSwapOption<RingWriter>>,
+    recording: Arc<RecordingTaps>,
+    events_tx: Sender<CaptureEvent>,
+    stt_dropped: Arc<AtomicU64>,
+    output_dropped: Arc<AtomicU64>,
+}
 
-    // EventsShared with Render device + Capture direction → loopback mode
-    let mode = StreamMode::EventsShared {
-        autoconvert: true,
-        buffer_duration_hns: def_time,
+/// Mix loopback into the mic stream, forward the result to the output ring if set, and
+/// optionally fork each source, before mixing, to its STT lane: the echo-cancelled
+/// mic in its native format and the loopback as mono at the mic rate, each with its
+/// gain applied. Source recordings are taken before echo cancellation and gains.
+/// The output, STT and recording rings are loaded from their slots each block, so
+/// set_stt_sender and clear_stt_sender are visible dynamically.
+/// Non-realtime sources wait for an STT sender and are back-pressured by it, so a
+/// fast replay reaches the pipeline in full instead of being dropped.
+/// Per-source levels are metered before mixing and sent every `LEVEL_INTERVAL`.
+/// Realtime audio a consumer cannot take is dropped and counted, never waited for.
+/// Realtime sources and their STT rings are also watched for stalls, drops and
+/// backlog (`audio-health`).
+fn processor_thread(
+    mut mic: MicInput,
+    mut loopback: Option<LoopbackInput>,
+    outputs: ProcessorOutputs,
+    is_running: Arc<AtomicBool>,
+) {
+    let ProcessorOutputs {
+        output,
+        stt_tx,
+        stt_loopback_tx,
+        recording,
+        events_tx,
+        stt_dropped,
+        output_dropped,
+    } = outputs;
+    let channels = mic.format.channels.max(1) as usize;
+    let mut block = vec![0.0; block_len(mic.format)];
+    let mut loopback_block = loopback
+        .as_ref()
+        .map_or_else(Vec::new, |l| vec![0.0; block_len(l.format)]);
+    let mut mixer = loopback.as_ref().and_then(|l| create_mixer(l, mic.format));
+    let mut mic_meter = LevelMeter::new();
+    let mut loopback_meter = loopback.as_ref().map(|_| LevelMeter::new());
+    let mut last_levels = Instant::now();
+    let mut health = mic.realtime.then(|| {
+        let now = Instant::now();
+        let mut sources = vec![SourceMonitor::new(SourceRole::Mic, mic.format, now)];
+        if let Some(ref l) = loopback {
+            let stt_format = SourceFormat {
+                sample_rate: mic.format.sample_rate,
+                channels: 1,
+            };
+            sources.push(
+                SourceMonitor::new(SourceRole::Loopback, l.format, now).with_stt_format(stt_format),
+            );
+        }
+        HealthMonitor::new(sources, now)
+    });
+    let mut last_health = Instant::now();
+    let emit = |event| {
+        let _ = events_tx.try_send(event);
+    };
+    // Realtime audio a ring cannot take is dropped; replays wait for room
+    let forward = |tx: &RingWriter, samples: &[f32]| {
+        if mic.realtime {
+            tx.push(samples)
+        } else {
+            tx.push_blocking(samples, &is_running)
+        }
     };
 
-    audio_client.initialize_client(&mix_format, &Direction::Capture, &mode)?;
+    while is_running.load(Ordering::SeqCst) {
+        if last_levels.elapsed() >= LEVEL_INTERVAL {
+            last_levels = Instant::now();
+            let levels = AudioLevels {
+                mic: Some(mic_meter.take()),
+                loopback: loopback_meter.as_mut().map(LevelMeter::take),
+            };
+            emit(CaptureEvent::Levels(levels));
+        }
+        if let Some(ref mut health) = health {
+            if last_health.elapsed() >= HEALTH_INTERVAL {
+                last_health = Instant::now();
+                let stats = ring_stats(&mic, loopback.as_ref(), &stt_tx, &stt_loopback_tx);
+                health.check(last_health, &stats, emit);
+            }
+        }
 
-    let h_event = audio_client.set_get_eventhandle()?;
-    let capture_client = audio_client.get_audiocaptureclient()?;
-    audio_client.start_stream()?;
+        let stt = stt_tx.load();
+        let stt_loopback = stt_loopback_tx.load();
+        if !mic.realtime && stt.is_none() {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
 
-    tracing::info!("WASAPI loopback stream started");
+        // Queue whatever loopback audio arrived since the last mic block
+        if let Some(ref mut l) = loopback {
+            loop {
+                let n = l.rx.pop(&mut loopback_block);
+                if n == 0 {
+                    break;
+                }
+                let samples = &loopback_block[..n];
+                if let Some(ref mut health) = health {
+                    health.on_buffer(SourceRole::Loopback, Instant::now(), n);
+                }
+                if let Some(ref mut meter) = loopback_meter {
+                    meter.push(samples);
+                }
+                if let Some(ref tx) = *recording.loopback.load() {
+                    forward(tx, samples);
+                }
+                if let Some(ref mut m) = mixer {
+                    m.push_loopback(samples);
+                }
+            }
+        }
 
-    let mut sample_queue: VecDeque<u8> = VecDeque::with_capacity(16384);
+        let n = match mic.rx.recv_timeout(&mut block, Duration::from_millis(50)) {
+            Ok(n) => n,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let samples = &mut block[..n];
+        let frames = (n / channels) as u64;
+        if let Some(ref mut health) = health {
+            health.on_buffer(SourceRole::Mic, Instant::now(), n);
+        }
+        mic_meter.push(samples);
+        if let Some(ref tx) = *recording.mic.load() {
+            forward(tx, samples);
+        }
+        if let Some(ref mut m) = mixer {
+            m.cancel_echo(samples);
+        }
+        apply_gain(samples, mic.gain);
 
-    while is_running.load(Ordering::SeqCst) {
-        // Read captured data into deque
-        match capture_client.read_from_device_to_deque(&mut sample_queue) {
-            Ok(_buffer_info) => {
-                if !sample_queue.is_empty() {
-                    let data: Vec<u8> = sample_queue.drain(..).collect();
-                    if tx.try_send(data).is_err() {
-                        tracing::warn!("Loopback buffer full, dropping frame");
-                    }
+        // Fork: copy each source to its STT lane if connected
+        let mut fork = |role, tx: &RingWriter, lane: &[f32]| {
+            if forward(tx, lane) {
+                if let Some(ref mut health) = health {
+                    health.on_stt(role, frames as usize);
                 }
+            } else if mic.realtime {
+                stt_dropped.fetch_add(frames, Ordering::Relaxed);
             }
-            Err(e) => {
-                tracing::warn!("WASAPI read error: {}", e);
-            }
+        };
+        if let Some(ref tx) = *stt {
+            fork(SourceRole::Mic, tx, samples);
+        }
+        if let (Some(ref tx), Some(m)) = (&*stt_loopback, mixer.as_ref()) {
+            fork(SourceRole::Loopback, tx, m.loopback());
+        }
+        if let Some(ref m) = mixer {
+            m.add_loopback(samples);
         }
 
-        // Wait for next audio event (100ms timeout)
-        let _ = h_event.wait_for_event(100);
+        // The output is a preview for the frontend; never wait for it
+        if let Some(ref tx) = *output.load() {
+            if !tx.push(samples) && mic.realtime {
+                output_dropped.fetch_add(frames, Ordering::Relaxed);
+            }
+        }
     }
+    if let Some(ref mut health) = health {
+        let stats = ring_stats(&mic, loopback.as_ref(), &stt_tx, &stt_loopback_tx);
+        health.finish(Instant::now(), &stats, emit);
+    }
+    tracing::info!("Audio processor thread exiting");
+}
+
+/// Counters of the source rings and their STT rings for a health check, mic first.
+fn ring_stats(
+    mic: &MicInput,
+    loopback: Option<&LoopbackInput>,
+    stt_tx: &ArcSwapOption<RingWriter>,
+    stt_loopback_tx: &ArcSwapOption<RingWriter>,
+) -> [RingStats; 2] {
+    let stats = |rx: &RingReader, stt: &ArcSwapOption<RingWriter>| {
+        let stt = stt.load();
+        RingStats {
+            dropped_frames: rx.dropped_frames(),
+            backlog: rx.len(),
+            stt_dropped_frames: stt.as_ref().map_or(0, |tx| tx.dropped_frames()),
+            stt_backlog: stt.as_ref().map_or(0, |tx| tx.backlog()),
+        }
+    };
+    [
+        stats(&mic.rx, stt_tx),
+        loopback.map_or_else(RingStats::default, |l| stats(&l.rx, stt_loopback_tx)),
+    ]
+}
 
-    audio_client.stop_stream()?;
-    tracing::info!("WASAPI loopback thread exiting");
-    Ok(())
+/// Samples in one processor block of `format`.
+fn block_len(format: SourceFormat) -> usize {
+    let frames = (format.sample_rate as usize / BLOCKS_PER_SECOND).max(1);
+    frames * format.channels.max(1) as usize
+}
+
+/// Build the loopback mixer for the loopback source's native format.
+fn create_mixer(loopback: &LoopbackInput, mic_format: SourceFormat) -> Option<LoopbackMixer> {
+    match LoopbackMixer::new(loopback.mix_config, mic_format, loopback.format) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            tracing::error!("Failed to create loopback mixer: {}", e);
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::file_source::FileSource;
 
     #[test]
     fn creates_manager() {
@@ -294,6 +725,22 @@ mod tests {
         assert!(!manager.is_running.load(Ordering::SeqCst));
     }
 
+    #[test]
+    fn clearing_a_replaced_stt_sender_keeps_the_new_one() {
+        let manager = AudioCaptureManager::new();
+        let (calibration_tx, calibration_rx) = sample_ring(16, 1);
+        let (meeting_tx, meeting_rx) = sample_ring(16, 1);
+        manager.set_stt_sender(calibration_tx);
+        manager.set_stt_sender(meeting_tx);
+
+        manager.clear_stt_sender_for(&calibration_rx);
+        let kept = manager.stt_tx.load();
+        assert!(kept.as_ref().is_some_and(|tx| tx.feeds(&meeting_rx)));
+
+        manager.clear_stt_sender_for(&meeting_rx);
+        assert!(manager.stt_tx.load().is_none());
+    }
+
     #[test]
     fn stop_sets_flag() {
         let mut manager = AudioCaptureManager::new();
@@ -301,4 +748,114 @@ mod tests {
         manager.stop();
         assert!(!manager.is_running.load(Ordering::SeqCst));
     }
+
+    #[test]
+    fn file_replay_reaches_stt_in_full() {
+        let path = std::env::temp_dir().join(format!("rt-capture-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..48000 {
+            writer.write_sample((i % 100) as f32 / 100.0).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let source = FileSource::open(&path, false).unwrap();
+        let mut manager = AudioCaptureManager::new();
+        manager.start_with_sources(Box::new(source), None).unwrap();
+        assert_eq!(manager.mic_format(), (48000, 2));
+        assert!(manager.devices().mic.as_deref().unwrap().starts_with("file:"));
+
+        // Attaching STT late must not lose the start of the file
+        thread::sleep(Duration::from_millis(50));
+        let (stt_tx, mut stt_rx) = sample_ring(960, 2);
+        manager.set_stt_sender(stt_tx);
+
+        let mut received = Vec::new();
+        let mut chunk = [0.0; 512];
+        while let Ok(n) = stt_rx.recv_timeout(&mut chunk, Duration::from_secs(1)) {
+            received.extend_from_slice(&chunk[..n]);
+        }
+        assert_eq!(manager.dropped_frames(), DroppedFrames::default());
+        manager.stop();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(received.len(), 48000);
+        assert_eq!(received[1], 0.01);
+    }
+
+    #[test]
+    fn records_sources_on_their_own_thread() {
+        let dir = std::env::temp_dir().join(format!("rt-capture-rec-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("input.wav");
+        std::fs::create_dir_all(&dir).unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..32000 {
+            writer.write_sample((i % 1000) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut manager = AudioCaptureManager::new();
+        let source = FileSource::open(&path, false).unwrap();
+        manager.start_with_sources(Box::new(source), None).unwrap();
+        manager.start_source_recording(&dir, 3).unwrap();
+        let (stt_tx, mut stt_rx) = sample_ring(1600, 1);
+        manager.set_stt_sender(stt_tx);
+
+        // Read until the replay has ended
+        let mut chunk = [0.0; 512];
+        let wait = Duration::from_secs(1);
+        while stt_rx.recv_timeout(&mut chunk, wait).is_ok() {}
+        manager.stop();
+
+        let recorded = meeting_recording_path(&dir, 3, Some("mic"));
+        let samples = crate::audio::file_source::read_wav_samples(&recorded).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+        assert_eq!(samples.len(), 32000);
+    }
+
+    #[test]
+    fn emits_levels_for_active_sources() {
+        let path = std::env::temp_dir().join(format!("rt-levels-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..16000 {
+            writer.write_sample(if i % 2 == 0 { 16384i16 } else { -16384 }).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut manager = AudioCaptureManager::new();
+        let events = manager.events();
+        let source = FileSource::open(&path, true).unwrap();
+        manager.start_with_sources(Box::new(source), None).unwrap();
+
+        // Skip windows metered before the first buffer arrived
+        let level = (0..20)
+            .filter_map(|_| match events.recv_timeout(Duration::from_secs(1)) {
+                Ok(CaptureEvent::Levels(levels)) => levels.mic,
+                _ => None,
+            })
+            .find(|l| l.peak > 0.0)
+            .unwrap();
+        manager.stop();
+        let _ = std::fs::remove_file(path);
+
+        assert!((level.rms - 0.5).abs() < 0.01);
+        assert!(!level.clipping);
+    }
 }
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Input channels to capture, numbered from 1 as on audio interface labels.
+/// Empty keeps every channel; `[3]` takes only the third input.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ChannelMap(pub Vec<u16>);
+
+impl ChannelMap {
+    pub fn is_all(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Fails if any selected channel does not exist in a `channels`-wide stream.
+    pub fn validate(&self, channels: u16) -> Result<()> {
+        match self.0.iter().find(|&&c| c == 0 || c > channels) {
+            Some(c) => anyhow::bail!(
+                "Channel {} out of range: device has {} channels",
+                c,
+                channels
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Channel count after mapping a `channels`-wide stream.
+    pub fn output_channels(&self, channels: u16) -> u16 {
+        if self.is_all() {
+            channels
+        } else {
+            self.0.len() as u16
+        }
+    }
+
+    /// Zero-based index within a `channels`-wide input frame of each output channel.
+    pub fn source_indices(&self, channels: u16) -> Vec<usize> {
+        if self.is_all() {
+            (0..channels.max(1) as usize).collect()
+        } else {
+            self.0.iter().map(|&c| c as usize - 1).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_single_channel_of_interface() {
+        let map = ChannelMap(vec![3]);
+        map.validate(4).unwrap();
+        assert_eq!(map.output_channels(4), 1);
+        assert_eq!(map.source_indices(4), vec![2]);
+        assert_eq!(ChannelMap(vec![4, 1]).source_indices(4), vec![3, 0]);
+        assert_eq!(ChannelMap::default().source_indices(2), vec![0, 1]);
+    }
+
+    #[test]
+    fn rejects_missing_channels() {
+        assert!(ChannelMap(vec![3]).validate(2).is_err());
+        assert!(ChannelMap(vec![0]).validate(2).is_err());
+        assert!(ChannelMap::default().validate(1).is_ok());
+    }
+}
@@ -1,9 +1,14 @@
 #![allow(deprecated)] // cpal 0.17 deprecates name() in favor of description()
 
+#[cfg(target_os = "linux")]
+use crate::audio::pulse_loopback::{self, MonitorSource};
 use crate::audio::types::DeviceInfo;
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait};
 
+/// Prefix marking a `DeviceInfo.id` as a loopback (system audio) device.
+pub const LOOPBACK_ID_PREFIX: &str = "loopback:";
+
 /// List all available audio input devices and loopback-capable output devices.
 pub fn list_devices() -> Result<Vec<DeviceInfo>> {
     let mut devices = Vec::new();
@@ -40,7 +45,7 @@ pub fn list_devices() -> Result<Vec<DeviceInfo>> {
             };
 
             devices.push(DeviceInfo {
-                id: format!("loopback:{}", name),
+                id: format!("{}{}", LOOPBACK_ID_PREFIX, name),
                 name: format!("{} (Loopback)", name),
                 is_input: false,
                 is_loopback: true,
@@ -50,6 +55,24 @@ pub fn list_devices() -> Result<Vec<DeviceInfo>> {
         }
     }
 
+    // Sink monitor sources (for loopback capture on Linux)
+    #[cfg(target_os = "linux")]
+    match pulse_loopback::list_monitor_sources() {
+        Ok(sources) => {
+            for source in sources {
+                devices.push(DeviceInfo {
+                    id: format!("{}{}", LOOPBACK_ID_PREFIX, source.name),
+                    name: format!("{} (Loopback)", source.name),
+                    is_input: false,
+                    is_loopback: true,
+                    sample_rate: source.format.sample_rate,
+                    channels: source.format.channels,
+                });
+            }
+        }
+        Err(e) => tracing::debug!("PulseAudio monitor sources unavailable: {}", e),
+    }
+
     Ok(devices)
 }
 
@@ -60,6 +83,63 @@ pub fn get_default_input_device() -> Result<cpal::Device> {
         .ok_or_else(|| anyhow::anyhow!("No default input device found"))
 }
 
+/// Find an input device by the `DeviceInfo.id` returned from `list_devices`.
+pub fn find_input_device(id: &str) -> Result<cpal::Device> {
+    if id.starts_with(LOOPBACK_ID_PREFIX) {
+        anyhow::bail!("'{}' is a loopback device, not a microphone", id);
+    }
+    let host = cpal::default_host();
+    host.input_devices()?
+        .find(|d| d.name().map(|n| n == id).unwrap_or(false))
+        .ok_or_else(|| {
+            anyhow::anyhow!("Input device '{}' not found (unplugged or renamed?)", id)
+        })
+}
+
+/// Resolve the mic device: the requested ID, or the OS default when `None`.
+pub fn resolve_input_device(id: Option<&str>) -> Result<cpal::Device> {
+    match id {
+        Some(id) => find_input_device(id),
+        None => get_default_input_device(),
+    }
+}
+
+/// Strip the loopback prefix from a `DeviceInfo.id`, yielding the output device name.
+pub fn loopback_device_name(id: &str) -> Result<&str> {
+    id.strip_prefix(LOOPBACK_ID_PREFIX)
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not a loopback device ID", id))
+}
+
+/// Resolve the loopback output device: the requested ID, or the OS default render device.
+/// Fails if the requested device is no longer present.
+#[cfg(windows)]
+pub fn resolve_loopback_device(id: Option<&str>) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    match id {
+        Some(id) => {
+            let name = loopback_device_name(id)?;
+            host.output_devices()?
+                .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Loopback device '{}' not found (unplugged or renamed?)", name)
+                })
+        }
+        None => host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No default output device found")),
+    }
+}
+
+/// Resolve the loopback monitor source: the requested ID, or the default sink's monitor.
+/// Fails if the requested sink is no longer present.
+#[cfg(target_os = "linux")]
+pub fn resolve_loopback_source(id: Option<&str>) -> Result<MonitorSource> {
+    match id {
+        Some(id) => pulse_loopback::find_monitor_source(loopback_device_name(id)?),
+        None => pulse_loopback::default_monitor_source(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +149,22 @@ mod tests {
         let result = list_devices();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn missing_input_device_is_an_error() {
+        let err = find_input_device("no-such-device-xyz").err().unwrap();
+        assert!(err.to_string().contains("not found"), "got: {}", err);
+    }
+
+    #[test]
+    fn loopback_id_is_not_an_input_device() {
+        let err = find_input_device("loopback:Speakers").err().unwrap();
+        assert!(err.to_string().contains("loopback"), "got: {}", err);
+    }
+
+    #[test]
+    fn loopback_name_strips_prefix() {
+        assert_eq!(loopback_device_name("loopback:Speakers").unwrap(), "Speakers");
+        assert!(loopback_device_name("Microphone").is_err());
+    }
 }
@@ -0,0 +1,91 @@
+/// Blocks quieter than this RMS are treated as silence and leave the gain untouched,
+/// so pauses are not pumped up into audible noise.
+const GATE_RMS: f32 = 0.003;
+/// Per-block smoothing toward the desired gain: fast when reducing, slow when boosting.
+const ATTACK: f32 = 0.5;
+const RELEASE: f32 = 0.05;
+
+/// Automatic gain control bringing speech toward a target RMS level.
+pub struct Agc {
+    target_rms: f32,
+    max_gain: f32,
+    gain: f32,
+}
+
+impl Agc {
+    /// `target_rms` is the level speech is pulled toward (0.1 ≈ -20 dBFS);
+    /// boost is limited to `max_gain_db`.
+    pub fn new(target_rms: f32, max_gain_db: f32) -> Self {
+        Self {
+            target_rms,
+            max_gain: 10f32.powf(max_gain_db / 20.0),
+            gain: 1.0,
+        }
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Apply gain in place to one block (e.g. a 10ms VAD frame), ramping from the
+    /// previous gain to avoid zipper noise, and hard-limiting to full scale.
+    pub fn process(&mut self, block: &mut [f32]) {
+        if block.is_empty() {
+            return;
+        }
+        let rms = (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt();
+        let previous = self.gain;
+
+        if rms > GATE_RMS {
+            let desired = (self.target_rms / rms).min(self.max_gain);
+            let rate = if desired < self.gain { ATTACK } else { RELEASE };
+            self.gain += (desired - self.gain) * rate;
+        }
+
+        let step = (self.gain - previous) / block.len() as f32;
+        for (i, s) in block.iter_mut().enumerate() {
+            let g = previous + step * (i + 1) as f32;
+            *s = (*s * g).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::dsp::test_signals::{rms, sine};
+
+    fn run(agc: &mut Agc, signal: &mut [f32]) {
+        for block in signal.chunks_mut(160) {
+            agc.process(block);
+        }
+    }
+
+    #[test]
+    fn boosts_quiet_speech_toward_target() {
+        let mut agc = Agc::new(0.1, 30.0);
+        let mut quiet = sine(300.0, 0.02, 16000 * 2);
+        run(&mut agc, &mut quiet);
+        assert!((rms(&quiet[24000..]) - 0.1).abs() < 0.02);
+    }
+
+    #[test]
+    fn tames_loud_speech_quickly() {
+        let mut agc = Agc::new(0.1, 30.0);
+        let mut loud = sine(300.0, 0.9, 3200);
+        run(&mut agc, &mut loud);
+        assert!((rms(&loud[1600..]) - 0.1).abs() < 0.02);
+    }
+
+    #[test]
+    fn does_not_boost_silence_or_exceed_max_gain() {
+        let mut agc = Agc::new(0.1, 12.0);
+        let mut hiss = sine(300.0, 0.001, 16000);
+        run(&mut agc, &mut hiss);
+        assert_eq!(agc.gain(), 1.0);
+
+        let mut quiet = sine(300.0, 0.005, 16000 * 2);
+        run(&mut agc, &mut quiet);
+        assert!(agc.gain() <= 10f32.powf(12.0 / 20.0) + 1e-3);
+    }
+}
@@ -0,0 +1,275 @@
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Adaptation step of the normalized frequency-domain LMS update.
+const STEP: f32 = 0.5;
+/// Smoothing of the per-bin far-end power used to normalize the step.
+const POWER_SMOOTHING: f32 = 0.9;
+/// Far-end blocks quieter than this (RMS, ~-70 dBFS) carry no echo worth learning.
+const FAR_ACTIVE_RMS: f32 = 3e-4;
+/// Regularization of the step normalization, as a per-sample power.
+const REGULARIZATION: f32 = 1e-6;
+/// Jump of the residual-to-far-end ratio over its running level taken as near-end
+/// talk (~6 dB); adaptation freezes meanwhile.
+const DOUBLE_TALK_RATIO: f32 = 4.0;
+/// Smoothing of the running residual-to-far-end ratio.
+const RESIDUAL_SMOOTHING: f32 = 0.95;
+/// Per-block rise of the running ratio while frozen, so a changed echo path
+/// (which also raises the residual) is relearned after a few seconds.
+const RESIDUAL_RISE: f32 = 1.03;
+
+/// Acoustic echo canceller: learns the path from the far-end reference (what the
+/// speakers play) to the mic with a partitioned-block frequency-domain adaptive
+/// filter and subtracts the predicted echo from the mic. Each partition covers one
+/// block, so the filter reaches `tail_ms` past the reference. Adaptation pauses
+/// while the residual jumps relative to the far end (near-end talk), so local
+/// speech does not unlearn the echo path. Streams with a fixed latency of `latency()`.
+pub struct EchoCanceller {
+    block: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    ifft_scratch: Vec<Complex<f32>>,
+    near: Vec<f32>,
+    far: Vec<f32>,
+    far_frame: Vec<f32>,
+    /// Far-end spectra of the last `partitions` blocks, newest at `newest`.
+    far_spectra: Vec<Vec<Complex<f32>>>,
+    newest: usize,
+    weights: Vec<Vec<Complex<f32>>>,
+    power: Vec<f32>,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    output: VecDeque<f32>,
+    /// Partition whose weights are constrained to a causal block next.
+    constrain: usize,
+    /// Running residual-to-far-end power ratio; starts as if nothing is cancelled.
+    residual: f32,
+}
+
+impl EchoCanceller {
+    pub fn new(sample_rate: u32, tail_ms: u32) -> Self {
+        // ~10ms blocks, rounded up to a power of two for the FFT
+        let block = (sample_rate as usize / 100).max(16).next_power_of_two();
+        let tail = sample_rate as usize * tail_ms as usize / 1000;
+        let partitions = tail.div_ceil(block).max(1);
+        let bins = block + 1;
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(2 * block);
+        let ifft = planner.plan_fft_inverse(2 * block);
+
+        Self {
+            block,
+            fft_scratch: fft.make_scratch_vec(),
+            ifft_scratch: ifft.make_scratch_vec(),
+            fft,
+            ifft,
+            near: Vec::with_capacity(block),
+            far: Vec::with_capacity(block),
+            far_frame: vec![0.0; 2 * block],
+            far_spectra: vec![vec![Complex::default(); bins]; partitions],
+            newest: 0,
+            weights: vec![vec![Complex::default(); bins]; partitions],
+            power: vec![0.0; bins],
+            time: vec![0.0; 2 * block],
+            spectrum: vec![Complex::default(); bins],
+            output: std::iter::repeat_n(0.0, block).collect(),
+            constrain: 0,
+            residual: 1.0,
+        }
+    }
+
+    /// Delay between input and output, in samples.
+    pub fn latency(&self) -> usize {
+        self.block
+    }
+
+    /// Remove the echo of `far` from `near` in place; both are mono and in step, one
+    /// far-end sample per mic sample. Output is delayed by `latency()` samples.
+    pub fn process(&mut self, near: &mut [f32], far: &[f32]) {
+        for (s, &f) in near.iter_mut().zip(far) {
+            self.near.push(*s);
+            self.far.push(f);
+            if self.near.len() == self.block {
+                self.process_block();
+                self.near.clear();
+                self.far.clear();
+            }
+            *s = self.output.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    fn process_block(&mut self) {
+        let b = self.block;
+        let partitions = self.weights.len();
+
+        // Far-end spectrum of the last two blocks (overlap-save)
+        self.far_frame.copy_within(b.., 0);
+        self.far_frame[b..].copy_from_slice(&self.far);
+        self.newest = (self.newest + partitions - 1) % partitions;
+        self.time.copy_from_slice(&self.far_frame);
+        let x = &mut self.far_spectra[self.newest];
+        let _ = self
+            .fft
+            .process_with_scratch(&mut self.time, x, &mut self.fft_scratch);
+
+        // Predicted echo: sum of every partition's filter applied to its block
+        self.spectrum.fill(Complex::default());
+        for (p, w) in self.weights.iter().enumerate() {
+            let x = &self.far_spectra[(self.newest + p) % partitions];
+            for ((y, w), x) in self.spectrum.iter_mut().zip(w).zip(x) {
+                *y += w * x;
+            }
+        }
+        self.inverse();
+        let scale = 1.0 / (2 * b) as f32;
+        for (i, d) in self.near.iter().enumerate() {
+            let e = d - self.time[b + i] * scale;
+            self.output.push_back(e);
+            self.time[b + i] = e;
+        }
+
+        let far_power = self.far.iter().map(|f| f * f).sum::<f32>() / b as f32;
+        if far_power < FAR_ACTIVE_RMS * FAR_ACTIVE_RMS {
+            return;
+        }
+        let error_power = self.time[b..].iter().map(|e| e * e).sum::<f32>() / b as f32;
+        let ratio = error_power / far_power;
+        if ratio > DOUBLE_TALK_RATIO * self.residual {
+            self.residual *= RESIDUAL_RISE;
+            return;
+        }
+        self.residual = RESIDUAL_SMOOTHING * self.residual + (1.0 - RESIDUAL_SMOOTHING) * ratio;
+        // Every partition moves the prediction, so they share the step
+        let step = STEP / partitions as f32;
+
+        // Error spectrum, zero-padded in front like the overlap-save output
+        self.time[..b].fill(0.0);
+        let _ = self.fft.process_with_scratch(
+            &mut self.time,
+            &mut self.spectrum,
+            &mut self.fft_scratch,
+        );
+        let regularization = REGULARIZATION * (2 * b) as f32;
+        for (x, p) in self.far_spectra[self.newest].iter().zip(&mut self.power) {
+            *p = POWER_SMOOTHING * *p + (1.0 - POWER_SMOOTHING) * x.norm_sqr();
+        }
+        for (p, w) in self.weights.iter_mut().enumerate() {
+            let x = &self.far_spectra[(self.newest + p) % partitions];
+            for (((w, x), e), power) in w.iter_mut().zip(x).zip(&self.spectrum).zip(&self.power) {
+                *w += x.conj() * e * (step / (power + regularization));
+            }
+        }
+        self.constrain_partition();
+    }
+
+    /// Keep one partition's impulse response to a single block, rotating through
+    /// partitions; an unconstrained update would wrap echo into the wrong block.
+    fn constrain_partition(&mut self) {
+        let b = self.block;
+        let p = self.constrain;
+        self.constrain = (p + 1) % self.weights.len();
+        self.spectrum.copy_from_slice(&self.weights[p]);
+        self.inverse();
+        let scale = 1.0 / (2 * b) as f32;
+        for s in self.time[..b].iter_mut() {
+            *s *= scale;
+        }
+        self.time[b..].fill(0.0);
+        let _ = self.fft.process_with_scratch(
+            &mut self.time,
+            &mut self.weights[p],
+            &mut self.fft_scratch,
+        );
+    }
+
+    /// Inverse transform of `spectrum` into `time` (unscaled).
+    fn inverse(&mut self) {
+        // DC and Nyquist bins must be real for the inverse transform
+        self.spectrum[0].im = 0.0;
+        self.spectrum[self.block].im = 0.0;
+        let _ = self.ifft.process_with_scratch(
+            &mut self.spectrum,
+            &mut self.time,
+            &mut self.ifft_scratch,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::dsp::test_signals::{rms, sine, white_noise};
+
+    /// Mic pickup of `far` through a room: direct path after `delay` plus two reflections.
+    fn echo(far: &[f32], delay: usize) -> Vec<f32> {
+        let taps = [(delay, 0.6), (delay + 80, 0.25), (delay + 400, -0.1)];
+        (0..far.len())
+            .map(|i| {
+                taps.iter()
+                    .filter(|(d, _)| i >= *d)
+                    .map(|(d, g)| g * far[i - d])
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cancels_delayed_echo() {
+        for delay in [160, 1600] {
+            let far = white_noise(0.2, 16000 * 4, 7);
+            let mut mic = echo(&far, delay);
+            let before = rms(&mic[48000..]);
+
+            let mut aec = EchoCanceller::new(16000, 200);
+            aec.process(&mut mic, &far);
+            // At least 20 dB of echo removed once converged
+            let after = rms(&mic[48000..]);
+            assert!(
+                after < before * 0.1,
+                "delay {}: {} -> {}",
+                delay,
+                before,
+                after
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_near_end_speech_during_double_talk() {
+        // 3s of echo to converge, then 1s of local tone over the echo
+        let far = white_noise(0.2, 16000 * 4, 8);
+        let mut mic = echo(&far, 800);
+        let tone = sine(300.0, 0.1, 16000);
+        for (s, t) in mic[48000..].iter_mut().zip(&tone) {
+            *s += t;
+        }
+
+        let mut aec = EchoCanceller::new(16000, 200);
+        let latency = aec.latency();
+        for (near, far) in mic.chunks_mut(160).zip(far.chunks(160)) {
+            aec.process(near, far);
+        }
+        let out = &mic[48000 + latency + 1600..64000];
+        let residual: Vec<f32> = out
+            .iter()
+            .zip(&tone[1600..])
+            .map(|(o, t)| o - t)
+            .collect();
+        assert!(rms(&residual) < rms(&tone) * 0.2);
+    }
+
+    #[test]
+    fn passes_mic_through_without_far_end() {
+        let input = sine(440.0, 0.3, 4000);
+        let mut mic = input.clone();
+        let mut aec = EchoCanceller::new(16000, 200);
+        aec.process(&mut mic, &vec![0.0; 4000]);
+        let latency = aec.latency();
+        for i in latency..4000 {
+            assert!((mic[i] - input[i - latency]).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::f32::consts::PI;
+
+/// Second-order Butterworth high-pass (RBJ biquad). Removes DC offset and
+/// low-frequency rumble (desk bumps, HVAC) below `cutoff_hz`.
+pub struct HighPass {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl HighPass {
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos) / 2.0 / a0,
+            b1: -(1.0 + cos) / a0,
+            b2: (1.0 + cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Filter samples in place (transposed direct form II).
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for s in samples.iter_mut() {
+            let x = *s;
+            let y = self.b0 * x + self.z1;
+            self.z1 = self.b1 * x - self.a1 * y + self.z2;
+            self.z2 = self.b2 * x - self.a2 * y;
+            *s = y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::dsp::test_signals::{rms, sine};
+
+    #[test]
+    fn removes_dc_offset() {
+        let mut hp = HighPass::new(80.0, 16000);
+        let mut signal = vec![0.3f32; 16000];
+        hp.process(&mut signal);
+        assert!(rms(&signal[8000..]) < 1e-3);
+    }
+
+    #[test]
+    fn passes_speech_band_and_cuts_rumble() {
+        let mut hp = HighPass::new(80.0, 16000);
+        let mut voice = sine(1000.0, 0.5, 16000);
+        hp.process(&mut voice);
+        assert!((rms(&voice[8000..]) - rms(&sine(1000.0, 0.5, 8000))).abs() < 0.01);
+
+        let mut hp = HighPass::new(80.0, 16000);
+        let mut rumble = sine(20.0, 0.5, 16000);
+        hp.process(&mut rumble);
+        // 2 octaves below cutoff at 12 dB/octave: ~-24 dB
+        assert!(rms(&rumble[8000..]) < 0.1 * rms(&sine(20.0, 0.5, 8000)));
+    }
+}
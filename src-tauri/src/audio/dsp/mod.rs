@@ -0,0 +1,155 @@
+mod agc;
+mod echo_canceller;
+mod high_pass;
+mod noise_suppressor;
+
+pub use agc::Agc;
+pub use echo_canceller::EchoCanceller;
+pub use high_pass::HighPass;
+pub use noise_suppressor::NoiseSuppressor;
+
+use serde::{Deserialize, Serialize};
+
+/// Pre-processing applied to 16kHz mono audio between the resampler and the VAD.
+/// Stages run in order: high-pass → noise suppression → AGC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspConfig {
+    /// Remove DC offset and rumble below `high_pass_hz`.
+    pub high_pass: bool,
+    pub high_pass_hz: f32,
+    /// Spectral suppression of stationary noise (fans, hum).
+    pub noise_suppression: bool,
+    /// Max attenuation of a noisy frequency bin.
+    pub noise_reduction_db: f32,
+    /// Pull speech toward `agc_target_rms`. Off by default: it also raises the floor the VAD sees.
+    pub agc: bool,
+    pub agc_target_rms: f32,
+    pub agc_max_gain_db: f32,
+}
+
+impl Default for DspConfig {
+    fn default() -> Self {
+        Self {
+            high_pass: true,
+            high_pass_hz: 80.0,
+            noise_suppression: true,
+            noise_reduction_db: 20.0,
+            agc: false,
+            agc_target_rms: 0.1,
+            agc_max_gain_db: 24.0,
+        }
+    }
+}
+
+/// The enabled stages of a `DspConfig`, with their streaming state.
+pub struct DspChain {
+    high_pass: Option<HighPass>,
+    noise: Option<NoiseSuppressor>,
+    agc: Option<Agc>,
+}
+
+impl DspChain {
+    pub fn new(config: &DspConfig, sample_rate: u32) -> Self {
+        Self {
+            high_pass: config
+                .high_pass
+                .then(|| HighPass::new(config.high_pass_hz, sample_rate)),
+            noise: config
+                .noise_suppression
+                .then(|| NoiseSuppressor::new(config.noise_reduction_db)),
+            agc: config
+                .agc
+                .then(|| Agc::new(config.agc_target_rms, config.agc_max_gain_db)),
+        }
+    }
+
+    /// Delay the chain adds to the signal, in samples.
+    pub fn latency(&self) -> usize {
+        self.noise.as_ref().map_or(0, NoiseSuppressor::latency)
+    }
+
+    /// Process one block in place.
+    pub fn process(&mut self, block: &mut [f32]) {
+        if let Some(ref mut hp) = self.high_pass {
+            hp.process(block);
+        }
+        if let Some(ref mut ns) = self.noise {
+            ns.process(block);
+        }
+        if let Some(ref mut agc) = self.agc {
+            agc.process(block);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_signals {
+    /// `len` samples of a sine at 16kHz.
+    pub fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    /// Deterministic uniform white noise in [-amplitude, amplitude].
+    pub fn white_noise(amplitude: f32, len: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                amplitude * ((state >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0)
+            })
+            .collect()
+    }
+
+    pub fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_signals::{rms, sine, white_noise};
+    use super::*;
+
+    #[test]
+    fn disabled_chain_is_passthrough() {
+        let config = DspConfig {
+            high_pass: false,
+            noise_suppression: false,
+            agc: false,
+            ..DspConfig::default()
+        };
+        let mut chain = DspChain::new(&config, 16000);
+        let input = sine(440.0, 0.3, 1600);
+        let mut block = input.clone();
+        chain.process(&mut block);
+        assert_eq!(block, input);
+        assert_eq!(chain.latency(), 0);
+    }
+
+    #[test]
+    fn default_chain_strips_dc_without_losing_speech() {
+        // 0.5s of quiet room noise, then a 300ms tone burst, all with a DC offset
+        let mut signal = white_noise(0.005, 8000, 3);
+        let tone = sine(1000.0, 0.3, 4800);
+        signal.extend_from_slice(&tone);
+        signal.extend(std::iter::repeat_n(0.0, 1600));
+        for s in signal.iter_mut() {
+            *s += 0.2;
+        }
+
+        let mut chain = DspChain::new(&DspConfig::default(), 16000);
+        for frame in signal.chunks_mut(160) {
+            chain.process(frame);
+        }
+        let start = 8000 + chain.latency();
+        let burst = &signal[start + 800..start + 4000];
+        let mean = burst.iter().sum::<f32>() / burst.len() as f32;
+        assert!(mean.abs() < 0.01);
+        assert!(rms(burst) > 0.8 * rms(&tone));
+    }
+}
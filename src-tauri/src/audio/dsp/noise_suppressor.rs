@@ -0,0 +1,200 @@
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// STFT frame (32ms at 16kHz) and hop (50% overlap).
+const FRAME: usize = 512;
+const HOP: usize = FRAME / 2;
+/// Temporal smoothing of the power spectrum before noise tracking.
+const POWER_SMOOTHING: f32 = 0.7;
+/// Per-frame rise of the noise floor estimate (~2.5 dB/s), so speech bursts are not learned.
+const NOISE_RISE: f32 = 1.01;
+/// Over-subtraction factor compensating for minimum tracking underestimating the mean.
+const OVER_SUBTRACTION: f32 = 4.0;
+/// Gain smoothing across frames to limit musical noise.
+const GAIN_SMOOTHING: f32 = 0.5;
+
+/// Spectral-subtraction noise suppressor for stationary noise (fans, hum, hiss).
+/// Tracks the per-bin noise floor by minimum statistics and applies a floored
+/// Wiener-style gain. Streams with a fixed latency of `latency()` samples.
+pub struct NoiseSuppressor {
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    ifft_scratch: Vec<Complex<f32>>,
+    window: Vec<f32>,
+    /// Windowed frame, then the inverse transform of the filtered spectrum.
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    history: Vec<f32>,
+    fresh: Vec<f32>,
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+    power: Vec<f32>,
+    noise: Vec<f32>,
+    gain: Vec<f32>,
+    floor: f32,
+    primed: bool,
+}
+
+impl NoiseSuppressor {
+    /// `max_reduction_db` bounds attenuation per bin (e.g. 20 dB → gain floor 0.1).
+    pub fn new(max_reduction_db: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let bins = FRAME / 2 + 1;
+        // sqrt-Hann analysis + synthesis windows sum to 1 at 50% overlap
+        let window = (0..FRAME)
+            .map(|i| {
+                let hann = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME as f32).cos();
+                hann.sqrt()
+            })
+            .collect();
+
+        // Latency padding, with room for the hop each frame adds
+        let mut output = VecDeque::with_capacity(2 * HOP);
+        output.extend(std::iter::repeat_n(0.0, HOP));
+        let fft = planner.plan_fft_forward(FRAME);
+        let ifft = planner.plan_fft_inverse(FRAME);
+
+        Self {
+            fft_scratch: fft.make_scratch_vec(),
+            ifft_scratch: ifft.make_scratch_vec(),
+            fft,
+            ifft,
+            window,
+            time: vec![0.0; FRAME],
+            spectrum: vec![Complex::default(); bins],
+            history: vec![0.0; FRAME],
+            fresh: Vec::with_capacity(HOP),
+            overlap: vec![0.0; FRAME],
+            output,
+            power: vec![0.0; bins],
+            noise: vec![0.0; bins],
+            gain: vec![1.0; bins],
+            floor: 10f32.powf(-max_reduction_db.abs() / 20.0),
+            primed: false,
+        }
+    }
+
+    /// Delay between input and output, in samples.
+    pub fn latency(&self) -> usize {
+        FRAME
+    }
+
+    /// Suppress noise in place; output is delayed by `latency()` samples.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for s in samples.iter_mut() {
+            self.fresh.push(*s);
+            if self.fresh.len() == HOP {
+                self.history.copy_within(HOP.., 0);
+                self.history[FRAME - HOP..].copy_from_slice(&self.fresh);
+                self.fresh.clear();
+                self.process_frame();
+            }
+            *s = self.output.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    fn process_frame(&mut self) {
+        for ((t, x), w) in self.time.iter_mut().zip(&self.history).zip(&self.window) {
+            *t = x * w;
+        }
+        let spectrum = &mut self.spectrum;
+        if self
+            .fft
+            .process_with_scratch(&mut self.time, spectrum, &mut self.fft_scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        for (k, bin) in spectrum.iter_mut().enumerate() {
+            let p = bin.norm_sqr();
+            self.power[k] = if self.primed {
+                POWER_SMOOTHING * self.power[k] + (1.0 - POWER_SMOOTHING) * p
+            } else {
+                p
+            };
+            // Minimum tracking: follow drops immediately, rise slowly
+            self.noise[k] = if !self.primed || self.power[k] < self.noise[k] {
+                self.power[k]
+            } else {
+                self.noise[k] * NOISE_RISE
+            };
+
+            let target = if p > 0.0 {
+                (1.0 - OVER_SUBTRACTION * self.noise[k] / p).max(self.floor)
+            } else {
+                self.floor
+            };
+            self.gain[k] = GAIN_SMOOTHING * self.gain[k] + (1.0 - GAIN_SMOOTHING) * target;
+            *bin *= Complex::new(self.gain[k], 0.0);
+        }
+        self.primed = true;
+
+        // DC and Nyquist bins must be real for the inverse transform
+        spectrum[0].im = 0.0;
+        spectrum[FRAME / 2].im = 0.0;
+        if self
+            .ifft
+            .process_with_scratch(spectrum, &mut self.time, &mut self.ifft_scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        let scale = 1.0 / FRAME as f32;
+        for ((acc, x), w) in self.overlap.iter_mut().zip(&self.time).zip(&self.window) {
+            *acc += x * w * scale;
+        }
+        self.output.extend(&self.overlap[..HOP]);
+        self.overlap.copy_within(HOP.., 0);
+        self.overlap[FRAME - HOP..].fill(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::dsp::test_signals::{rms, sine, white_noise};
+
+    #[test]
+    fn attenuates_stationary_noise() {
+        let mut ns = NoiseSuppressor::new(20.0);
+        let mut noise = white_noise(0.05, 16000 * 3, 1);
+        let before = rms(&noise[32000..]);
+        ns.process(&mut noise);
+        // At least 10 dB quieter once the floor is learned
+        assert!(rms(&noise[32000..]) < before * 0.32);
+    }
+
+    #[test]
+    fn keeps_speech_bursts_over_noise() {
+        // 2s of noise to learn the floor, then a 300ms tone burst on top
+        let mut signal = white_noise(0.01, 16000 * 3, 2);
+        let burst = sine(440.0, 0.3, 4800);
+        for (s, b) in signal[32000..36800].iter_mut().zip(&burst) {
+            *s += b;
+        }
+
+        let mut ns = NoiseSuppressor::new(20.0);
+        let latency = ns.latency();
+        ns.process(&mut signal);
+        let out = &signal[32000 + latency + 800..36800 + latency - 800];
+        assert!(rms(out) > 0.8 * rms(&burst));
+    }
+
+    #[test]
+    fn reconstructs_signal_when_nothing_to_remove() {
+        // Onset of a tone: gains are still ~1, so output is the delayed input
+        let input = sine(1000.0, 0.5, 2048);
+        let mut out = input.clone();
+        let mut ns = NoiseSuppressor::new(0.0);
+        ns.process(&mut out);
+        let latency = ns.latency();
+        for i in latency..2048 {
+            assert!((out[i] - input[i - latency]).abs() < 1e-3);
+        }
+    }
+}
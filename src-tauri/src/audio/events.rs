@@ -0,0 +1,51 @@
+use crate::audio::health::{AudioHealth, HealthIncident};
+use crate::audio::levels::AudioLevels;
+use crate::audio::types::{DeviceInfo, SourceRole};
+use serde::Serialize;
+
+/// Notifications from the capture threads, forwarded to the frontend as Tauri events.
+#[derive(Debug, Clone)]
+pub enum CaptureEvent {
+    /// Per-source signal levels, sent at a fixed rate while capturing.
+    Levels(AudioLevels),
+    /// Devices were plugged / unplugged, or a stream was moved to another device.
+    DeviceChanged(DeviceChange),
+    /// Stall / drop / backlog state of the sources, sent when it changes.
+    Health(AudioHealth),
+    /// A source recovered from (or capture stopped during) an unhealthy stretch.
+    Incident(HealthIncident),
+}
+
+impl CaptureEvent {
+    /// Tauri event name this is emitted under.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Levels(_) => "audio-levels",
+            Self::DeviceChanged(_) => "audio-device-changed",
+            Self::Health(_) => "audio-health",
+            Self::Incident(_) => "audio-incident",
+        }
+    }
+}
+
+/// Payload of the `audio-device-changed` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceChange {
+    /// The set of available devices changed; `devices` is the new `list_audio_devices`.
+    ListChanged { devices: Vec<DeviceInfo> },
+    /// The stream was rebuilt on `current` (a fallback, the new default, or the
+    /// requested device coming back). Capture and STT continued without a restart.
+    Recovered {
+        role: SourceRole,
+        previous: String,
+        current: String,
+        reason: String,
+    },
+    /// The stream failed and no device could be opened yet; retried until one appears.
+    Lost {
+        role: SourceRole,
+        previous: String,
+        reason: String,
+    },
+}
@@ -0,0 +1,262 @@
+use crate::audio::ring::RingWriter;
+use crate::audio::source::AudioSource;
+use crate::audio::types::SourceFormat;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Prefix marking a source ID as a file replay rather than a device.
+pub const FILE_ID_PREFIX: &str = "file:";
+
+/// Interleaved f32 samples of a WAV file, decoded as they are read.
+type WavSamples = Box<dyn Iterator<Item = hound::Result<f32>> + Send>;
+
+/// Replays a WAV file as if it were a capture device, for reproducing meetings
+/// and running the pipeline on machines without sound hardware.
+/// `realtime` paces delivery at wall-clock speed; otherwise the file is streamed
+/// as fast as the consumer accepts it.
+pub struct FileSource {
+    path: PathBuf,
+    format: SourceFormat,
+    realtime: bool,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FileSource {
+    /// Open a WAV file (8/16/24/32-bit int or 32-bit float PCM) and read its format.
+    pub fn open(path: &Path, realtime: bool) -> Result<Self> {
+        let reader = hound::WavReader::open(path)
+            .with_context(|| format!("Failed to open WAV file {:?}", path))?;
+        let spec = reader.spec();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            format: SourceFormat {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+            },
+            realtime,
+            handle: None,
+        })
+    }
+}
+
+impl AudioSource for FileSource {
+    fn id(&self) -> String {
+        format!("{}{}", FILE_ID_PREFIX, self.path.display())
+    }
+
+    fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    fn is_realtime(&self) -> bool {
+        self.realtime
+    }
+
+    fn start(&mut self, ring: RingWriter, is_running: Arc<AtomicBool>) -> Result<()> {
+        let samples = open_wav_samples(&self.path)?;
+        let format = self.format;
+        let realtime = self.realtime;
+
+        let handle = thread::Builder::new()
+            .name("file-replay".to_string())
+            .spawn(move || replay_thread(samples, format, realtime, ring, is_running))?;
+
+        tracing::info!(
+            "File replay started: {:?} ({}Hz {}ch, realtime={})",
+            self.path,
+            format.sample_rate,
+            format.channels,
+            realtime
+        );
+        self.handle = Some(handle);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // Replay thread exits on its own once `is_running` is cleared
+        self.handle.take();
+    }
+}
+
+/// Decode a whole WAV file to interleaved f32 in [-1.0, 1.0].
+pub fn read_wav_samples(path: &Path) -> Result<Vec<f32>> {
+    open_wav_samples(path)?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to decode WAV samples")
+}
+
+/// Open a WAV file for decoding to interleaved f32 in [-1.0, 1.0] as it is read.
+fn open_wav_samples(path: &Path) -> Result<WavSamples> {
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file {:?}", path))?;
+    let spec = reader.spec();
+
+    Ok(match spec.sample_format {
+        hound::SampleFormat::Float => Box::new(reader.into_samples::<f32>()),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            Box::new(
+                reader
+                    .into_samples::<i32>()
+                    .map(move |s| s.map(|v| v as f32 * scale)),
+            )
+        }
+    })
+}
+
+/// Decode and write the file in 10ms chunks, pacing to wall-clock time when
+/// `realtime`. A sample that fails to decode ends the replay there.
+fn replay_thread(
+    mut samples: WavSamples,
+    format: SourceFormat,
+    realtime: bool,
+    ring: RingWriter,
+    is_running: Arc<AtomicBool>,
+) {
+    let channels = format.channels.max(1) as usize;
+    let chunk_len = (format.sample_rate as usize / 100).max(1) * channels;
+    let mut chunk = Vec::with_capacity(chunk_len);
+    let mut error = None;
+    let start = Instant::now();
+    let mut frames_sent: u64 = 0;
+
+    while error.is_none() {
+        chunk.clear();
+        for sample in samples.by_ref().take(chunk_len) {
+            match sample {
+                Ok(s) => chunk.push(s),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        // Only whole frames go out
+        chunk.truncate(chunk.len() / channels * channels);
+        if chunk.is_empty() {
+            break;
+        }
+
+        if realtime {
+            let due = Duration::from_secs_f64(frames_sent as f64 / format.sample_rate as f64);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+
+        // Back-pressure instead of drops; gives up on shutdown
+        if !ring.push_blocking(&chunk, &is_running) {
+            tracing::info!("File replay stopped early");
+            return;
+        }
+        frames_sent += (chunk.len() / channels) as u64;
+    }
+
+    if let Some(e) = error {
+        tracing::warn!("File replay cut short, failed to decode WAV samples: {}", e);
+    }
+    tracing::info!(
+        "File replay finished: {} frames in {:.1}s",
+        frames_sent,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring::sample_ring;
+
+    fn write_test_wav(samples: &[i16], sample_rate: u32, channels: u16) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rt-replay-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn collect_all(source: &mut FileSource) -> Vec<f32> {
+        let (ring, mut rx) = sample_ring(400, source.format().channels);
+        let is_running = Arc::new(AtomicBool::new(true));
+        source.start(ring, is_running).unwrap();
+        let mut out = Vec::new();
+        let mut chunk = [0.0; 256];
+        while let Ok(n) = rx.recv_timeout(&mut chunk, Duration::from_secs(2)) {
+            out.extend_from_slice(&chunk[..n]);
+        }
+        out
+    }
+
+    #[test]
+    fn reads_format_and_scales_int_samples() {
+        let path = write_test_wav(&[16384, -16384, 0, 32767], 16000, 2);
+        let source = FileSource::open(&path, false).unwrap();
+        assert_eq!(source.format().sample_rate, 16000);
+        assert_eq!(source.format().channels, 2);
+        assert!(source.id().starts_with(FILE_ID_PREFIX));
+
+        let samples = read_wav_samples(&path).unwrap();
+        assert_eq!(samples, vec![0.5, -0.5, 0.0, 32767.0 / 32768.0]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn fast_replay_delivers_every_sample_in_order() {
+        // 1s of 16kHz mono through a tiny ring: back-pressure, not drops
+        let input: Vec<i16> = (0..16000).map(|i| (i % 1000) as i16).collect();
+        let path = write_test_wav(&input, 16000, 1);
+        let mut source = FileSource::open(&path, false).unwrap();
+        assert!(!source.is_realtime());
+
+        let out = collect_all(&mut source);
+        assert_eq!(out.len(), input.len());
+        assert!((out[999] - 999.0 / 32768.0).abs() < 1e-6);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn realtime_replay_paces_to_wall_clock() {
+        // 200ms of audio must take roughly 200ms to deliver
+        let path = write_test_wav(&vec![0i16; 3200], 16000, 1);
+        let mut source = FileSource::open(&path, true).unwrap();
+
+        let started = Instant::now();
+        let out = collect_all(&mut source);
+        assert_eq!(out.len(), 3200);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn truncated_file_replays_what_it_has() {
+        let path = write_test_wav(&vec![1000i16; 3200], 16000, 1);
+        // Header still promises 3200 samples
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 2000).unwrap();
+
+        let mut source = FileSource::open(&path, false).unwrap();
+        let out = collect_all(&mut source);
+        assert_eq!(out.len(), 2200);
+        assert!(read_wav_samples(&path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(FileSource::open(Path::new("/nonexistent/meeting.wav"), true).is_err());
+    }
+}
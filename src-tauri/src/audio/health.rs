@@ -0,0 +1,573 @@
+use crate::audio::events::CaptureEvent;
+use crate::audio::types::{SourceFormat, SourceRole};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// How long since a source's last buffer before it counts as stalled.
+const STALL_WARNING: Duration = Duration::from_millis(500);
+const STALL_CRITICAL: Duration = Duration::from_millis(2000);
+/// Share of a source's frames dropped within a check window.
+const DROP_WARNING: f32 = 0.01;
+const DROP_CRITICAL: f32 = 0.1;
+/// Audio waiting in a source ring for the processor (rings hold 2s).
+const BACKLOG_WARNING_MS: u64 = 500;
+const BACKLOG_CRITICAL_MS: u64 = 1500;
+/// Audio waiting in an STT ring for whisper (rings hold 10s).
+const STT_BACKLOG_WARNING_MS: u64 = 5000;
+const STT_BACKLOG_CRITICAL_MS: u64 = 8000;
+/// While unhealthy, `audio-health` is repeated this often even without a change.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Severity of a health report or incident.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    #[default]
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl HealthState {
+    fn grade<T: PartialOrd>(value: T, warning: T, critical: T) -> Self {
+        if value >= critical {
+            Self::Critical
+        } else if value >= warning {
+            Self::Warning
+        } else {
+            Self::Ok
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// What made a source unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssue {
+    /// The OS stopped delivering buffers.
+    Stall,
+    /// Buffers arrived faster than the processor took them and were dropped.
+    Drops,
+    /// The processor is falling behind the source.
+    Backlog,
+    /// Transcription fell behind and the source's STT ring dropped audio, leaving
+    /// a gap in the transcript.
+    SttDrops,
+    /// Transcription is falling behind the source.
+    SttBacklog,
+}
+
+impl HealthIssue {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Stall => "stall",
+            Self::Drops => "drops",
+            Self::Backlog => "backlog",
+            Self::SttDrops => "stt_drops",
+            Self::SttBacklog => "stt_backlog",
+        }
+    }
+}
+
+/// One source's state in an `audio-health` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceHealth {
+    pub role: SourceRole,
+    pub state: HealthState,
+    /// Worst issue, `None` while healthy.
+    pub issue: Option<HealthIssue>,
+    pub since_last_buffer_ms: u64,
+    /// Share of frames dropped since the previous check, 0.0–1.0.
+    pub drop_rate: f32,
+    pub backlog_ms: u64,
+    /// Same for the source's STT ring; 0 outside meetings.
+    pub stt_drop_rate: f32,
+    pub stt_backlog_ms: u64,
+}
+
+/// Payload of the `audio-health` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioHealth {
+    /// Worst source state.
+    pub state: HealthState,
+    pub sources: Vec<SourceHealth>,
+}
+
+/// A stretch of time a source spent unhealthy, reported once it recovers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthIncident {
+    pub role: SourceRole,
+    /// Issue that opened the incident.
+    pub issue: HealthIssue,
+    /// Worst state reached.
+    pub severity: HealthState,
+    pub duration_ms: u64,
+    pub dropped_frames: u64,
+}
+
+/// Ring counters sampled by the processor for a health check.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RingStats {
+    /// Total frames dropped so far.
+    pub dropped_frames: u64,
+    /// Samples waiting to be read.
+    pub backlog: usize,
+    /// Same for the source's STT ring, 0 without one. A new ring counts from 0.
+    pub stt_dropped_frames: u64,
+    pub stt_backlog: usize,
+}
+
+struct OpenIncident {
+    issue: HealthIssue,
+    severity: HealthState,
+    started: Instant,
+    lost_at_start: u64,
+}
+
+/// Tracks buffers, drops and backlog of one source, and of its STT ring, between
+/// checks.
+pub struct SourceMonitor {
+    role: SourceRole,
+    format: SourceFormat,
+    /// Format of the source's STT ring.
+    stt_format: SourceFormat,
+    last_buffer: Instant,
+    frames: u64,
+    dropped: u64,
+    stt_frames: u64,
+    stt_dropped: u64,
+    /// Frames dropped from the source or STT rings since monitoring started.
+    lost: u64,
+    state: HealthState,
+    incident: Option<OpenIncident>,
+}
+
+impl SourceMonitor {
+    /// Start monitoring at `now`; a source that never delivers stalls from here.
+    pub fn new(role: SourceRole, format: SourceFormat, now: Instant) -> Self {
+        Self {
+            role,
+            format,
+            stt_format: format,
+            last_buffer: now,
+            frames: 0,
+            dropped: 0,
+            stt_frames: 0,
+            stt_dropped: 0,
+            lost: 0,
+            state: HealthState::Ok,
+            incident: None,
+        }
+    }
+
+    /// The source reaches STT in another format (e.g. loopback as mono at the mic rate).
+    pub fn with_stt_format(mut self, format: SourceFormat) -> Self {
+        self.stt_format = format;
+        self
+    }
+
+    /// Note a buffer of `samples` interleaved samples read from the source.
+    pub fn on_buffer(&mut self, now: Instant, samples: usize) {
+        self.last_buffer = now;
+        self.frames += (samples / self.channels()) as u64;
+    }
+
+    /// Note `frames` frames the STT ring took.
+    pub fn on_stt(&mut self, frames: usize) {
+        self.stt_frames += frames as u64;
+    }
+
+    /// Grade the source since the previous check. Returns its health and, when it
+    /// just recovered, the incident that ended.
+    pub fn check(
+        &mut self,
+        now: Instant,
+        stats: RingStats,
+    ) -> (SourceHealth, Option<HealthIncident>) {
+        let stalled = now.saturating_duration_since(self.last_buffer);
+        let (dropped, stt_dropped) = self.count_drops(stats);
+        let drop_rate = rate(dropped, self.frames + dropped);
+        let stt_drop_rate = rate(stt_dropped, self.stt_frames + stt_dropped);
+        let backlog_ms = duration_ms(stats.backlog, self.format);
+        let stt_backlog_ms = duration_ms(stats.stt_backlog, self.stt_format);
+        self.frames = 0;
+        self.stt_frames = 0;
+
+        // Some loopback APIs deliver nothing while no app plays audio, so a quiet
+        // loopback is not a stall; the watchdog reports a loopback stream that failed
+        let stall = match self.role {
+            SourceRole::Loopback => HealthState::Ok,
+            _ => HealthState::grade(stalled, STALL_WARNING, STALL_CRITICAL),
+        };
+        let graded = [
+            (HealthIssue::Stall, stall),
+            (
+                HealthIssue::Drops,
+                HealthState::grade(drop_rate, DROP_WARNING, DROP_CRITICAL),
+            ),
+            (
+                HealthIssue::Backlog,
+                HealthState::grade(backlog_ms, BACKLOG_WARNING_MS, BACKLOG_CRITICAL_MS),
+            ),
+            (
+                HealthIssue::SttDrops,
+                HealthState::grade(stt_drop_rate, DROP_WARNING, DROP_CRITICAL),
+            ),
+            (
+                HealthIssue::SttBacklog,
+                HealthState::grade(
+                    stt_backlog_ms,
+                    STT_BACKLOG_WARNING_MS,
+                    STT_BACKLOG_CRITICAL_MS,
+                ),
+            ),
+        ];
+        let (issue, state) =
+            graded
+                .into_iter()
+                .fold((None, HealthState::Ok), |worst, (issue, state)| {
+                    if state > worst.1 {
+                        (Some(issue), state)
+                    } else {
+                        worst
+                    }
+                });
+
+        let ended = match (issue, &mut self.incident) {
+            (Some(issue), None) => {
+                // A stall began with the last buffer, not when it was noticed
+                let started = match issue {
+                    HealthIssue::Stall => self.last_buffer,
+                    _ => now,
+                };
+                self.incident = Some(OpenIncident {
+                    issue,
+                    severity: state,
+                    started,
+                    lost_at_start: self.lost - dropped - stt_dropped,
+                });
+                None
+            }
+            (Some(_), Some(open)) => {
+                open.severity = open.severity.max(state);
+                None
+            }
+            (None, Some(_)) => self.close(now),
+            (None, None) => None,
+        };
+        self.state = state;
+
+        let health = SourceHealth {
+            role: self.role,
+            state,
+            issue,
+            since_last_buffer_ms: stalled.as_millis() as u64,
+            drop_rate,
+            backlog_ms,
+            stt_drop_rate,
+            stt_backlog_ms,
+        };
+        (health, ended)
+    }
+
+    /// End an open incident at `now`, e.g. when capture stops, counting drops up
+    /// to `stats`.
+    pub fn finish(&mut self, now: Instant, stats: RingStats) -> Option<HealthIncident> {
+        self.count_drops(stats);
+        self.close(now)
+    }
+
+    fn close(&mut self, now: Instant) -> Option<HealthIncident> {
+        self.incident.take().map(|open| HealthIncident {
+            role: self.role,
+            issue: open.issue,
+            severity: open.severity,
+            duration_ms: now.saturating_duration_since(open.started).as_millis() as u64,
+            dropped_frames: self.lost - open.lost_at_start,
+        })
+    }
+
+    /// Frames the source and STT rings dropped since the previous count.
+    fn count_drops(&mut self, stats: RingStats) -> (u64, u64) {
+        let dropped = stats.dropped_frames.saturating_sub(self.dropped);
+        // A meeting's STT ring replaces the last one, counting from 0 again
+        let stt_dropped = stats
+            .stt_dropped_frames
+            .checked_sub(self.stt_dropped)
+            .unwrap_or(stats.stt_dropped_frames);
+        self.dropped = stats.dropped_frames;
+        self.stt_dropped = stats.stt_dropped_frames;
+        self.lost += dropped + stt_dropped;
+        (dropped, stt_dropped)
+    }
+
+    fn channels(&self) -> usize {
+        self.format.channels.max(1) as usize
+    }
+}
+
+fn rate(part: u64, whole: u64) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f32 / whole as f32
+    }
+}
+
+/// Milliseconds of `format` audio in `samples` interleaved samples.
+fn duration_ms(samples: usize, format: SourceFormat) -> u64 {
+    let frames = samples / format.channels.max(1) as usize;
+    frames as u64 * 1000 / format.sample_rate.max(1) as u64
+}
+
+/// Health of all captured sources. Emits `audio-health` when any source changes
+/// state and every `REPORT_INTERVAL` while one is unhealthy, plus finished incidents.
+pub struct HealthMonitor {
+    sources: Vec<SourceMonitor>,
+    last_report: Instant,
+}
+
+impl HealthMonitor {
+    pub fn new(sources: Vec<SourceMonitor>, now: Instant) -> Self {
+        Self {
+            sources,
+            last_report: now,
+        }
+    }
+
+    pub fn on_buffer(&mut self, role: SourceRole, now: Instant, samples: usize) {
+        if let Some(source) = self.sources.iter_mut().find(|s| s.role == role) {
+            source.on_buffer(now, samples);
+        }
+    }
+
+    pub fn on_stt(&mut self, role: SourceRole, frames: usize) {
+        if let Some(source) = self.sources.iter_mut().find(|s| s.role == role) {
+            source.on_stt(frames);
+        }
+    }
+
+    /// Check every source; `stats` are in the order the sources were given.
+    pub fn check(&mut self, now: Instant, stats: &[RingStats], mut emit: impl FnMut(CaptureEvent)) {
+        let mut changed = false;
+        let mut sources = Vec::with_capacity(self.sources.len());
+        for (source, stats) in self.sources.iter_mut().zip(stats) {
+            let before = source.state;
+            let (health, ended) = source.check(now, *stats);
+            changed |= health.state != before;
+            sources.push(health);
+            if let Some(incident) = ended {
+                emit(CaptureEvent::Incident(incident));
+            }
+        }
+        let state = sources.iter().map(|s| s.state).max().unwrap_or_default();
+        let due = state != HealthState::Ok && now - self.last_report >= REPORT_INTERVAL;
+        if changed || due {
+            self.last_report = now;
+            emit(CaptureEvent::Health(AudioHealth { state, sources }));
+        }
+    }
+
+    /// Close open incidents, e.g. when capture stops.
+    pub fn finish(
+        &mut self,
+        now: Instant,
+        stats: &[RingStats],
+        mut emit: impl FnMut(CaptureEvent),
+    ) {
+        for (source, stats) in self.sources.iter_mut().zip(stats) {
+            if let Some(incident) = source.finish(now, *stats) {
+                emit(CaptureEvent::Incident(incident));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONO_16K: SourceFormat = SourceFormat {
+        sample_rate: 16000,
+        channels: 1,
+    };
+
+    fn at(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn stall_escalates_and_recovery_reports_the_incident() {
+        let start = Instant::now();
+        let mut mic = SourceMonitor::new(SourceRole::Mic, MONO_16K, start);
+        mic.on_buffer(at(start, 100), 1600);
+        assert_eq!(
+            mic.check(at(start, 200), RingStats::default()).0.state,
+            HealthState::Ok
+        );
+
+        let (health, _) = mic.check(at(start, 800), RingStats::default());
+        assert_eq!(health.state, HealthState::Warning);
+        assert_eq!(health.issue, Some(HealthIssue::Stall));
+        assert_eq!(health.since_last_buffer_ms, 700);
+        assert_eq!(
+            mic.check(at(start, 2500), RingStats::default()).0.state,
+            HealthState::Critical
+        );
+
+        mic.on_buffer(at(start, 3000), 160);
+        let (health, incident) = mic.check(at(start, 3100), RingStats::default());
+        assert_eq!(health.state, HealthState::Ok);
+        let incident = incident.unwrap();
+        assert_eq!(incident.issue, HealthIssue::Stall);
+        assert_eq!(incident.severity, HealthState::Critical);
+        assert_eq!(incident.duration_ms, 3000);
+    }
+
+    #[test]
+    fn grades_drop_rate_and_backlog() {
+        let start = Instant::now();
+        let mut mic = SourceMonitor::new(SourceRole::Mic, MONO_16K, start);
+        mic.on_buffer(start, 950);
+        let stats = RingStats {
+            dropped_frames: 50,
+            backlog: 1600,
+            ..RingStats::default()
+        };
+        let (health, _) = mic.check(start, stats);
+        assert_eq!(health.drop_rate, 0.05);
+        assert_eq!(health.backlog_ms, 100);
+        assert_eq!(
+            (health.state, health.issue),
+            (HealthState::Warning, Some(HealthIssue::Drops))
+        );
+
+        // No new drops, but 1.5s waiting
+        mic.on_buffer(start, 1000);
+        let stats = RingStats {
+            dropped_frames: 50,
+            backlog: 24000,
+            ..RingStats::default()
+        };
+        let (health, _) = mic.check(start, stats);
+        assert_eq!(health.drop_rate, 0.0);
+        assert_eq!(
+            (health.state, health.issue),
+            (HealthState::Critical, Some(HealthIssue::Backlog))
+        );
+    }
+
+    #[test]
+    fn stt_falling_behind_is_an_incident() {
+        let start = Instant::now();
+        let stereo_48k = SourceFormat {
+            sample_rate: 48000,
+            channels: 2,
+        };
+        let mut loopback =
+            SourceMonitor::new(SourceRole::Loopback, stereo_48k, start).with_stt_format(MONO_16K);
+        let mut check = |ms, stt_frames, stats| {
+            loopback.on_buffer(at(start, ms), 9600);
+            loopback.on_stt(stt_frames);
+            loopback.check(at(start, ms), stats)
+        };
+
+        // Whisper lags: 6s waiting in the STT ring, then the full ring drops 20%
+        let backlog = RingStats {
+            stt_backlog: 96000,
+            ..RingStats::default()
+        };
+        let (health, _) = check(250, 4000, backlog);
+        assert_eq!(health.stt_backlog_ms, 6000);
+        assert_eq!(
+            (health.state, health.issue),
+            (HealthState::Warning, Some(HealthIssue::SttBacklog))
+        );
+        let dropping = RingStats {
+            stt_dropped_frames: 1000,
+            stt_backlog: 160000,
+            ..RingStats::default()
+        };
+        let (health, _) = check(500, 4000, dropping);
+        assert_eq!(health.stt_drop_rate, 0.2);
+        assert_eq!(health.state, HealthState::Critical);
+
+        // The next meeting's ring counts its drops from 0
+        let (health, incident) = check(750, 4000, RingStats::default());
+        assert_eq!(health.state, HealthState::Ok);
+        let incident = incident.unwrap();
+        assert_eq!(incident.issue, HealthIssue::SttBacklog);
+        assert_eq!(incident.severity, HealthState::Critical);
+        assert_eq!(incident.dropped_frames, 1000);
+        let new_ring = RingStats {
+            stt_dropped_frames: 400,
+            ..RingStats::default()
+        };
+        assert_eq!(check(1000, 3600, new_ring).0.stt_drop_rate, 0.1);
+    }
+
+    #[test]
+    fn monitor_reports_changes_and_closes_incidents_on_finish() {
+        let start = Instant::now();
+        let mut monitor = HealthMonitor::new(
+            vec![
+                SourceMonitor::new(SourceRole::Mic, MONO_16K, start),
+                SourceMonitor::new(SourceRole::Loopback, MONO_16K, start),
+            ],
+            start,
+        );
+        let stats = [RingStats::default(); 2];
+        let mut events = Vec::new();
+        monitor.on_buffer(SourceRole::Mic, at(start, 250), 160);
+        monitor.check(at(start, 250), &stats, |e| events.push(e));
+        assert!(events.is_empty());
+
+        // Mic silent for 3s while loopback keeps delivering
+        monitor.on_buffer(SourceRole::Loopback, at(start, 3000), 160);
+        monitor.check(at(start, 3000), &stats, |e| events.push(e));
+        let Some(CaptureEvent::Health(health)) = events.pop() else {
+            panic!("expected audio-health");
+        };
+        assert_eq!(health.state, HealthState::Critical);
+        assert_eq!(health.sources[0].issue, Some(HealthIssue::Stall));
+        assert_eq!(health.sources[1].state, HealthState::Ok);
+
+        monitor.finish(at(start, 3750), &stats, |e| events.push(e));
+        let Some(CaptureEvent::Incident(incident)) = events.pop() else {
+            panic!("expected an incident");
+        };
+        assert_eq!(incident.role, SourceRole::Mic);
+        assert_eq!(incident.duration_ms, 3500);
+    }
+
+    #[test]
+    fn idle_loopback_is_not_an_incident() {
+        let start = Instant::now();
+        let mut monitor = HealthMonitor::new(
+            vec![
+                SourceMonitor::new(SourceRole::Mic, MONO_16K, start),
+                SourceMonitor::new(SourceRole::Loopback, MONO_16K, start),
+            ],
+            start,
+        );
+        let stats = [RingStats::default(); 2];
+        let mut events = Vec::new();
+        monitor.on_buffer(SourceRole::Loopback, at(start, 100), 160);
+
+        // Nothing plays for ten minutes: loopback delivers no buffers at all
+        for ms in (250..600_000).step_by(250) {
+            monitor.on_buffer(SourceRole::Mic, at(start, ms), 4000);
+            monitor.check(at(start, ms), &stats, |e| events.push(e));
+        }
+        monitor.finish(at(start, 600_000), &stats, |e| events.push(e));
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::audio::adapter::FormatAdapter;
+use crate::audio::ring::{sample_ring, RingWriter};
+use crate::audio::source::AudioSource;
+use crate::audio::types::{SourceFormat, SourceRole};
+use anyhow::Result;
+use crossbeam::channel::RecvTimeoutError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Frames the adapter thread converts per pass (10ms at 48kHz).
+const ADAPTER_BLOCK_FRAMES: usize = 480;
+
+/// A started source with its own run flag, so it can be replaced mid-capture.
+pub(crate) struct LiveSource {
+    source: Box<dyn AudioSource>,
+    running: Arc<AtomicBool>,
+}
+
+impl LiveSource {
+    /// Start `source` writing into `ring` in `expected` format. A source with another
+    /// format (e.g. a replacement device) writes into its own ring and is converted on an
+    /// adapter thread, so whatever reads `ring` never sees the switch.
+    pub fn start(
+        mut source: Box<dyn AudioSource>,
+        ring: RingWriter,
+        expected: SourceFormat,
+    ) -> Result<Self> {
+        let running = Arc::new(AtomicBool::new(true));
+        let format = source.format();
+        if format == expected {
+            source.start(ring, running.clone())?;
+            return Ok(Self { source, running });
+        }
+
+        let mut adapter = FormatAdapter::new(format, expected)?;
+        // One second of the source's native audio
+        let (raw_ring, mut raw) = sample_ring(format.sample_rate as usize, format.channels);
+        source.start(raw_ring, running.clone())?;
+        let flag = running.clone();
+        let mut block = vec![0.0; ADAPTER_BLOCK_FRAMES * format.channels.max(1) as usize];
+        thread::Builder::new()
+            .name("audio-adapter".to_string())
+            .spawn(move || {
+                while flag.load(Ordering::SeqCst) {
+                    match raw.recv_timeout(&mut block, Duration::from_millis(50)) {
+                        Ok(n) => adapter.process_with(&block[..n], |out| {
+                            ring.push(out);
+                        }),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })?;
+        tracing::info!(
+            "Adapting '{}' from {:?} to {:?}",
+            source.id(),
+            format,
+            expected
+        );
+        Ok(Self { source, running })
+    }
+
+    pub fn id(&self) -> String {
+        self.source.id()
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.source.has_failed()
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        self.source.is_realtime()
+    }
+
+    pub fn stop(mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.source.stop();
+    }
+}
+
+/// Sources of a running capture, shared between the manager and its watchdog.
+#[derive(Default)]
+pub(crate) struct LiveSources {
+    pub mic: Option<LiveSource>,
+    pub loopback: Option<LiveSource>,
+}
+
+impl LiveSources {
+    pub fn slot(&mut self, role: SourceRole) -> &mut Option<LiveSource> {
+        match role {
+            SourceRole::Mic => &mut self.mic,
+            SourceRole::Loopback => &mut self.loopback,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+#![allow(deprecated)] // cpal 0.17 deprecates name() in favor of description()
+
+use crate::audio::channel_map::ChannelMap;
+use crate::audio::device;
+use crate::audio::ring::RingWriter;
+use crate::audio::source::AudioSource;
+use crate::audio::types::SourceFormat;
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, I24, U24};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Microphone (or any cpal input device) capture source.
+/// Accepts every cpal sample format, delivering f32 in the selected channels.
+pub struct MicSource {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    channel_map: ChannelMap,
+    name: String,
+    stream: Option<cpal::Stream>,
+    failed: Arc<AtomicBool>,
+}
+
+impl MicSource {
+    /// Open the requested input device (`DeviceInfo.id`), or the OS default when `None`.
+    pub fn open(device_id: Option<&str>) -> Result<Self> {
+        let device = device::resolve_input_device(device_id)?;
+        let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+        let config = device
+            .default_input_config()
+            .with_context(|| format!("Failed to get input config for '{}'", name))?;
+
+        Ok(Self {
+            device,
+            config,
+            channel_map: ChannelMap::default(),
+            name,
+            stream: None,
+            failed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Capture only the mapped channels (e.g. input 3 of an audio interface).
+    pub fn with_channel_map(mut self, map: ChannelMap) -> Result<Self> {
+        map.validate(self.config.channels())
+            .with_context(|| format!("Invalid channel selection for '{}'", self.name))?;
+        self.channel_map = map;
+        Ok(self)
+    }
+
+    /// The callback converts and remaps samples straight into `ring`: no allocation,
+    /// no locks. A full ring drops the buffer and counts it in `RingWriter::dropped_frames`.
+    fn build_stream<T>(&self, ring: RingWriter) -> Result<cpal::Stream>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let failed = self.failed.clone();
+        let channels = self.config.channels().max(1) as usize;
+        let picks = self.channel_map.source_indices(self.config.channels());
+        let stream = self.device.build_input_stream(
+            &self.config.clone().into(),
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let frames = data.len() / channels;
+                ring.push_fn(frames * picks.len(), |i| {
+                    mapped_sample(data, channels, &picks, i)
+                });
+            },
+            move |err| {
+                tracing::error!("Mic stream error: {}", err);
+                // Unplugged / invalidated streams never resume; let the watchdog rebuild
+                if matches!(
+                    err,
+                    cpal::StreamError::DeviceNotAvailable | cpal::StreamError::StreamInvalidated
+                ) {
+                    failed.store(true, Ordering::SeqCst);
+                }
+            },
+            None,
+        )?;
+        Ok(stream)
+    }
+}
+
+/// Output sample `i` of interleaved `channels`-wide `data` keeping only the
+/// `picks` input channels (zero-based, in output order), as f32.
+fn mapped_sample<T>(data: &[T], channels: usize, picks: &[usize], i: usize) -> f32
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let (frame, ch) = (i / picks.len(), i % picks.len());
+    data[frame * channels + picks[ch]].to_sample::<f32>()
+}
+
+impl AudioSource for MicSource {
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+
+    fn format(&self) -> SourceFormat {
+        SourceFormat {
+            sample_rate: self.config.sample_rate(),
+            channels: self.channel_map.output_channels(self.config.channels()),
+        }
+    }
+
+    fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    fn start(&mut self, ring: RingWriter, _is_running: Arc<AtomicBool>) -> Result<()> {
+        tracing::info!(
+            "Mic '{}': {} ch, {}Hz, {:?}, channels {:?}",
+            self.name,
+            self.config.channels(),
+            self.config.sample_rate(),
+            self.config.sample_format(),
+            self.channel_map
+        );
+
+        let stream = match self.config.sample_format() {
+            SampleFormat::I8 => self.build_stream::<i8>(ring)?,
+            SampleFormat::I16 => self.build_stream::<i16>(ring)?,
+            SampleFormat::I24 => self.build_stream::<I24>(ring)?,
+            SampleFormat::I32 => self.build_stream::<i32>(ring)?,
+            SampleFormat::I64 => self.build_stream::<i64>(ring)?,
+            SampleFormat::U8 => self.build_stream::<u8>(ring)?,
+            SampleFormat::U16 => self.build_stream::<u16>(ring)?,
+            SampleFormat::U24 => self.build_stream::<U24>(ring)?,
+            SampleFormat::U32 => self.build_stream::<u32>(ring)?,
+            SampleFormat::U64 => self.build_stream::<u64>(ring)?,
+            SampleFormat::F32 => self.build_stream::<f32>(ring)?,
+            SampleFormat::F64 => self.build_stream::<f64>(ring)?,
+            other => anyhow::bail!("Unsupported sample format {:?} on '{}'", other, self.name),
+        };
+
+        stream.play()?;
+        self.stream = Some(stream);
+        tracing::info!("Mic stream started");
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // Drop the cpal stream to release audio device resources
+        if let Some(stream) = self.stream.take() {
+            drop(stream);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapped<T>(data: &[T], channels: u16, map: ChannelMap) -> Vec<f32>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let picks = map.source_indices(channels);
+        let len = data.len() / channels as usize * picks.len();
+        (0..len)
+            .map(|i| mapped_sample(data, channels as usize, &picks, i))
+            .collect()
+    }
+
+    #[test]
+    fn maps_third_channel_of_integer_formats() {
+        // Two frames of a 4-channel interface; input 3 carries the signal
+        let i16s = [0, 0, i16::MIN, 0, 0, 0, 16384, 0];
+        assert_eq!(mapped(&i16s, 4, ChannelMap(vec![3])), vec![-1.0, 0.5]);
+
+        let u8s: [u8; 8] = [128, 128, 0, 128, 128, 128, 192, 128];
+        assert_eq!(mapped(&u8s, 4, ChannelMap(vec![3])), vec![-1.0, 0.5]);
+
+        let i24 = |v| I24::new(v).unwrap();
+        let i24s = [0, 0, -(1 << 23), 0, 0, 0, 1 << 22, 0].map(i24);
+        assert_eq!(mapped(&i24s, 4, ChannelMap(vec![3])), vec![-1.0, 0.5]);
+    }
+
+    #[test]
+    fn keeps_map_order_and_all_channels() {
+        let i32s = [0, i32::MIN, 1 << 30, 0];
+        assert_eq!(
+            mapped(&i32s, 2, ChannelMap(vec![2, 1])),
+            vec![-1.0, 0.0, 0.0, 0.5]
+        );
+        assert_eq!(
+            mapped(&i32s, 2, ChannelMap::default()),
+            vec![0.0, -1.0, 0.5, 0.0]
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::audio::adapter::MonoConverter;
+use crate::audio::dsp::EchoCanceller;
+use crate::audio::types::SourceFormat;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Max loopback audio queued ahead of the mic before the oldest is dropped (500ms).
+const MAX_QUEUE_MS: usize = 500;
+/// Loopback kept queued past each mic block to ride out delivery jitter (20ms).
+const REFERENCE_DEPTH_MS: usize = 20;
+/// Extra queue depth tolerated before it is trimmed back to `REFERENCE_DEPTH_MS` (20ms).
+const REFERENCE_SLACK_MS: usize = 20;
+/// After loopback starts or resumes, how long any extra depth is trimmed at once,
+/// while delivery jitter still shows how far ahead of the mic it runs (100ms).
+const REFERENCE_SETTLE_MS: usize = 100;
+
+/// Per-source gains and echo cancellation for mic and system loopback. The gains apply
+/// to each source's STT lane, and so to the meeting recording, as well as to the mix.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixConfig {
+    pub mic_gain: f32,
+    pub loopback_gain: f32,
+    /// Remove speaker playback picked up by the mic, using loopback as the reference,
+    /// so remote speech is not heard (and transcribed) twice.
+    pub echo_cancellation: bool,
+    /// Longest echo path the canceller models (output latency plus room reverb).
+    pub echo_tail_ms: u32,
+}
+
+impl Default for MixConfig {
+    fn default() -> Self {
+        Self {
+            mic_gain: 1.0,
+            loopback_gain: 1.0,
+            echo_cancellation: true,
+            echo_tail_ms: 200,
+        }
+    }
+}
+
+/// Scale samples in place by `gain`, clipping to full scale.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain != 1.0 {
+        for s in samples {
+            *s = (*s * gain).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Converts loopback audio to the mic's rate and mixes it into mic frames.
+/// Loopback is downmixed to mono, resampled, and queued until the mic catches up;
+/// mic frames without queued loopback are mixed with silence (WASAPI delivers
+/// nothing while the system is quiet). The queue is held near a fixed depth, so the
+/// loopback taken for a mic block stays a steady distance behind the newest loopback
+/// whatever the startup offset between the streams or the silence they pause for.
+/// With echo cancellation on, each mic channel first has the loopback echo removed,
+/// which delays the mic by `echo_latency()`; the loopback handed on is delayed as
+/// much, so mixed, forked and recorded audio of both stays in step.
+pub struct LoopbackMixer {
+    config: MixConfig,
+    mic_channels: usize,
+    converter: MonoConverter,
+    queue: VecDeque<f32>,
+    max_queue: usize,
+    /// Queue depth left past each mic block, and the most allowed before trimming.
+    target_depth: usize,
+    max_depth: usize,
+    settle_frames: usize,
+    /// Mic frames left before the slack applies again.
+    settling: usize,
+    /// The last mic block found no loopback queued.
+    loopback_idle: bool,
+    /// One canceller per mic channel; empty when echo cancellation is off.
+    echo: Vec<EchoCanceller>,
+    /// Loopback for the current mic block, as the cancellers take it.
+    reference: Vec<f32>,
+    /// `echo_latency()` frames of loopback, delaying it like the cancelled mic.
+    delay: VecDeque<f32>,
+    /// Loopback in step with the cancelled mic block (see `loopback`).
+    delayed: Vec<f32>,
+    channel: Vec<f32>,
+}
+
+impl LoopbackMixer {
+    pub fn new(config: MixConfig, mic: SourceFormat, loopback: SourceFormat) -> Result<Self> {
+        let converter = MonoConverter::new(loopback, mic.sample_rate)?;
+
+        tracing::info!(
+            "Loopback mixer: {}Hz {}ch -> {}Hz (gains mic={}, loopback={}, aec={})",
+            loopback.sample_rate,
+            loopback.channels,
+            mic.sample_rate,
+            config.mic_gain,
+            config.loopback_gain,
+            config.echo_cancellation,
+        );
+
+        let mic_channels = mic.channels.max(1) as usize;
+        let echo = if config.echo_cancellation {
+            (0..mic_channels)
+                .map(|_| EchoCanceller::new(mic.sample_rate, config.echo_tail_ms))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let ms = |ms: usize| mic.sample_rate as usize * ms / 1000;
+        let latency = echo.first().map_or(0, EchoCanceller::latency);
+        Ok(Self {
+            config,
+            mic_channels,
+            converter,
+            queue: VecDeque::with_capacity(ms(MAX_QUEUE_MS)),
+            max_queue: ms(MAX_QUEUE_MS),
+            target_depth: ms(REFERENCE_DEPTH_MS),
+            max_depth: ms(REFERENCE_DEPTH_MS + REFERENCE_SLACK_MS),
+            settle_frames: ms(REFERENCE_SETTLE_MS),
+            settling: ms(REFERENCE_SETTLE_MS),
+            loopback_idle: false,
+            echo,
+            reference: Vec::new(),
+            delay: VecDeque::from(vec![0.0; latency]),
+            delayed: Vec::new(),
+            channel: Vec::new(),
+        })
+    }
+
+    /// Queue interleaved loopback samples (in the loopback's native format).
+    pub fn push_loopback(&mut self, interleaved: &[f32]) {
+        let queue = &mut self.queue;
+        self.converter
+            .process_with(interleaved, |block| queue.extend(block));
+
+        if self.queue.len() > self.max_queue {
+            let excess = self.queue.len() - self.max_queue;
+            self.queue.drain(..excess);
+            tracing::debug!("Loopback ahead of mic, dropped {} frames", excess);
+        }
+    }
+
+    /// Mix queued loopback into interleaved mic samples in place, applying per-source
+    /// gains after removing the loopback's echo from the mic.
+    pub fn mix(&mut self, mic: &mut [f32]) {
+        self.cancel_echo(mic);
+        apply_gain(mic, self.config.mic_gain);
+        self.add_loopback(mic);
+    }
+
+    /// Take the loopback frames that go with this mic block and remove their echo
+    /// from `mic` in place. `loopback()` then holds the loopback in step with the
+    /// cancelled mic, with the loopback gain applied; the mic gain is left to the caller.
+    pub fn cancel_echo(&mut self, mic: &mut [f32]) {
+        let frames = mic.len() / self.mic_channels;
+        self.align_queue(frames);
+        self.reference.clear();
+        self.reference
+            .extend((0..frames).map(|_| self.queue.pop_front().unwrap_or(0.0)));
+
+        for (c, aec) in self.echo.iter_mut().enumerate() {
+            self.channel.clear();
+            self.channel
+                .extend(mic.iter().skip(c).step_by(self.mic_channels));
+            aec.process(&mut self.channel, &self.reference);
+            for (s, &clean) in mic
+                .iter_mut()
+                .skip(c)
+                .step_by(self.mic_channels)
+                .zip(&self.channel)
+            {
+                *s = clean;
+            }
+        }
+
+        self.delay.extend(&self.reference);
+        self.delayed.clear();
+        self.delayed.extend(self.delay.drain(..frames));
+        apply_gain(&mut self.delayed, self.config.loopback_gain);
+    }
+
+    /// Keep the queue, before taking `frames`, near its target depth. Loopback further
+    /// ahead would be too old to match the mic's echo; loopback back from silence is
+    /// the newest audio, so it goes in at the target depth.
+    fn align_queue(&mut self, frames: usize) {
+        let len = self.queue.len();
+        if len == 0 {
+            self.loopback_idle = true;
+        } else if self.loopback_idle {
+            self.loopback_idle = false;
+            self.settling = self.settle_frames;
+            let missing = (frames + self.target_depth).saturating_sub(len);
+            for _ in 0..missing {
+                self.queue.push_front(0.0);
+            }
+        } else {
+            let limit = if self.settling > 0 {
+                self.target_depth
+            } else {
+                self.max_depth
+            };
+            if len > frames + limit {
+                self.queue.drain(..len - frames - self.target_depth);
+            }
+        }
+        self.settling = self.settling.saturating_sub(frames);
+    }
+
+    /// Mono loopback at the mic rate to go with the mic block the last `cancel_echo`
+    /// returned, one sample per mic frame.
+    pub fn loopback(&self) -> &[f32] {
+        &self.delayed
+    }
+
+    /// Mix the loopback from the last `cancel_echo` into `mic`, whose gain the
+    /// caller has already applied.
+    pub fn add_loopback(&self, mic: &mut [f32]) {
+        for (frame, &lb) in mic.chunks_mut(self.mic_channels).zip(&self.delayed) {
+            for s in frame {
+                *s = (*s + lb).clamp(-1.0, 1.0);
+            }
+        }
+    }
+
+    /// Delay echo cancellation adds to the mic, in frames.
+    pub fn echo_latency(&self) -> usize {
+        self.echo.first().map_or(0, EchoCanceller::latency)
+    }
+
+    /// Loopback frames waiting to be mixed.
+    pub fn queued_frames(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sample_rate: u32, channels: u16) -> SourceFormat {
+        SourceFormat {
+            sample_rate,
+            channels,
+        }
+    }
+
+    fn without_echo_cancellation() -> MixConfig {
+        MixConfig {
+            echo_cancellation: false,
+            ..MixConfig::default()
+        }
+    }
+
+    #[test]
+    fn mixes_same_rate_with_gains() {
+        let config = MixConfig {
+            mic_gain: 0.5,
+            loopback_gain: 2.0,
+            echo_cancellation: false,
+            ..MixConfig::default()
+        };
+        let mut mixer = LoopbackMixer::new(config, format(16000, 1), format(16000, 2)).unwrap();
+        // Stereo loopback downmixes to 0.1 per frame
+        mixer.push_loopback(&[0.1, 0.1, 0.2, 0.0]);
+
+        let mut out = [0.4, 0.4, 0.4];
+        mixer.mix(&mut out);
+        assert!((out[0] - 0.4).abs() < 1e-6); // 0.2 + 0.2
+        assert!((out[1] - 0.4).abs() < 1e-6);
+        assert!((out[2] - 0.2).abs() < 1e-6); // no loopback left -> mic only
+    }
+
+    #[test]
+    fn loopback_lane_carries_its_gain() {
+        let config = MixConfig {
+            mic_gain: 0.5,
+            loopback_gain: 2.0,
+            echo_cancellation: false,
+            ..MixConfig::default()
+        };
+        let mut mixer = LoopbackMixer::new(config, format(16000, 1), format(16000, 1)).unwrap();
+        mixer.push_loopback(&[0.1, 0.6]);
+
+        let mut mic = [0.4, 0.4];
+        mixer.cancel_echo(&mut mic);
+        assert_eq!(mic, [0.4, 0.4]); // mic gain is the caller's
+        assert!((mixer.loopback()[0] - 0.2).abs() < 1e-6);
+        assert_eq!(mixer.loopback()[1], 1.0); // clipped
+    }
+
+    #[test]
+    fn duplicates_loopback_across_mic_channels() {
+        let mut mixer = LoopbackMixer::new(
+            without_echo_cancellation(),
+            format(16000, 2),
+            format(16000, 1),
+        )
+        .unwrap();
+        mixer.push_loopback(&[0.25]);
+        let mut out = [0.0, 0.5];
+        mixer.mix(&mut out);
+        assert_eq!(out, [0.25, 0.75]);
+    }
+
+    #[test]
+    fn resamples_loopback_to_mic_rate() {
+        let mut mixer =
+            LoopbackMixer::new(MixConfig::default(), format(16000, 1), format(48000, 2)).unwrap();
+        // 100ms of stereo 48kHz loopback -> ~100ms at 16kHz once resampled
+        mixer.push_loopback(&vec![0.1f32; 4800 * 2]);
+        let queued = mixer.queued_frames();
+        assert!(queued > 1000 && queued <= 1600, "queued {}", queued);
+    }
+
+    #[test]
+    fn caps_queue_when_mic_falls_behind() {
+        let mut mixer =
+            LoopbackMixer::new(MixConfig::default(), format(16000, 1), format(16000, 1)).unwrap();
+        mixer.push_loopback(&vec![0.1f32; 16000]);
+        assert_eq!(mixer.queued_frames(), 8000);
+    }
+
+    #[test]
+    fn echo_cancellation_keeps_loopback_in_step_with_mic() {
+        // A click in each stream, 0.25s apart; cancelling echo must not move one
+        // against the other
+        let offset = |echo_cancellation| {
+            let config = MixConfig {
+                echo_cancellation,
+                ..MixConfig::default()
+            };
+            let mut mixer = LoopbackMixer::new(config, format(16000, 1), format(16000, 1)).unwrap();
+            let click = |at: usize| (0..16000).map(move |i| if i == at { 0.5 } else { 0.0 });
+            let (mut mic, far): (Vec<f32>, Vec<f32>) =
+                (click(4000).collect(), click(8000).collect());
+            let mut loopback = Vec::new();
+            for (near, far) in mic.chunks_mut(160).zip(far.chunks(160)) {
+                mixer.push_loopback(far);
+                mixer.cancel_echo(near);
+                loopback.extend_from_slice(mixer.loopback());
+            }
+            let peak = |x: &[f32]| {
+                (0..x.len())
+                    .max_by(|&a, &b| x[a].abs().total_cmp(&x[b].abs()))
+                    .unwrap()
+            };
+            peak(&loopback) as i64 - peak(&mic) as i64
+        };
+        assert_eq!(offset(true), offset(false));
+    }
+
+    #[test]
+    fn removes_loopback_echo_from_mic_before_mixing() {
+        use crate::audio::dsp::test_signals::{rms, white_noise};
+
+        // Mic hears the loopback 30ms late at half level; mute loopback in the mix
+        let config = MixConfig {
+            loopback_gain: 0.0,
+            ..MixConfig::default()
+        };
+        let mut mixer = LoopbackMixer::new(config, format(16000, 1), format(16000, 1)).unwrap();
+        let far = white_noise(0.2, 16000 * 3, 11);
+        let mut mic: Vec<f32> = (0..far.len())
+            .map(|i| if i >= 480 { 0.5 * far[i - 480] } else { 0.0 })
+            .collect();
+        let before = rms(&mic[32000..]);
+        for (near, far) in mic.chunks_mut(160).zip(far.chunks(160)) {
+            mixer.push_loopback(far);
+            mixer.mix(near);
+        }
+        assert!(rms(&mic[32000..]) < before * 0.1);
+    }
+
+    #[test]
+    fn cancels_echo_with_jittered_offset_blocks() {
+        use crate::audio::dsp::test_signals::{rms, white_noise};
+
+        // Loopback starts 260ms before the mic, arrives in uneven packets up to 15ms
+        // late and pauses for 300ms of silence; the mic hears it 60ms late at half
+        // level. Mute loopback in the mix
+        let (offset, silence) = (4160, 56000..60800);
+        let mut far = white_noise(0.2, 16000 * 8, 5);
+        far[silence.clone()].fill(0.0);
+        let echo = |t: usize| if t >= 960 { 0.5 * far[t - 960] } else { 0.0 };
+        let mut mic: Vec<f32> = (offset..far.len()).map(echo).collect();
+        // The second before the silence, and the last second
+        let windows = [36000..52000, mic.len() - 16000..mic.len()];
+        let before = windows.clone().map(|w| rms(&mic[w]));
+
+        let config = MixConfig {
+            loopback_gain: 0.0,
+            ..MixConfig::default()
+        };
+        let mut mixer = LoopbackMixer::new(config, format(16000, 1), format(16000, 1)).unwrap();
+        let (mut delivered, mut mic_pos) = (0, 0);
+        for (tick, now) in (80..=far.len()).step_by(80).enumerate() {
+            // Held back for 0-3 ticks of 5ms, nothing delivered during the silence
+            let due = now.saturating_sub(80 * [0, 3, 1, 2, 0, 3, 1][tick % 7]);
+            if due > delivered {
+                let parts = [
+                    (delivered, due.min(silence.start)),
+                    (delivered.max(silence.end), due),
+                ];
+                for (start, end) in parts {
+                    if start < end {
+                        mixer.push_loopback(&far[start..end]);
+                    }
+                }
+                delivered = due;
+            }
+            if now > offset && (now - offset) % 320 == 0 {
+                mixer.mix(&mut mic[mic_pos..mic_pos + 320]);
+                mic_pos += 320;
+            }
+        }
+        for (w, before) in windows.into_iter().zip(before) {
+            let after = rms(&mic[w]);
+            assert!(after < before * 0.1, "{} -> {}", before, after);
+        }
+    }
+}
@@ -1,10 +1,46 @@
+pub mod adapter;
+pub mod calibration;
 pub mod capture;
+pub mod channel_map;
 pub mod device;
+pub mod dsp;
+pub mod events;
+pub mod file_source;
+pub mod health;
+pub mod levels;
+pub mod live_source;
+pub mod mic_source;
+pub mod mixer;
+pub mod neural_vad;
+pub mod noise_floor;
+#[cfg(target_os = "linux")]
+pub mod pulse_loopback;
+pub mod recorder;
 pub mod resampler;
+pub mod ring;
+pub mod source;
 pub mod types;
+pub mod utterance;
 pub mod vad;
+pub mod voice_detector;
+pub mod watchdog;
+#[cfg(windows)]
+pub mod wasapi_loopback;
 
 pub use capture::AudioCaptureManager;
+pub use channel_map::ChannelMap;
 pub use device::list_devices;
-pub use types::DeviceInfo;
+pub use dsp::DspConfig;
+pub use events::{CaptureEvent, DeviceChange};
+pub use file_source::FileSource;
+pub use health::{AudioHealth, HealthIncident, HealthIssue, HealthState};
+pub use levels::{AudioLevels, SourceLevel};
+pub use mixer::MixConfig;
+pub use recorder::WavRecorder;
+pub use source::AudioSource;
+pub use types::{
+    DeviceInfo, DeviceSelection, DroppedFrames, RecordingMode, SourceFormat, SourceRole,
+};
+pub use utterance::{Utterance, UtteranceBuilder};
 pub use vad::{EnergyVad, SpeechBuffer, VadConfig, VadEvent};
+pub use voice_detector::{create_detector, VadBackend, VoiceDetector};
@@ -0,0 +1,216 @@
+use crate::audio::vad::{VadConfig, VadEvent};
+use crate::audio::voice_detector::{Endpointer, VoiceDetector};
+use anyhow::{Context, Result};
+use ort::session::Session;
+use ort::value::Tensor;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Samples per Silero window at 16kHz (32ms).
+const WINDOW: usize = 512;
+/// Samples of the previous window the model expects in front of each window.
+const CONTEXT: usize = 64;
+/// Recurrent state shape: [2, 1, 128].
+const STATE_LEN: usize = 2 * 128;
+/// Probability must fall this far below the speech threshold to end speech.
+const HYSTERESIS: f32 = 0.15;
+
+/// Default Silero model location: {app_data}/models/vad/silero_vad.onnx
+pub fn default_model_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir
+        .join("models")
+        .join("vad")
+        .join("silero_vad.onnx")
+}
+
+/// Scores fixed windows of 16kHz audio with a speech probability.
+pub trait SpeechModel: Send {
+    fn probability(&mut self, window: &[f32]) -> Result<f32>;
+    fn reset(&mut self);
+}
+
+/// Silero VAD v5 ONNX model on CPU. ONNX Runtime is loaded from the system
+/// (or `ORT_DYLIB_PATH`) when the first model opens.
+pub struct SileroModel {
+    session: Session,
+    state: Vec<f32>,
+    context: Vec<f32>,
+}
+
+impl SileroModel {
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            anyhow::bail!("VAD model not found: {}", path.display());
+        }
+        // ort panics instead of erroring when the runtime library is missing
+        let session = catch_unwind(AssertUnwindSafe(|| {
+            Session::builder()?
+                .with_intra_threads(1)?
+                .commit_from_file(path)
+        }))
+        .map_err(|_| anyhow::anyhow!("ONNX Runtime library could not be loaded"))?
+        .with_context(|| format!("Failed to load VAD model {}", path.display()))?;
+
+        tracing::info!("Silero VAD loaded from {}", path.display());
+        Ok(Self {
+            session,
+            state: vec![0.0; STATE_LEN],
+            context: vec![0.0; CONTEXT],
+        })
+    }
+}
+
+impl SpeechModel for SileroModel {
+    fn probability(&mut self, window: &[f32]) -> Result<f32> {
+        let mut input = Vec::with_capacity(CONTEXT + window.len());
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(window);
+        self.context = window[window.len() - CONTEXT..].to_vec();
+
+        let input = Tensor::from_array(([1, input.len()], input))?;
+        let state = Tensor::from_array(([2, 1, 128], self.state.clone()))?;
+        let sr = Tensor::from_array(((), vec![16000i64]))?;
+        let outputs = self
+            .session
+            .run(ort::inputs!["input" => input, "state" => state, "sr" => sr])?;
+
+        let (_, prob) = outputs[0].try_extract_tensor::<f32>()?;
+        let (_, state) = outputs[1].try_extract_tensor::<f32>()?;
+        self.state.copy_from_slice(state);
+        prob.first()
+            .copied()
+            .context("VAD model returned no probability")
+    }
+
+    fn reset(&mut self) {
+        self.state.fill(0.0);
+        self.context.fill(0.0);
+    }
+}
+
+/// Voice detector driven by a speech-probability model. Frames are collected into
+/// model windows; speech starts above `speech_threshold` and ends once the
+/// probability drops `HYSTERESIS` below it.
+pub struct NeuralVad<M: SpeechModel> {
+    model: M,
+    pending: Vec<f32>,
+    threshold: f32,
+    speaking: bool,
+    endpointer: Endpointer,
+    reported_error: bool,
+}
+
+pub type SileroVad = NeuralVad<SileroModel>;
+
+impl SileroVad {
+    pub fn open(path: &Path, config: &VadConfig) -> Result<Self> {
+        Ok(Self::new(SileroModel::open(path)?, config))
+    }
+}
+
+impl<M: SpeechModel> NeuralVad<M> {
+    pub fn new(model: M, config: &VadConfig) -> Self {
+        Self {
+            model,
+            pending: Vec::with_capacity(WINDOW * 2),
+            threshold: config.speech_threshold,
+            speaking: false,
+            endpointer: Endpointer::new(config.silence_limit),
+            reported_error: false,
+        }
+    }
+}
+
+impl<M: SpeechModel> VoiceDetector for NeuralVad<M> {
+    fn process_frame(&mut self, samples: &[f32]) -> VadEvent {
+        self.pending.extend_from_slice(samples);
+        while self.pending.len() >= WINDOW {
+            let window: Vec<f32> = self.pending.drain(..WINDOW).collect();
+            match self.model.probability(&window) {
+                Ok(p) if self.speaking => self.speaking = p >= self.threshold - HYSTERESIS,
+                Ok(p) => self.speaking = p >= self.threshold,
+                Err(e) if !self.reported_error => {
+                    tracing::warn!("VAD model inference failed: {}", e);
+                    self.reported_error = true;
+                }
+                Err(_) => {}
+            }
+        }
+        self.endpointer.update(self.speaking)
+    }
+
+    fn reset(&mut self) {
+        self.model.reset();
+        self.pending.clear();
+        self.speaking = false;
+        self.endpointer.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the window's first sample as its speech probability.
+    struct FakeModel {
+        windows: usize,
+    }
+
+    impl SpeechModel for FakeModel {
+        fn probability(&mut self, window: &[f32]) -> Result<f32> {
+            assert_eq!(window.len(), WINDOW);
+            self.windows += 1;
+            Ok(window[0])
+        }
+
+        fn reset(&mut self) {
+            self.windows = 0;
+        }
+    }
+
+    fn vad(silence_limit: usize) -> NeuralVad<FakeModel> {
+        let config = VadConfig {
+            silence_limit,
+            ..VadConfig::default()
+        };
+        NeuralVad::new(FakeModel { windows: 0 }, &config)
+    }
+
+    /// Feed `frames` 10ms frames all filled with `value`.
+    fn feed(v: &mut NeuralVad<FakeModel>, value: f32, frames: usize) -> Vec<VadEvent> {
+        (0..frames)
+            .map(|_| v.process_frame(&[value; 160]))
+            .collect()
+    }
+
+    #[test]
+    fn speech_holds_through_hysteresis_then_ends() {
+        let mut v = vad(10);
+        // 512-sample windows complete on the 4th, 7th, 10th... frame
+        assert!(feed(&mut v, 0.9, 4).ends_with(&[VadEvent::Speech]));
+        // 0.4 sits between the on (0.5) and off (0.35) thresholds
+        assert!(feed(&mut v, 0.4, 20).iter().all(|&e| e == VadEvent::Speech));
+        let events = feed(&mut v, 0.1, 20);
+        assert_eq!(
+            events.iter().filter(|&&e| e == VadEvent::SpeechEnd).count(),
+            1
+        );
+        assert_eq!(v.model.windows, 13);
+    }
+
+    #[test]
+    fn middling_probability_does_not_start_speech() {
+        let mut v = vad(10);
+        assert!(feed(&mut v, 0.4, 30)
+            .iter()
+            .all(|&e| e == VadEvent::Silence));
+    }
+
+    #[test]
+    #[ignore = "requires ONNX Runtime and a Silero model at $SILERO_VAD_MODEL"]
+    fn silero_model_scores_silence_low() {
+        let path = PathBuf::from(std::env::var("SILERO_VAD_MODEL").unwrap());
+        let mut model = SileroModel::open(&path).unwrap();
+        assert!(model.probability(&[0.0; WINDOW]).unwrap() < 0.5);
+    }
+}
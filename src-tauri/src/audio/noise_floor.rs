@@ -0,0 +1,66 @@
+/// Lowest floor tracked, so digital silence cannot drive the threshold to zero.
+pub const MIN_FLOOR: f32 = 0.0005;
+/// How far the floor moves toward a quieter frame, per frame.
+const FALL: f32 = 0.1;
+/// Per-frame growth while frames are louder: ~10s to rise 2.7x at 10ms frames,
+/// so speech barely moves it but a noisier room is picked up within seconds.
+const RISE: f32 = 1.001;
+
+/// Running estimate of the background level (frame RMS) of a stream.
+/// Falls quickly in pauses and creeps up slowly, never above the current frame.
+#[derive(Debug, Clone)]
+pub struct NoiseFloor {
+    level: f32,
+}
+
+impl NoiseFloor {
+    pub fn new(initial: f32) -> Self {
+        Self {
+            level: initial.max(MIN_FLOOR),
+        }
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Feed one frame's RMS and return the updated floor.
+    pub fn update(&mut self, rms: f32) -> f32 {
+        if rms < self.level {
+            self.level += (rms - self.level) * FALL;
+        } else {
+            self.level = (self.level * RISE).min(rms);
+        }
+        self.level = self.level.max(MIN_FLOOR);
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_to_quiet_room_quickly() {
+        let mut floor = NoiseFloor::new(0.02);
+        for _ in 0..100 {
+            floor.update(0.002);
+        }
+        assert!((floor.level() - 0.002).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rises_slowly_with_speech_but_follows_louder_room() {
+        let mut floor = NoiseFloor::new(0.005);
+        // 1s of speech
+        for _ in 0..100 {
+            floor.update(0.2);
+        }
+        assert!(floor.level() < 0.006);
+        // 20s of office noise
+        for _ in 0..2000 {
+            floor.update(0.03);
+        }
+        assert!(floor.level() > 0.029);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::audio::device;
+use crate::audio::ring::RingWriter;
+use crate::audio::source::AudioSource;
+use crate::audio::types::SourceFormat;
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Suffix PulseAudio gives the monitor source of every sink.
+const MONITOR_SUFFIX: &str = ".monitor";
+
+/// Bytes per read from `parec` (~10ms of 48kHz stereo f32).
+const READ_CHUNK_BYTES: usize = 3840;
+
+/// A sink monitor source that can be recorded as system audio.
+/// Works with PulseAudio and PipeWire (via pipewire-pulse); talks to the sound server
+/// through the `pactl` / `parec` CLI tools so no native PulseAudio bindings are linked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorSource {
+    pub name: String,
+    pub format: SourceFormat,
+}
+
+/// List sink monitor sources via `pactl list short sources`.
+pub fn list_monitor_sources() -> Result<Vec<MonitorSource>> {
+    let output = run_pactl(&["list", "short", "sources"])?;
+    Ok(parse_short_sources(&output))
+}
+
+/// Monitor source of the default sink.
+pub fn default_monitor_source() -> Result<MonitorSource> {
+    let sink = run_pactl(&["get-default-sink"])?;
+    let name = format!("{}{}", sink.trim(), MONITOR_SUFFIX);
+    find_monitor_source(&name)
+}
+
+/// Find a monitor source by name; fails if it no longer exists.
+pub fn find_monitor_source(name: &str) -> Result<MonitorSource> {
+    list_monitor_sources()?
+        .into_iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Loopback device '{}' not found (sink removed?)", name))
+}
+
+/// System audio loopback capture of a sink monitor (Linux only).
+pub struct PulseLoopbackSource {
+    source: MonitorSource,
+    failed: Arc<AtomicBool>,
+}
+
+impl PulseLoopbackSource {
+    /// Open the requested loopback device (`DeviceInfo.id`), or the default sink's monitor.
+    pub fn open(device_id: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            source: device::resolve_loopback_source(device_id)?,
+            failed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+impl AudioSource for PulseLoopbackSource {
+    fn id(&self) -> String {
+        format!("{}{}", device::LOOPBACK_ID_PREFIX, self.source.name)
+    }
+
+    fn format(&self) -> SourceFormat {
+        self.source.format
+    }
+
+    fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    fn start(&mut self, ring: RingWriter, is_running: Arc<AtomicBool>) -> Result<()> {
+        let source = self.source.clone();
+        let failed = self.failed.clone();
+
+        thread::Builder::new()
+            .name("pulse-loopback".to_string())
+            .spawn(move || {
+                if let Err(e) = capture_thread(source, ring, is_running.clone()) {
+                    tracing::error!("PulseAudio loopback error: {}", e);
+                }
+                // Exiting while still wanted means the device or server went away
+                if is_running.load(Ordering::SeqCst) {
+                    failed.store(true, Ordering::SeqCst);
+                }
+            })?;
+
+        tracing::info!("PulseAudio loopback thread started on '{}'", self.source.name);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // The capture thread kills parec on its own once `is_running` is cleared
+    }
+}
+
+/// Record a monitor source with `parec` and decode f32 samples into `ring`
+/// until `is_running` is cleared. Blocks the calling thread.
+fn capture_thread(
+    source: MonitorSource,
+    ring: RingWriter,
+    is_running: Arc<AtomicBool>,
+) -> Result<()> {
+    let mut child = Command::new("parec")
+        .arg(format!("--device={}", source.name))
+        .arg("--format=float32le")
+        .arg(format!("--rate={}", source.format.sample_rate))
+        .arg(format!("--channels={}", source.format.channels))
+        .arg("--latency-msec=20")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to start parec (is pulseaudio-utils installed?)")?;
+
+    let mut stdout = child.stdout.take().context("parec stdout not captured")?;
+    tracing::info!("PulseAudio loopback stream started on '{}'", source.name);
+
+    // Keep reads aligned to whole frames so every chunk decodes cleanly
+    let frame_bytes = 4 * source.format.channels.max(1) as usize;
+    let chunk_bytes = (READ_CHUNK_BYTES / frame_bytes).max(1) * frame_bytes;
+    let mut buf = vec![0u8; chunk_bytes];
+
+    while is_running.load(Ordering::SeqCst) {
+        match stdout.read_exact(&mut buf) {
+            Ok(()) => {
+                ring.push_fn(buf.len() / 4, |i| {
+                    f32::from_le_bytes([buf[i * 4], buf[i * 4 + 1], buf[i * 4 + 2], buf[i * 4 + 3]])
+                });
+            }
+            Err(e) => {
+                tracing::warn!("parec stream ended: {}", e);
+                break;
+            }
+        }
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+    tracing::info!("PulseAudio loopback thread exiting");
+    Ok(())
+}
+
+fn run_pactl(args: &[&str]) -> Result<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .output()
+        .context("Failed to run pactl (is pulseaudio-utils installed?)")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "pactl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `pactl list short sources`, keeping only sink monitors.
+/// Line format: `ID\tNAME\tDRIVER\tSAMPLE_SPEC\tSTATE`.
+fn parse_short_sources(output: &str) -> Vec<MonitorSource> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split('\t').collect();
+            let name = *cols.get(1)?;
+            if !name.ends_with(MONITOR_SUFFIX) {
+                return None;
+            }
+            Some(MonitorSource {
+                name: name.to_string(),
+                format: parse_sample_spec(cols.get(3)?)?,
+            })
+        })
+        .collect()
+}
+
+/// Parse a PulseAudio sample spec such as `s16le 2ch 44100Hz`.
+fn parse_sample_spec(spec: &str) -> Option<SourceFormat> {
+    let mut channels = None;
+    let mut sample_rate = None;
+    for part in spec.split_whitespace() {
+        if let Some(ch) = part.strip_suffix("ch") {
+            channels = ch.parse().ok();
+        } else if let Some(hz) = part.strip_suffix("Hz") {
+            sample_rate = hz.parse().ok();
+        }
+    }
+    Some(SourceFormat {
+        sample_rate: sample_rate?,
+        channels: channels?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring::sample_ring;
+
+    const SOURCES: &str = "\
+47\talsa_output.pci-0000_00_1f.3.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tSUSPENDED
+48\talsa_input.pci-0000_00_1f.3.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING
+52\trt_null.monitor\tmodule-null-sink.c\tfloat32le 1ch 44100Hz\tIDLE
+";
+
+    #[test]
+    fn parses_only_monitor_sources() {
+        let sources = parse_short_sources(SOURCES);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].name, "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor");
+        assert_eq!(
+            sources[1].format,
+            SourceFormat {
+                sample_rate: 44100,
+                channels: 1
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_sample_spec() {
+        assert!(parse_sample_spec("s16le 48000Hz").is_none());
+        assert!(parse_short_sources("1\tbroken.monitor").is_empty());
+    }
+
+    /// Headless end-to-end check against a PulseAudio/PipeWire null sink.
+    /// Run with `cargo test -- --ignored` on a machine with a sound server.
+    #[test]
+    #[ignore = "requires a running PulseAudio or PipeWire server"]
+    fn captures_from_null_sink_monitor() {
+        let module = run_pactl(&[
+            "load-module",
+            "module-null-sink",
+            "sink_name=rt_translator_test",
+        ])
+        .unwrap();
+
+        let mut source = PulseLoopbackSource::open(Some("loopback:rt_translator_test.monitor"))
+            .unwrap();
+        let (ring, mut rx) = sample_ring(48000, source.format().channels);
+        let is_running = Arc::new(AtomicBool::new(true));
+        source.start(ring, is_running.clone()).unwrap();
+
+        let mut chunk = vec![0.0; 4800];
+        let n = rx.recv_timeout(&mut chunk, std::time::Duration::from_secs(5)).unwrap();
+        chunk.truncate(n);
+        is_running.store(false, Ordering::SeqCst);
+        source.stop();
+        let _ = run_pactl(&["unload-module", module.trim()]);
+
+        assert!(!chunk.is_empty());
+        assert_eq!(chunk.len() % source.format().channels as usize, 0);
+    }
+}
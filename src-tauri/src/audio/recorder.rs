@@ -0,0 +1,202 @@
+use crate::audio::types::SourceFormat;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+
+/// Writes a capture stream to a 16-bit PCM WAV file.
+/// The header is updated about once per second of audio, so a recording can be
+/// read while it is still being written, and finalized on `finish` (or on drop,
+/// ignoring errors).
+pub struct WavRecorder {
+    writer: hound::WavWriter<BufWriter<File>>,
+    path: PathBuf,
+    channels: u16,
+    frames: u64,
+    /// Frames between header updates (one second).
+    flush_every: u64,
+    flushed: u64,
+}
+
+impl WavRecorder {
+    /// Create the file (and its parent directory) for interleaved samples in `format`.
+    pub fn create(path: &Path, format: SourceFormat) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create recordings dir {:?}", dir))?;
+        }
+        let spec = hound::WavSpec {
+            channels: format.channels,
+            sample_rate: format.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .with_context(|| format!("Failed to create recording {:?}", path))?;
+
+        tracing::info!("Recording to {:?}", path);
+        Ok(Self {
+            writer,
+            path: path.to_path_buf(),
+            channels: format.channels.max(1),
+            frames: 0,
+            flush_every: format.sample_rate.max(1) as u64,
+            flushed: 0,
+        })
+    }
+
+    /// Append interleaved f32 samples, clamped to [-1.0, 1.0].
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for &s in samples {
+            let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_sample(v)?;
+        }
+        self.frames += (samples.len() / self.channels as usize) as u64;
+        if self.frames - self.flushed >= self.flush_every {
+            self.writer.flush()?;
+            self.flushed = self.frames;
+        }
+        Ok(())
+    }
+
+    /// Frames written so far; also the frame offset of the next `write`.
+    pub fn frames_written(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flush and finalize the WAV header.
+    pub fn finish(self) -> Result<()> {
+        let path = self.path.clone();
+        self.writer
+            .finalize()
+            .with_context(|| format!("Failed to finalize recording {:?}", path))?;
+        tracing::info!("Recording saved: {:?}", path);
+        Ok(())
+    }
+}
+
+/// Directory under the app data dir holding meeting recordings.
+pub fn recordings_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("recordings")
+}
+
+/// `meeting-<id>.wav` for the mixed stream, `meeting-<id>-<source>.wav` per source.
+pub fn meeting_recording_path(dir: &Path, meeting_id: i64, source: Option<&str>) -> PathBuf {
+    match source {
+        Some(source) => dir.join(format!("meeting-{}-{}.wav", meeting_id, source)),
+        None => dir.join(format!("meeting-{}.wav", meeting_id)),
+    }
+}
+
+/// Cut frames `[start, end)` out of a recording, widened by `padding_ms` on each side
+/// (clamped to the file), and re-encode them as a standalone WAV.
+pub fn read_clip_wav(path: &Path, start: u64, end: u64, padding_ms: u32) -> Result<Vec<u8>> {
+    let mut reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open recording {:?}", path))?;
+    let spec = reader.spec();
+    let total = reader.duration() as u64;
+    if start >= total || end <= start {
+        anyhow::bail!("Clip {}..{} is outside the recording ({} frames)", start, end, total);
+    }
+
+    let padding = padding_ms as u64 * spec.sample_rate as u64 / 1000;
+    let from = start.saturating_sub(padding);
+    let to = (end + padding).min(total);
+    reader.seek(from as u32)?;
+
+    let mut bytes = Vec::new();
+    let mut writer = hound::WavWriter::new(Cursor::new(&mut bytes), spec)?;
+    let count = ((to - from) * spec.channels as u64) as usize;
+    for sample in reader.samples::<i16>().take(count) {
+        writer.write_sample(sample?)?;
+    }
+    writer.finalize()?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::file_source::read_wav_samples;
+
+    #[test]
+    fn records_and_reads_back_frames() {
+        let dir = std::env::temp_dir().join(format!("rt-rec-{}", uuid::Uuid::new_v4()));
+        let path = meeting_recording_path(&dir, 7, Some("mic"));
+        assert!(path.ends_with("meeting-7-mic.wav"));
+
+        let format = SourceFormat {
+            sample_rate: 16000,
+            channels: 2,
+        };
+        let mut recorder = WavRecorder::create(&path, format).unwrap();
+        recorder.write(&[0.5, -0.5, 2.0, 0.0]).unwrap();
+        assert_eq!(recorder.frames_written(), 2);
+        recorder.finish().unwrap();
+
+        let samples = read_wav_samples(&path).unwrap();
+        assert_eq!(samples.len(), 4);
+        assert!((samples[0] - 0.5).abs() < 1e-3);
+        assert!((samples[2] - 1.0).abs() < 1e-3); // clamped
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn clips_are_padded_and_clamped() {
+        let dir = std::env::temp_dir().join(format!("rt-clip-{}", uuid::Uuid::new_v4()));
+        let path = meeting_recording_path(&dir, 1, None);
+        let format = SourceFormat {
+            sample_rate: 16000,
+            channels: 1,
+        };
+        // 1s ramp so each frame's position is recoverable from its value
+        let mut recorder = WavRecorder::create(&path, format).unwrap();
+        let ramp: Vec<f32> = (0..16000).map(|i| i as f32 / 16000.0).collect();
+        recorder.write(&ramp).unwrap();
+        recorder.finish().unwrap();
+
+        // 100ms padding = 1600 frames on each side
+        let clip = read_clip_wav(&path, 8000, 9600, 100).unwrap();
+        let reader = hound::WavReader::new(Cursor::new(clip)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16000);
+        assert_eq!(reader.duration(), 4800);
+
+        // Padding past the end of the file is clamped
+        let clip = read_clip_wav(&path, 15000, 16000, 100).unwrap();
+        let mut reader = hound::WavReader::new(Cursor::new(clip)).unwrap();
+        assert_eq!(reader.duration(), 2600);
+        let first = reader.samples::<i16>().next().unwrap().unwrap();
+        assert!((first as f32 / i16::MAX as f32 - 13400.0 / 16000.0).abs() < 1e-3);
+
+        assert!(read_clip_wav(&path, 20000, 21000, 0).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn clips_are_readable_while_recording() {
+        let dir = std::env::temp_dir().join(format!("rt-live-{}", uuid::Uuid::new_v4()));
+        let path = meeting_recording_path(&dir, 2, None);
+        let format = SourceFormat {
+            sample_rate: 16000,
+            channels: 1,
+        };
+        let mut recorder = WavRecorder::create(&path, format).unwrap();
+        recorder.write(&[0.25; 20000]).unwrap();
+        recorder.write(&[0.25; 4000]).unwrap();
+
+        // The header covers audio up to its last update, a second or less ago
+        let clip = read_clip_wav(&path, 1000, 2000, 0).unwrap();
+        let reader = hound::WavReader::new(Cursor::new(clip)).unwrap();
+        assert_eq!(reader.duration(), 1000);
+        assert!(read_clip_wav(&path, 21000, 22000, 0).is_err());
+
+        recorder.write(&[0.25; 16000]).unwrap();
+        assert!(read_clip_wav(&path, 21000, 22000, 0).is_ok());
+        recorder.finish().unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
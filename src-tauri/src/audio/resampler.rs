@@ -1,15 +1,57 @@
 use anyhow::Result;
-use rubato::audioadapter::Adapter;
-use rubato::{Fft, FixedSync, Resampler};
+use audioadapter_buffers::direct::InterleavedSlice;
+use rubato::{
+    Async, Fft, FixedAsync, FixedSync, Indexing, Resampler, SincInterpolationParameters,
+    SincInterpolationType, WindowFunction,
+};
+use serde::{Deserialize, Serialize};
 
-/// Wraps rubato's FFT resampler for audio rate conversion and channel downmix.
+/// Resampling strategy for the STT input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResamplerKind {
+    /// FFT resampler on 1024-frame chunks: cheapest per sample, ~25ms of buffering.
+    #[default]
+    Fft,
+    /// Windowed-sinc (polyphase) resampler on 160-frame chunks for live captions:
+    /// ~4ms of buffering at 48kHz.
+    Sinc,
+}
+
+impl ResamplerKind {
+    /// Input frames per process call.
+    pub fn chunk_size(self) -> usize {
+        match self {
+            ResamplerKind::Fft => 1024,
+            ResamplerKind::Sinc => 160,
+        }
+    }
+}
+
+/// Sinc filter for `ResamplerKind::Sinc`. Tuned for 48k→16k: every output sample
+/// lands on an input sample (ratio 1/3), so a short filter and linear interpolation
+/// between precomputed phases stay exact, with a cutoff just under 8kHz.
+const SINC_PARAMETERS: SincInterpolationParameters = SincInterpolationParameters {
+    sinc_len: 64,
+    f_cutoff: 0.925,
+    oversampling_factor: 96,
+    interpolation: SincInterpolationType::Linear,
+    window: WindowFunction::BlackmanHarris2,
+};
+
+/// Wraps a rubato resampler (FFT or sinc) for audio rate conversion and channel downmix.
 pub struct AudioResampler {
-    resampler: Fft<f32>,
+    resampler: Box<dyn Resampler<f32>>,
     input_channels: usize,
+    /// Interleaved resampler output, sized once for the largest chunk.
+    scratch: Vec<f32>,
+    /// Frames consumed and produced since creation or the last flush.
+    frames_in: usize,
+    frames_out: usize,
 }
 
 impl AudioResampler {
-    /// Create a new resampler.
+    /// Create a new FFT resampler.
     /// `chunk_size` is the number of frames per channel per processing call.
     pub fn new(
         input_rate: u32,
@@ -25,56 +67,115 @@ impl AudioResampler {
             channels,
             FixedSync::Input,
         )?;
+        Ok(Self::wrap(Box::new(resampler), channels))
+    }
 
-        Ok(Self {
+    /// Create a resampler of the given kind with its own chunk size.
+    pub fn with_kind(
+        kind: ResamplerKind,
+        input_rate: u32,
+        output_rate: u32,
+        channels: usize,
+    ) -> Result<Self> {
+        match kind {
+            ResamplerKind::Fft => Self::new(input_rate, output_rate, channels, kind.chunk_size()),
+            ResamplerKind::Sinc => {
+                let resampler = Async::<f32>::new_sinc(
+                    output_rate as f64 / input_rate as f64,
+                    1.0,
+                    &SINC_PARAMETERS,
+                    kind.chunk_size(),
+                    channels,
+                    FixedAsync::Input,
+                )?;
+                Ok(Self::wrap(Box::new(resampler), channels))
+            }
+        }
+    }
+
+    fn wrap(resampler: Box<dyn Resampler<f32>>, channels: usize) -> Self {
+        let scratch = vec![0.0; resampler.output_frames_max() * channels];
+        Self {
             resampler,
             input_channels: channels,
-        })
-    }
-
-    /// Resample interleaved stereo input to mono output.
-    /// Input: interleaved [L0, R0, L1, R1, ...] with `chunk_size` frames.
-    /// Output: mono resampled samples.
-    pub fn process_stereo_to_mono(&mut self, input: &[f32]) -> Result<Vec<f32>> {
-        let frames = input.len() / 2;
-        let left: Vec<f32> = input.iter().step_by(2).copied().collect();
-        let right: Vec<f32> = input.iter().skip(1).step_by(2).copied().collect();
-        let planar = vec![left, right];
-
-        let resampled = self.resampler.process(
-            &audioadapter_buffers::direct::SequentialSliceOfVecs::new(&planar, 2, frames)?,
-            0,
-            None,
-        )?;
+            scratch,
+            frames_in: 0,
+            frames_out: 0,
+        }
+    }
+
+    /// Resample one chunk of interleaved input ([c0, c1, ..., cN-1, c0, ...] with
+    /// `input_frames_next()` frames) and append the average of all channels to `out`.
+    /// Works in place on the input and reused buffers: no allocation once `out` has grown.
+    pub fn process_into(&mut self, input: &[f32], out: &mut Vec<f32>) -> Result<()> {
+        let written = self.process_chunk(input, None)?;
+        self.frames_in += input.len() / self.input_channels;
+        self.emit_mono(written, out);
+        Ok(())
+    }
 
-        // Average L+R channels to mono using Adapter trait
-        let out_frames = resampled.frames();
-        let mut mono = Vec::with_capacity(out_frames);
-        for i in 0..out_frames {
-            let l = resampled.read_sample(0, i).unwrap_or(0.0);
-            let r = resampled.read_sample(1, i).unwrap_or(0.0);
-            mono.push((l + r) / 2.0);
+    /// Resample the final, shorter-than-a-chunk `partial` input and drain the filter,
+    /// appending everything still owed to `out` so the stream's tail is not lost.
+    /// The resampler then starts over as if new.
+    pub fn flush_into(&mut self, partial: &[f32], out: &mut Vec<f32>) -> Result<()> {
+        self.frames_in += partial.len() / self.input_channels;
+        let owed = (self.frames_in as f64 * self.resampler.resample_ratio()).ceil() as usize
+            + self.resampler.output_delay();
+        let mut input = partial;
+        while self.frames_out < owed {
+            let frames = input.len() / self.input_channels;
+            let indexing = Indexing {
+                input_offset: 0,
+                output_offset: 0,
+                partial_len: Some(frames),
+                active_channels_mask: None,
+            };
+            let written = self.process_chunk(input, Some(&indexing))?;
+            if written == 0 {
+                break;
+            }
+            self.emit_mono(written.min(owed - self.frames_out), out);
+            // Later passes only push the padding silence through the filter
+            input = &[];
         }
-        Ok(mono)
+        self.resampler.reset();
+        self.frames_in = 0;
+        self.frames_out = 0;
+        Ok(())
     }
 
-    /// Resample mono input to mono output.
-    pub fn process_mono(&mut self, input: &[f32]) -> Result<Vec<f32>> {
-        let frames = input.len();
-        let planar = vec![input.to_vec()];
+    /// Resampler output frames that precede the first input frame (filter delay).
+    pub fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
 
-        let resampled = self.resampler.process(
-            &audioadapter_buffers::direct::SequentialSliceOfVecs::new(&planar, 1, frames)?,
-            0,
-            None,
+    fn process_chunk(&mut self, input: &[f32], indexing: Option<&Indexing>) -> Result<usize> {
+        let ch = self.input_channels;
+        let out_frames = self.scratch.len() / ch;
+        let (_, written) = self.resampler.process_into_buffer(
+            &InterleavedSlice::new(input, ch, input.len() / ch)?,
+            &mut InterleavedSlice::new_mut(&mut self.scratch, ch, out_frames)?,
+            indexing,
         )?;
+        Ok(written)
+    }
 
-        let out_frames = resampled.frames();
-        let mut mono = Vec::with_capacity(out_frames);
-        for i in 0..out_frames {
-            mono.push(resampled.read_sample(0, i).unwrap_or(0.0));
-        }
-        Ok(mono)
+    /// Append the channel average of the first `frames` output frames to `out`.
+    fn emit_mono(&mut self, frames: usize, out: &mut Vec<f32>) {
+        let ch = self.input_channels;
+        out.extend(
+            self.scratch[..frames * ch]
+                .chunks_exact(ch)
+                .map(|frame| frame.iter().sum::<f32>() / ch as f32),
+        );
+        self.frames_out += frames;
+    }
+
+    /// Resample interleaved input to a new mono buffer (see `process_into`).
+    pub fn process_to_mono(&mut self, input: &[f32]) -> Result<Vec<f32>> {
+        let mut out = Vec::new();
+        self.process_into(input, &mut out)?;
+        Ok(out)
     }
 
     /// Number of input frames required per process call.
@@ -110,6 +211,52 @@ mod tests {
         assert!((mixed[2] - 0.9).abs() < f32::EPSILON);
     }
 
+    #[test]
+    fn resamples_multichannel_to_mono() {
+        // 6-channel 48kHz with signal on one channel only
+        let mut rs = AudioResampler::new(48000, 16000, 6, 1024).unwrap();
+        let mut input = vec![0.0f32; 1024 * 6];
+        for frame in input.chunks_exact_mut(6) {
+            frame[2] = 0.6;
+        }
+        let mut out = Vec::new();
+        for _ in 0..4 {
+            out.extend(rs.process_to_mono(&input).unwrap());
+        }
+        assert!(out.len() > 1000);
+        // Past the filter warm-up, the level is the channel average
+        assert!((out[out.len() - 1] - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn sinc_buffers_less_than_fft() {
+        let fft = AudioResampler::with_kind(ResamplerKind::Fft, 48000, 16000, 1).unwrap();
+        let sinc = AudioResampler::with_kind(ResamplerKind::Sinc, 48000, 16000, 1).unwrap();
+        // Chunk plus filter delay, in 48kHz input frames
+        let latency = |rs: &AudioResampler| rs.input_frames_next() + rs.output_delay() * 3;
+        assert!(latency(&sinc) * 4 < latency(&fft));
+    }
+
+    #[test]
+    fn flush_emits_the_tail_of_a_partial_chunk() {
+        for kind in [ResamplerKind::Fft, ResamplerKind::Sinc] {
+            let mut rs = AudioResampler::with_kind(kind, 48000, 16000, 2).unwrap();
+            let chunk = rs.input_frames_next();
+            // Two full chunks and 100 frames more, constant 0.5
+            let input = vec![0.5f32; (chunk * 2 + 100) * 2];
+            let mut out = Vec::new();
+            for block in input.chunks(chunk * 2).filter(|b| b.len() == chunk * 2) {
+                rs.process_into(block, &mut out).unwrap();
+            }
+            rs.flush_into(&input[chunk * 4..], &mut out).unwrap();
+
+            let delay = rs.output_delay();
+            let tail = &out[delay..];
+            assert_eq!(tail.len(), (chunk * 2 + 100).div_ceil(3), "{:?}", kind);
+            assert!((tail[tail.len() - 20] - 0.5).abs() < 0.01, "{:?}", kind);
+        }
+    }
+
     #[test]
     fn mix_audio_clamps_overflow() {
         let a = vec![1.0];
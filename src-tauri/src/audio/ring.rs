@@ -0,0 +1,338 @@
+use crossbeam::channel::RecvTimeoutError;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Longest a waiting reader sleeps before re-checking, in case a wakeup was missed.
+const MAX_PARK: Duration = Duration::from_millis(5);
+
+/// Preallocated single-producer / single-consumer ring of interleaved f32 samples.
+/// Writes and reads move whole frames, never allocate and never block the writer.
+/// `capacity_frames` is rounded up so the ring holds at least one frame.
+pub fn sample_ring(capacity_frames: usize, channels: u16) -> (RingWriter, RingReader) {
+    let channels = channels.max(1) as usize;
+    let capacity = capacity_frames.max(1) * channels;
+    let shared = Arc::new(Shared {
+        buf: (0..capacity).map(|_| UnsafeCell::new(0.0)).collect(),
+        channels,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        writing: AtomicBool::new(false),
+        writers: AtomicUsize::new(1),
+        reader_gone: AtomicBool::new(false),
+        dropped_frames: AtomicU64::new(0),
+        reader: OnceLock::new(),
+    });
+    (
+        RingWriter {
+            shared: shared.clone(),
+        },
+        RingReader { shared },
+    )
+}
+
+struct Shared {
+    buf: Box<[UnsafeCell<f32>]>,
+    channels: usize,
+    /// Total samples read; only the reader stores it.
+    head: AtomicUsize,
+    /// Total samples written; only the writer holding `writing` stores it.
+    tail: AtomicUsize,
+    /// Claimed by the writer currently pushing. Clones of one writer (e.g. an old and a
+    /// replacement device during hot-swap) never write concurrently; the loser drops.
+    writing: AtomicBool,
+    writers: AtomicUsize,
+    reader_gone: AtomicBool,
+    dropped_frames: AtomicU64,
+    reader: OnceLock<Thread>,
+}
+
+// Samples are only touched by the claimed writer (free region) and the reader
+// (filled region); `head` / `tail` hand regions over with acquire/release.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn filled(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    /// Mutable view of `len` samples starting at ring index `start` (no wrap).
+    ///
+    /// # Safety
+    /// Caller must own the region: the writer its free space, the reader its filled space.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slice_mut(&self, start: usize, len: usize) -> &mut [f32] {
+        let ptr = self.buf.as_ptr().add(start) as *mut f32;
+        std::slice::from_raw_parts_mut(ptr, len)
+    }
+
+    fn wake_reader(&self) {
+        if let Some(reader) = self.reader.get() {
+            reader.unpark();
+        }
+    }
+}
+
+/// Writing end of a `sample_ring`. Cloneable so a replacement source can take over.
+pub struct RingWriter {
+    shared: Arc<Shared>,
+}
+
+impl RingWriter {
+    /// Whether this writer fills `reader`'s ring.
+    pub fn feeds(&self, reader: &RingReader) -> bool {
+        Arc::ptr_eq(&self.shared, &reader.shared)
+    }
+
+    /// Append `samples` (whole frames) if they fit entirely; otherwise drop them,
+    /// count the dropped frames and return false.
+    pub fn push(&self, samples: &[f32]) -> bool {
+        self.write(samples.len(), |dst, offset| {
+            dst.copy_from_slice(&samples[offset..offset + dst.len()])
+        })
+    }
+
+    /// Like `push`, with sample `i` of `len` produced by `sample(i)`. Lets callbacks
+    /// convert or remap samples straight into the ring.
+    pub fn push_fn(&self, len: usize, mut sample: impl FnMut(usize) -> f32) -> bool {
+        self.write(len, |dst, offset| {
+            for (i, d) in dst.iter_mut().enumerate() {
+                *d = sample(offset + i);
+            }
+        })
+    }
+
+    /// Append all of `samples`, waiting for space (back-pressure for non-realtime
+    /// sources). Returns false if `is_running` clears or the reader is gone first.
+    pub fn push_blocking(&self, samples: &[f32], is_running: &AtomicBool) -> bool {
+        let channels = self.shared.channels;
+        let mut rest = samples;
+        while !rest.is_empty() {
+            if !is_running.load(Ordering::SeqCst) || self.shared.reader_gone.load(Ordering::Acquire)
+            {
+                return false;
+            }
+            let free = self.shared.capacity() - self.shared.filled();
+            let n = (free.min(rest.len()) / channels) * channels;
+            if n > 0 && self.push(&rest[..n]) {
+                rest = &rest[n..];
+            } else {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        true
+    }
+
+    /// Frames dropped because the ring was full.
+    pub fn dropped_frames(&self) -> u64 {
+        self.shared.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Samples written and not yet read.
+    pub fn backlog(&self) -> usize {
+        self.shared.filled()
+    }
+
+    fn write(&self, len: usize, mut fill: impl FnMut(&mut [f32], usize)) -> bool {
+        let shared = &*self.shared;
+        if len == 0 {
+            return true;
+        }
+        if shared.writing.swap(true, Ordering::Acquire) {
+            shared
+                .dropped_frames
+                .fetch_add((len / shared.channels) as u64, Ordering::Relaxed);
+            return false;
+        }
+
+        let capacity = shared.capacity();
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let fits = len <= capacity - shared.filled();
+        if fits {
+            let start = tail % capacity;
+            let first = len.min(capacity - start);
+            // SAFETY: [tail, tail + len) is free space and we hold the write claim
+            unsafe {
+                fill(shared.slice_mut(start, first), 0);
+                if first < len {
+                    fill(shared.slice_mut(0, len - first), first);
+                }
+            }
+            shared.tail.store(tail.wrapping_add(len), Ordering::Release);
+        } else {
+            shared
+                .dropped_frames
+                .fetch_add((len / shared.channels) as u64, Ordering::Relaxed);
+        }
+        shared.writing.store(false, Ordering::Release);
+        if fits {
+            shared.wake_reader();
+        }
+        fits
+    }
+}
+
+impl Clone for RingWriter {
+    fn clone(&self) -> Self {
+        self.shared.writers.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for RingWriter {
+    fn drop(&mut self) {
+        self.shared.writers.fetch_sub(1, Ordering::AcqRel);
+        self.shared.wake_reader();
+    }
+}
+
+/// Reading end of a `sample_ring`.
+pub struct RingReader {
+    shared: Arc<Shared>,
+}
+
+impl RingReader {
+    /// Samples waiting to be read.
+    pub fn len(&self) -> usize {
+        self.shared.filled()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Ring size in samples.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Frames dropped by writers because the ring was full.
+    pub fn dropped_frames(&self) -> u64 {
+        self.shared.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Move up to `out.len()` samples (whole frames) into `out`. Returns the count.
+    pub fn pop(&mut self, out: &mut [f32]) -> usize {
+        let shared = &*self.shared;
+        let capacity = shared.capacity();
+        let n = (shared.filled().min(out.len()) / shared.channels) * shared.channels;
+        if n == 0 {
+            return 0;
+        }
+        let head = shared.head.load(Ordering::Relaxed);
+        let start = head % capacity;
+        let first = n.min(capacity - start);
+        // SAFETY: [head, head + n) is filled and only the reader consumes it
+        unsafe {
+            out[..first].copy_from_slice(shared.slice_mut(start, first));
+            out[first..n].copy_from_slice(shared.slice_mut(0, n - first));
+        }
+        shared.head.store(head.wrapping_add(n), Ordering::Release);
+        n
+    }
+
+    /// Wait up to `timeout` for samples, then `pop` them. Fails with `Disconnected`
+    /// once every writer is dropped and the ring is drained, like a channel.
+    /// The calling thread is woken by writers; use one reader thread per ring.
+    pub fn recv_timeout(
+        &mut self,
+        out: &mut [f32],
+        timeout: Duration,
+    ) -> Result<usize, RecvTimeoutError> {
+        let reader = self.shared.reader.get_or_init(thread::current);
+        let is_reader = reader.id() == thread::current().id();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let n = self.pop(out);
+            if n > 0 {
+                return Ok(n);
+            }
+            if self.shared.writers.load(Ordering::Acquire) == 0 {
+                return match self.pop(out) {
+                    0 => Err(RecvTimeoutError::Disconnected),
+                    n => Ok(n),
+                };
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            let wait = (deadline - now).min(MAX_PARK);
+            if is_reader {
+                thread::park_timeout(wait);
+            } else {
+                thread::sleep(wait.min(Duration::from_millis(1)));
+            }
+        }
+    }
+}
+
+impl Drop for RingReader {
+    fn drop(&mut self) {
+        self.shared.reader_gone.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_and_preserves_order() {
+        let (tx, mut rx) = sample_ring(4, 2);
+        let mut out = [0.0; 8];
+        assert!(tx.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+        assert_eq!(rx.pop(&mut out[..4]), 4);
+        // Wraps past the end of the 8-sample buffer
+        assert!(tx.push_fn(6, |i| 7.0 + i as f32));
+        assert_eq!(rx.pop(&mut out), 8);
+        assert_eq!(out, [5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn full_ring_drops_whole_buffers_and_counts_frames() {
+        let (tx, mut rx) = sample_ring(4, 2);
+        assert!(tx.push(&[0.1; 6]));
+        assert!(!tx.push(&[0.2; 4]));
+        assert_eq!(tx.dropped_frames(), 2);
+        // A partial frame is never handed out
+        let mut out = [0.0; 5];
+        assert_eq!(rx.pop(&mut out), 4);
+        assert_eq!(rx.len(), 2);
+    }
+
+    #[test]
+    fn reader_sees_disconnect_after_draining() {
+        let (tx, mut rx) = sample_ring(16, 1);
+        let tx2 = tx.clone();
+        drop(tx);
+        let producer = thread::spawn(move || {
+            let running = AtomicBool::new(true);
+            // 100 samples through a 16-sample ring: waits instead of dropping
+            let samples: Vec<f32> = (0..100).map(|i| i as f32).collect();
+            assert!(tx2.push_blocking(&samples, &running));
+        });
+
+        let mut out = [0.0; 8];
+        let mut got = Vec::new();
+        loop {
+            match rx.recv_timeout(&mut out, Duration::from_secs(2)) {
+                Ok(n) => got.extend_from_slice(&out[..n]),
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => panic!("timed out"),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(got, (0..100).map(|i| i as f32).collect::<Vec<_>>());
+        assert_eq!(rx.dropped_frames(), 0);
+    }
+}
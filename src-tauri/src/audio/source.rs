@@ -0,0 +1,35 @@
+use crate::audio::ring::RingWriter;
+use crate::audio::types::SourceFormat;
+use anyhow::Result;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// A producer of interleaved f32 audio feeding `AudioCaptureManager`'s processor thread.
+/// Implemented by live devices (cpal mic, WASAPI / PulseAudio loopback) and by file replay.
+pub trait AudioSource: Send {
+    /// Identifier recorded with the meeting (`DeviceInfo.id`, or `file:<path>` for replays).
+    fn id(&self) -> String;
+
+    /// Native format of the samples this source delivers.
+    fn format(&self) -> SourceFormat;
+
+    /// Whether samples arrive at wall-clock pace. Non-realtime sources (fast file replay)
+    /// are back-pressured by the processor instead of having frames dropped.
+    fn is_realtime(&self) -> bool {
+        true
+    }
+
+    /// Whether the stream died on its own (device unplugged, server gone). The capture
+    /// watchdog replaces failed live sources; replays never fail.
+    fn has_failed(&self) -> bool {
+        false
+    }
+
+    /// Begin writing samples into `ring`. Realtime sources drop buffers the ring has
+    /// no room for; replays wait for space. Sources running their own thread exit
+    /// once `is_running` is cleared.
+    fn start(&mut self, ring: RingWriter, is_running: Arc<AtomicBool>) -> Result<()>;
+
+    /// Stop delivering samples and release the underlying device or file.
+    fn stop(&mut self);
+}
@@ -11,6 +11,59 @@ pub struct DeviceInfo {
     pub channels: u16,
 }
 
+/// Devices chosen for a capture session, by `DeviceInfo.id`.
+/// `None` means "use the OS default" when requesting, and "not captured" once resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSelection {
+    pub mic: Option<String>,
+    pub loopback: Option<String>,
+}
+
+/// Native sample format of a capture stream (interleaved f32).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Which capture stream something refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceRole {
+    Mic,
+    Loopback,
+}
+
+impl SourceRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mic => "mic",
+            Self::Loopback => "loopback",
+        }
+    }
+}
+
+/// Frames dropped per stage because its consumer fell behind, since capture started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DroppedFrames {
+    pub mic: u64,
+    pub loopback: u64,
+    pub stt: u64,
+    pub output: u64,
+}
+
+/// What to write to disk while a meeting runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingMode {
+    #[default]
+    Off,
+    /// The mixed 16kHz mono stream fed to STT (what transcript sample offsets index).
+    Mixed,
+    /// The mixed stream plus each source in its native format.
+    PerSource,
+}
+
 /// Audio capture configuration.
 #[derive(Debug, Clone)]
 pub struct AudioConfig {
@@ -0,0 +1,260 @@
+use crate::audio::vad::{SpeechBuffer, VadConfig, VadEvent};
+use std::collections::VecDeque;
+
+/// How far back from the end of a full utterance to look for a split point.
+const SPLIT_SEARCH_MS: usize = 4000;
+/// Window whose energy is compared when choosing the split point.
+const SPLIT_WINDOW_MS: usize = 50;
+
+/// A complete utterance and its `[start, end)` sample range in the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Utterance {
+    pub samples: Vec<f32>,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Groups VAD-classified frames into utterances for STT.
+/// Keeps `pre_roll_ms` of audio from before onset, every silence frame inside the
+/// utterance (gaps between words), and `hangover_ms` of silence after the last
+/// speech frame, so whisper hears natural, unclipped speech.
+pub struct UtteranceBuilder {
+    buffer: SpeechBuffer,
+    pre_roll: VecDeque<f32>,
+    pre_roll_samples: usize,
+    hangover_samples: usize,
+    /// Silence samples at the end of `buffer` since the last speech frame.
+    trailing_silence: usize,
+    start: u64,
+    samples_per_ms: usize,
+}
+
+impl UtteranceBuilder {
+    pub fn new(config: &VadConfig, sample_rate: u32) -> Self {
+        let per_ms = sample_rate as usize / 1000;
+        let pre_roll_samples = config.pre_roll_ms as usize * per_ms;
+        Self {
+            buffer: SpeechBuffer::new(sample_rate, config.max_utterance_secs),
+            pre_roll: VecDeque::with_capacity(pre_roll_samples),
+            pre_roll_samples,
+            hangover_samples: config.hangover_ms as usize * per_ms,
+            trailing_silence: 0,
+            start: 0,
+            samples_per_ms: per_ms,
+        }
+    }
+
+    /// Add a frame whose first sample sits at `frame_start` in the stream.
+    /// Returns the finished utterance on `SpeechEnd`.
+    pub fn push(&mut self, frame: &[f32], event: VadEvent, frame_start: u64) -> Option<Utterance> {
+        match event {
+            VadEvent::Speech => {
+                if self.buffer.is_empty() {
+                    self.start = frame_start.saturating_sub(self.pre_roll.len() as u64);
+                    let pre_roll: Vec<f32> = self.pre_roll.drain(..).collect();
+                    self.buffer.push(&pre_roll);
+                }
+                self.buffer.push(frame);
+                self.trailing_silence = 0;
+                None
+            }
+            VadEvent::Silence if self.buffer.is_empty() => {
+                self.remember(frame);
+                None
+            }
+            VadEvent::Silence => {
+                self.buffer.push(frame);
+                self.trailing_silence += frame.len();
+                None
+            }
+            VadEvent::SpeechEnd => {
+                self.buffer.push(frame);
+                self.trailing_silence += frame.len();
+                self.flush()
+            }
+        }
+    }
+
+    /// Whether the utterance reached the `max_utterance_secs` cap.
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    /// Cut a long utterance at the quietest point near its end, instead of wherever
+    /// the cap was hit. Audio after the cut stays buffered and opens the next utterance.
+    pub fn split(&mut self) -> Option<Utterance> {
+        let mut samples = self.buffer.take()?;
+        let at = quietest_point(
+            &samples,
+            SPLIT_SEARCH_MS * self.samples_per_ms,
+            SPLIT_WINDOW_MS * self.samples_per_ms,
+        );
+        let rest = samples.split_off(at);
+        self.buffer.push(&rest);
+        self.trailing_silence = self.trailing_silence.min(rest.len());
+
+        let start = self.start;
+        self.start += samples.len() as u64;
+        Some(Utterance {
+            start,
+            end: self.start,
+            samples,
+        })
+    }
+
+    /// The utterance still being collected and its stream offset, if speech started.
+    pub fn pending(&self) -> Option<(&[f32], u64)> {
+        (!self.buffer.is_empty()).then(|| (self.buffer.samples(), self.start))
+    }
+
+    /// End the current utterance now, trimming silence beyond the hangover.
+    /// The trimmed tail becomes pre-roll for the next utterance.
+    pub fn flush(&mut self) -> Option<Utterance> {
+        let excess = self.trailing_silence.saturating_sub(self.hangover_samples);
+        let keep = self.buffer.len() - excess;
+        let tail = self.buffer.truncate(keep);
+        self.trailing_silence = 0;
+        let samples = self.buffer.take();
+        self.remember(&tail);
+
+        samples.map(|samples| Utterance {
+            start: self.start,
+            end: self.start + samples.len() as u64,
+            samples,
+        })
+    }
+
+    fn remember(&mut self, audio: &[f32]) {
+        self.pre_roll.extend(audio);
+        let excess = self.pre_roll.len().saturating_sub(self.pre_roll_samples);
+        self.pre_roll.drain(..excess);
+    }
+}
+
+/// Middle of the lowest-energy `window` within the last `search` samples.
+fn quietest_point(samples: &[f32], search: usize, window: usize) -> usize {
+    let from = samples.len().saturating_sub(search);
+    if samples.len() - from < window * 2 {
+        return samples.len();
+    }
+    let tail = &samples[from..];
+    let mut energy: f64 = tail[..window].iter().map(|&s| (s * s) as f64).sum();
+    let (mut best, mut best_energy) = (0, energy);
+    for i in 1..=tail.len() - window {
+        energy += (tail[i + window - 1] * tail[i + window - 1]) as f64;
+        energy -= (tail[i - 1] * tail[i - 1]) as f64;
+        if energy < best_energy - 1e-9 {
+            best = i;
+            best_energy = energy;
+        }
+    }
+    from + best + window / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(pre_roll_ms: u32, hangover_ms: u32) -> UtteranceBuilder {
+        let config = VadConfig {
+            pre_roll_ms,
+            hangover_ms,
+            ..VadConfig::default()
+        };
+        UtteranceBuilder::new(&config, 16000)
+    }
+
+    /// Feed (value, event) frames of 160 samples from stream offset 0.
+    fn feed(b: &mut UtteranceBuilder, frames: &[(f32, VadEvent)]) -> Vec<Utterance> {
+        frames
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(value, event))| b.push(&[value; 160], event, i as u64 * 160))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_pre_roll_gaps_and_hangover() {
+        use VadEvent::*;
+        let mut b = builder(20, 10);
+        // 3 quiet frames, speech, an inner gap, speech, then 3 frames of trailing silence
+        let frames = [
+            (0.1, Silence),
+            (0.2, Silence),
+            (0.3, Silence),
+            (1.0, Speech),
+            (0.4, Silence),
+            (1.0, Speech),
+            (0.5, Silence),
+            (0.6, Silence),
+            (0.7, SpeechEnd),
+        ];
+        let utterances = feed(&mut b, &frames);
+        assert_eq!(utterances.len(), 1);
+        let u = &utterances[0];
+
+        // 2 pre-roll frames + 3 speech/gap frames + 1 hangover frame
+        assert_eq!((u.start, u.end), (160, 160 + 6 * 160));
+        let firsts: Vec<f32> = u.samples.chunks(160).map(|c| c[0]).collect();
+        assert_eq!(firsts, vec![0.2, 0.3, 1.0, 0.4, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn trimmed_silence_becomes_next_pre_roll() {
+        use VadEvent::*;
+        let mut b = builder(10, 0);
+        let frames = [
+            (1.0, Speech),
+            (0.1, Silence),
+            (0.2, SpeechEnd),
+            (0.9, Speech),
+            (0.3, SpeechEnd),
+        ];
+        let utterances = feed(&mut b, &frames);
+        assert_eq!(utterances[0].samples, vec![1.0; 160]);
+        assert_eq!(
+            (utterances[1].start, utterances[1].samples[0]),
+            (2 * 160, 0.2)
+        );
+    }
+
+    #[test]
+    fn full_utterance_splits_in_quiet_gap() {
+        let config = VadConfig {
+            max_utterance_secs: 1,
+            ..VadConfig::default()
+        };
+        let mut b = UtteranceBuilder::new(&config, 16000);
+        // 1s of speech with a 50ms dip at frames 70..75
+        let frames: Vec<(f32, VadEvent)> = (0..100)
+            .map(|i| (if (70..75).contains(&i) { 0.01 } else { 0.5 }, VadEvent::Speech))
+            .collect();
+        assert!(feed(&mut b, &frames).is_empty());
+        assert!(b.is_full());
+
+        let head = b.split().unwrap();
+        assert_eq!((head.start, head.end), (0, 70 * 160 + 400));
+        assert!(!b.is_full());
+        let tail = b.flush().unwrap();
+        assert_eq!((tail.start, tail.end), (head.end, 100 * 160));
+        assert_eq!(tail.samples.len() + head.samples.len(), 16000);
+    }
+
+    #[test]
+    fn pending_shows_utterance_in_progress() {
+        use VadEvent::*;
+        let mut b = builder(10, 0);
+        feed(&mut b, &[(0.1, Silence), (1.0, Speech)]);
+        let (samples, start) = b.pending().unwrap();
+        assert_eq!((samples.len(), start), (320, 0));
+        assert!(b.push(&[0.0; 160], SpeechEnd, 320).is_some());
+        assert!(b.pending().is_none());
+    }
+
+    #[test]
+    fn flush_without_speech_yields_nothing() {
+        let mut b = builder(300, 200);
+        assert!(b.push(&[0.0; 160], VadEvent::Silence, 0).is_none());
+        assert!(b.flush().is_none());
+    }
+}
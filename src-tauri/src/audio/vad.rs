@@ -1,11 +1,36 @@
-/// Configuration for energy-based Voice Activity Detection.
+use crate::audio::noise_floor::NoiseFloor;
+use crate::audio::voice_detector::{Endpointer, VadBackend, VoiceDetector};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Configuration for Voice Activity Detection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VadConfig {
     /// RMS threshold below which a frame is considered silence (default 0.02).
+    /// With `adaptive`, only the starting point; `calibrate_vad` stores one per device.
     pub rms_threshold: f32,
+    /// Track the room's noise floor and keep the RMS threshold `noise_margin` above it.
+    pub adaptive: bool,
+    /// Ratio between the RMS threshold and the noise floor (3.0 ≈ 10 dB).
+    pub noise_margin: f32,
     /// Zero-crossing rate threshold (default 0.1).
     pub zcr_threshold: f32,
     /// How many consecutive silence frames before triggering SpeechEnd.
     pub silence_limit: usize,
+    /// Audio kept from before speech onset, so quiet attacks are not clipped.
+    pub pre_roll_ms: u32,
+    /// Trailing silence kept after the last speech frame of an utterance.
+    pub hangover_ms: u32,
+    /// Longest utterance sent to STT; longer speech is split at a quiet point.
+    pub max_utterance_secs: u32,
+    /// Detector implementation; the thresholds above apply to `Energy`.
+    pub backend: VadBackend,
+    /// Speech probability above which a `Silero` frame counts as speech.
+    pub speech_threshold: f32,
+    /// ONNX model file for `Silero`.
+    pub model_path: Option<PathBuf>,
 }
 
 impl Default for VadConfig {
@@ -13,9 +38,30 @@ impl Default for VadConfig {
         // 500ms silence at 10ms frames = 50 frames
         Self {
             rms_threshold: 0.02,
+            adaptive: true,
+            noise_margin: 3.0,
             zcr_threshold: 0.1,
             silence_limit: 50,
+            pre_roll_ms: 300,
+            hangover_ms: 200,
+            max_utterance_secs: 30,
+            backend: VadBackend::Energy,
+            speech_threshold: 0.5,
+            model_path: None,
+        }
+    }
+}
+
+impl VadConfig {
+    /// Fails on settings the detectors cannot run with.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.noise_margin.is_finite() && self.noise_margin > 0.0) {
+            anyhow::bail!("noise_margin must be above 0, got {}", self.noise_margin);
         }
+        if self.max_utterance_secs == 0 {
+            anyhow::bail!("max_utterance_secs must be at least 1");
+        }
+        Ok(())
     }
 }
 
@@ -32,17 +78,17 @@ pub enum VadEvent {
 
 /// Energy-based VAD using RMS + zero-crossing rate.
 pub struct EnergyVad {
+    endpointer: Endpointer,
+    noise_floor: NoiseFloor,
     config: VadConfig,
-    silence_frames: usize,
-    has_speech: bool,
 }
 
 impl EnergyVad {
     pub fn new(config: VadConfig) -> Self {
         Self {
+            endpointer: Endpointer::new(config.silence_limit),
+            noise_floor: NoiseFloor::new(config.rms_threshold / config.noise_margin),
             config,
-            silence_frames: 0,
-            has_speech: false,
         }
     }
 
@@ -55,28 +101,37 @@ impl EnergyVad {
         let rms = compute_rms(samples);
         let zcr = compute_zcr(samples);
 
-        let is_silence =
-            rms < self.config.rms_threshold && zcr < self.config.zcr_threshold;
-
-        if is_silence {
-            self.silence_frames += 1;
-            if self.has_speech && self.silence_frames >= self.config.silence_limit {
-                self.has_speech = false;
-                self.silence_frames = 0;
-                VadEvent::SpeechEnd
-            } else {
-                VadEvent::Silence
-            }
+        let rms_threshold = self.rms_threshold();
+        if self.config.adaptive {
+            self.noise_floor.update(rms);
+        }
+        let is_silence = rms < rms_threshold && zcr < self.config.zcr_threshold;
+
+        self.endpointer.update(!is_silence)
+    }
+
+    /// RMS level a frame must reach to count as speech.
+    pub fn rms_threshold(&self) -> f32 {
+        if self.config.adaptive {
+            self.noise_floor.level() * self.config.noise_margin
         } else {
-            self.silence_frames = 0;
-            self.has_speech = true;
-            VadEvent::Speech
+            self.config.rms_threshold
         }
     }
 
+    /// Keeps the learned noise floor: the room has not changed.
     pub fn reset(&mut self) {
-        self.silence_frames = 0;
-        self.has_speech = false;
+        self.endpointer.reset();
+    }
+}
+
+impl VoiceDetector for EnergyVad {
+    fn process_frame(&mut self, samples: &[f32]) -> VadEvent {
+        EnergyVad::process_frame(self, samples)
+    }
+
+    fn reset(&mut self) {
+        EnergyVad::reset(self)
     }
 }
 
@@ -110,6 +165,23 @@ impl SpeechBuffer {
         Some(std::mem::take(&mut self.samples))
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Drop samples past `len` (e.g. trailing silence).
+    pub fn truncate(&mut self, len: usize) -> Vec<f32> {
+        self.samples.split_off(len.min(self.samples.len()))
+    }
+
     /// Current buffer duration in milliseconds.
     pub fn duration_ms(&self) -> u64 {
         (self.samples.len() as u64 * 1000) / self.sample_rate as u64
@@ -125,7 +197,7 @@ impl SpeechBuffer {
     }
 }
 
-fn compute_rms(samples: &[f32]) -> f32 {
+pub(crate) fn compute_rms(samples: &[f32]) -> f32 {
     let sum: f32 = samples.iter().map(|s| s * s).sum();
     (sum / samples.len() as f32).sqrt()
 }
@@ -162,6 +234,23 @@ mod tests {
         assert_eq!(vad.process_frame(&samples), VadEvent::Speech);
     }
 
+    #[test]
+    fn rejects_settings_detectors_cannot_run_with() {
+        assert!(VadConfig::default().validate().is_ok());
+        for noise_margin in [0.0, -1.0, f32::NAN] {
+            let config = VadConfig {
+                noise_margin,
+                ..VadConfig::default()
+            };
+            assert!(config.validate().is_err(), "{}", noise_margin);
+        }
+        let config = VadConfig {
+            max_utterance_secs: 0,
+            ..VadConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn speech_end_after_sustained_silence() {
         let config = VadConfig {
@@ -183,6 +272,30 @@ mod tests {
         assert_eq!(vad.process_frame(&silence), VadEvent::SpeechEnd);
     }
 
+    #[test]
+    fn quiet_mic_speech_detected_once_floor_adapts() {
+        let tone = |amp: f32| -> Vec<f32> {
+            (0..160)
+                .map(|i| amp * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / 16000.0).sin())
+                .collect()
+        };
+        let room = tone(0.0015);
+        let speech = tone(0.015);
+
+        let mut fixed = EnergyVad::new(VadConfig {
+            adaptive: false,
+            ..VadConfig::default()
+        });
+        assert_eq!(fixed.process_frame(&speech), VadEvent::Silence);
+
+        let mut vad = EnergyVad::new(VadConfig::default());
+        for _ in 0..100 {
+            assert_eq!(vad.process_frame(&room), VadEvent::Silence);
+        }
+        assert!(vad.rms_threshold() < 0.005);
+        assert_eq!(vad.process_frame(&speech), VadEvent::Speech);
+    }
+
     #[test]
     fn speech_buffer_accumulation() {
         let mut buf = SpeechBuffer::new(16000, 30);
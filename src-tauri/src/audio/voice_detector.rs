@@ -0,0 +1,94 @@
+use crate::audio::neural_vad::SileroVad;
+use crate::audio::vad::{EnergyVad, VadConfig, VadEvent};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Classifies 10ms frames of 16kHz mono audio. Every backend reports `VadEvent`s with
+/// the same semantics: `SpeechEnd` once, after `silence_limit` silent frames following speech.
+pub trait VoiceDetector: Send {
+    fn process_frame(&mut self, samples: &[f32]) -> VadEvent;
+    fn reset(&mut self);
+}
+
+/// Which `VoiceDetector` a meeting uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VadBackend {
+    /// RMS + zero-crossing rate. No model needed.
+    #[default]
+    Energy,
+    /// Silero VAD v5 ONNX model, run on CPU.
+    Silero,
+}
+
+/// Build the detector selected by `config.backend`.
+pub fn create_detector(config: &VadConfig) -> Result<Box<dyn VoiceDetector>> {
+    match config.backend {
+        VadBackend::Energy => Ok(Box::new(EnergyVad::new(config.clone()))),
+        VadBackend::Silero => {
+            let path = config
+                .model_path
+                .as_deref()
+                .context("Silero VAD needs a model path")?;
+            Ok(Box::new(SileroVad::open(path, config)?))
+        }
+    }
+}
+
+/// Turns per-frame speech / silence decisions into `VadEvent`s.
+#[derive(Debug)]
+pub struct Endpointer {
+    silence_limit: usize,
+    silence_frames: usize,
+    has_speech: bool,
+}
+
+impl Endpointer {
+    pub fn new(silence_limit: usize) -> Self {
+        Self {
+            silence_limit,
+            silence_frames: 0,
+            has_speech: false,
+        }
+    }
+
+    pub fn update(&mut self, is_speech: bool) -> VadEvent {
+        if is_speech {
+            self.silence_frames = 0;
+            self.has_speech = true;
+            return VadEvent::Speech;
+        }
+        self.silence_frames += 1;
+        if self.has_speech && self.silence_frames >= self.silence_limit {
+            self.reset();
+            VadEvent::SpeechEnd
+        } else {
+            VadEvent::Silence
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.silence_frames = 0;
+        self.has_speech = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn energy_backend_needs_no_model() {
+        let mut detector = create_detector(&VadConfig::default()).unwrap();
+        assert_eq!(detector.process_frame(&[0.0; 160]), VadEvent::Silence);
+    }
+
+    #[test]
+    fn silero_backend_requires_model_path() {
+        let config = VadConfig {
+            backend: VadBackend::Silero,
+            ..VadConfig::default()
+        };
+        assert!(create_detector(&config).is_err());
+    }
+}
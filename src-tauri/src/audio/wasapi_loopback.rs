@@ -0,0 +1,161 @@
+#![allow(deprecated)] // cpal 0.17 deprecates name() in favor of description()
+
+use crate::audio::device;
+use crate::audio::ring::RingWriter;
+use crate::audio::source::AudioSource;
+use crate::audio::types::SourceFormat;
+use anyhow::{Context, Result};
+use cpal::traits::DeviceTrait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// System audio loopback capture of a render device via WASAPI (Windows only).
+pub struct WasapiLoopbackSource {
+    name: String,
+    format: SourceFormat,
+    failed: Arc<AtomicBool>,
+}
+
+impl WasapiLoopbackSource {
+    /// Open the requested loopback device (`DeviceInfo.id`), or the default render device.
+    pub fn open(device_id: Option<&str>) -> Result<Self> {
+        let device = device::resolve_loopback_device(device_id)?;
+        let name = device
+            .name()
+            .map_err(|e| anyhow::anyhow!("Failed to read output device name: {}", e))?;
+        let config = device
+            .default_output_config()
+            .with_context(|| format!("Failed to get output config for '{}'", name))?;
+
+        Ok(Self {
+            name,
+            format: SourceFormat {
+                sample_rate: config.sample_rate(),
+                channels: config.channels(),
+            },
+            failed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+impl AudioSource for WasapiLoopbackSource {
+    fn id(&self) -> String {
+        format!("{}{}", device::LOOPBACK_ID_PREFIX, self.name)
+    }
+
+    fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    fn start(&mut self, ring: RingWriter, is_running: Arc<AtomicBool>) -> Result<()> {
+        let name = self.name.clone();
+        let format = self.format;
+        let failed = self.failed.clone();
+
+        thread::Builder::new()
+            .name("wasapi-loopback".to_string())
+            .spawn(move || {
+                if let Err(e) = wasapi_loopback_thread(&name, format, ring, is_running.clone()) {
+                    tracing::error!("WASAPI loopback error: {}", e);
+                }
+                // Render device removed or its client invalidated mid-capture
+                if is_running.load(Ordering::SeqCst) {
+                    failed.store(true, Ordering::SeqCst);
+                }
+            })?;
+
+        tracing::info!("WASAPI loopback thread started on '{}'", self.name);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // The capture thread exits on its own once `is_running` is cleared
+    }
+}
+
+/// WASAPI loopback capture thread.
+/// Opens the named Render device, initializes for Capture direction → WASAPI auto-sets loopback.
+fn wasapi_loopback_thread(
+    device_name: &str,
+    format: SourceFormat,
+    ring: RingWriter,
+    is_running: Arc<AtomicBool>,
+) -> Result<()> {
+    use std::collections::VecDeque;
+    use wasapi::*;
+
+    // initialize_mta returns HRESULT; .ok() converts to WasapiRes
+    initialize_mta()
+        .ok()
+        .map_err(|e| anyhow::anyhow!("COM init failed: {}", e))?;
+
+    let enumerator = DeviceEnumerator::new()?;
+    let device = enumerator
+        .get_device_collection(&Direction::Render)?
+        .get_device_with_name(device_name)?;
+    let mut audio_client = device.get_iaudioclient()?;
+
+    // Request f32 at the device's native rate/channels; autoconvert handles the rest
+    let mix_format = WaveFormat::new(
+        32,
+        32,
+        &SampleType::Float,
+        format.sample_rate as usize,
+        format.channels as usize,
+        None,
+    );
+    tracing::info!("WASAPI loopback format: {:?}", mix_format);
+
+    let (def_time, _min_time) = audio_client.get_device_period()?;
+
+    // EventsShared with Render device + Capture direction → loopback mode
+    let mode = StreamMode::EventsShared {
+        autoconvert: true,
+        buffer_duration_hns: def_time,
+    };
+
+    audio_client.initialize_client(&mix_format, &Direction::Capture, &mode)?;
+
+    let h_event = audio_client.set_get_eventhandle()?;
+    let capture_client = audio_client.get_audiocaptureclient()?;
+    audio_client.start_stream()?;
+
+    tracing::info!("WASAPI loopback stream started");
+
+    let mut sample_queue: VecDeque<u8> = VecDeque::with_capacity(16384);
+    let frame_bytes = 4 * format.channels.max(1) as usize;
+
+    while is_running.load(Ordering::SeqCst) {
+        // Read captured data into deque
+        match capture_client.read_from_device_to_deque(&mut sample_queue) {
+            Ok(_buffer_info) => {
+                // Decode whole frames straight from the deque into the ring
+                let bytes = sample_queue.len() / frame_bytes * frame_bytes;
+                if bytes > 0 {
+                    let q = &sample_queue;
+                    ring.push_fn(bytes / 4, |i| {
+                        f32::from_le_bytes([q[i * 4], q[i * 4 + 1], q[i * 4 + 2], q[i * 4 + 3]])
+                    });
+                    sample_queue.drain(..bytes);
+                }
+            }
+            Err(e) => {
+                // AUDCLNT_E_DEVICE_INVALIDATED and friends: the client never recovers
+                let _ = audio_client.stop_stream();
+                anyhow::bail!("WASAPI read failed: {}", e);
+            }
+        }
+
+        // Wait for next audio event (100ms timeout)
+        let _ = h_event.wait_for_event(100);
+    }
+
+    audio_client.stop_stream()?;
+    tracing::info!("WASAPI loopback thread exiting");
+    Ok(())
+}
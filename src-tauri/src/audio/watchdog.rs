@@ -0,0 +1,646 @@
+#![allow(deprecated)] // cpal 0.17 deprecates name() in favor of description()
+
+use crate::audio::channel_map::ChannelMap;
+use crate::audio::device;
+use crate::audio::events::{CaptureEvent, DeviceChange};
+use crate::audio::live_source::{LiveSource, LiveSources};
+use crate::audio::mic_source::MicSource;
+use crate::audio::ring::RingWriter;
+use crate::audio::source::AudioSource;
+use crate::audio::types::{DeviceInfo, DeviceSelection, SourceFormat, SourceRole};
+use anyhow::Result;
+use cpal::traits::DeviceTrait;
+use crossbeam::channel::Sender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often streams are checked for errors.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often devices are enumerated (which walks ALSA and runs pactl) to find removed,
+/// returned and new default devices. A stream error triggers a scan straight away.
+const SCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Device queries and opening, abstracted so recovery can be tested without hardware.
+pub(crate) trait DeviceProvider: Send {
+    fn list(&self) -> Option<Vec<DeviceInfo>>;
+    fn default_id(&self, role: SourceRole) -> Option<String>;
+    fn open(&self, role: SourceRole, id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>>;
+}
+
+/// The real devices: cpal inputs plus the platform loopback.
+pub(crate) struct SystemDevices {
+    /// Channel selection re-applied to replacement mics that have those channels.
+    pub mic_channels: ChannelMap,
+}
+
+impl DeviceProvider for SystemDevices {
+    fn list(&self) -> Option<Vec<DeviceInfo>> {
+        device::list_devices().ok()
+    }
+
+    fn default_id(&self, role: SourceRole) -> Option<String> {
+        match role {
+            SourceRole::Mic => device::get_default_input_device().ok()?.name().ok(),
+            #[cfg(windows)]
+            SourceRole::Loopback => {
+                let name = device::resolve_loopback_device(None).ok()?.name().ok()?;
+                Some(format!("{}{}", device::LOOPBACK_ID_PREFIX, name))
+            }
+            #[cfg(target_os = "linux")]
+            SourceRole::Loopback => {
+                let source = device::resolve_loopback_source(None).ok()?;
+                Some(format!("{}{}", device::LOOPBACK_ID_PREFIX, source.name))
+            }
+            #[cfg(not(any(windows, target_os = "linux")))]
+            SourceRole::Loopback => None,
+        }
+    }
+
+    fn open(&self, role: SourceRole, id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>> {
+        match role {
+            SourceRole::Mic => {
+                let mic = MicSource::open(id)?;
+                let mic = match mic.with_channel_map(self.mic_channels.clone()) {
+                    Ok(mapped) => mapped,
+                    Err(e) => {
+                        tracing::warn!("{:#}; capturing all channels", e);
+                        MicSource::open(id)?
+                    }
+                };
+                Ok(Some(Box::new(mic)))
+            }
+            SourceRole::Loopback => crate::audio::capture::open_system_loopback(id),
+        }
+    }
+}
+
+/// Where a role's samples go and what was asked for it.
+pub(crate) struct Target {
+    pub role: SourceRole,
+    pub ring: RingWriter,
+    pub format: SourceFormat,
+    pub requested: Option<String>,
+}
+
+/// Per-role recovery state.
+struct RoleState {
+    target: Target,
+    /// Set while no device could be opened; holds the reason it was lost.
+    lost: Option<&'static str>,
+}
+
+/// Polls live sources and rebuilds any whose stream died or whose device went away,
+/// preferring the requested device and falling back to the OS default. Also follows
+/// default-device changes when no device was requested, and moves back to a requested
+/// device once it reappears. Streams are checked every tick; devices only every
+/// `SCAN_INTERVAL` or after a stream error, against the list the scan cached.
+pub(crate) struct Watchdog {
+    provider: Box<dyn DeviceProvider>,
+    sources: Arc<Mutex<LiveSources>>,
+    devices: Arc<Mutex<DeviceSelection>>,
+    events_tx: Sender<CaptureEvent>,
+    is_running: Arc<AtomicBool>,
+    roles: Vec<RoleState>,
+    /// Device ids from the last scan that could list them.
+    known: Option<Vec<String>>,
+    next_scan: Instant,
+}
+
+impl Watchdog {
+    pub fn new(
+        provider: Box<dyn DeviceProvider>,
+        sources: Arc<Mutex<LiveSources>>,
+        devices: Arc<Mutex<DeviceSelection>>,
+        events_tx: Sender<CaptureEvent>,
+        is_running: Arc<AtomicBool>,
+        targets: Vec<Target>,
+    ) -> Self {
+        let known = provider
+            .list()
+            .map(|list| list.into_iter().map(|d| d.id).collect());
+        Self {
+            provider,
+            sources,
+            devices,
+            events_tx,
+            is_running,
+            roles: targets
+                .into_iter()
+                .map(|target| RoleState { target, lost: None })
+                .collect(),
+            known,
+            next_scan: Instant::now() + SCAN_INTERVAL,
+        }
+    }
+
+    pub fn spawn(mut self) -> Result<()> {
+        thread::Builder::new()
+            .name("audio-watchdog".to_string())
+            .spawn(move || {
+                while self.is_running.load(Ordering::SeqCst) {
+                    thread::sleep(POLL_INTERVAL);
+                    if self.is_running.load(Ordering::SeqCst) {
+                        self.tick(Instant::now());
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Check every role's stream, and the devices when a scan is due or a stream failed.
+    pub fn tick(&mut self, now: Instant) {
+        let statuses: Vec<_> = (0..self.roles.len()).map(|i| self.status(i)).collect();
+        let failed = statuses.iter().any(|s| matches!(s, Some(Some((_, true)))));
+        let scanned = failed || now >= self.next_scan;
+        if scanned {
+            self.next_scan = now + SCAN_INTERVAL;
+            self.scan();
+        }
+        for (i, status) in statuses.into_iter().enumerate() {
+            if let Some(status) = status {
+                self.check(i, status, scanned);
+            }
+        }
+    }
+
+    /// Refresh the cached device list, reporting when it changed.
+    fn scan(&mut self) {
+        if let Some(list) = self.provider.list() {
+            let ids: Vec<String> = list.iter().map(|d| d.id.clone()).collect();
+            if self.known.as_ref().is_some_and(|known| *known != ids) {
+                self.emit(DeviceChange::ListChanged { devices: list });
+            }
+            self.known = Some(ids);
+        }
+    }
+
+    /// Id and failure flag of the role's stream, `Some(None)` without one, or `None`
+    /// when the sources can't be read.
+    fn status(&self, i: usize) -> Option<Option<(String, bool)>> {
+        let role = self.roles[i].target.role;
+        let mut sources = self.sources.lock().ok()?;
+        Some(
+            sources
+                .slot(role)
+                .as_ref()
+                .map(|s| (s.id(), s.has_failed())),
+        )
+    }
+
+    /// Whether the last scan listed device `id`; `None` if it couldn't list devices.
+    fn listed(&self, id: &str) -> Option<bool> {
+        self.known
+            .as_ref()
+            .map(|known| known.iter().any(|d| d == id))
+    }
+
+    fn check(&mut self, i: usize, status: Option<(String, bool)>, scanned: bool) {
+        let role = self.roles[i].target.role;
+        let (reason, fatal) = match status {
+            Some((_, true)) => ("stream error", true),
+            // Everything else depends on the devices, which only a scan looks at
+            _ if !scanned => return,
+            None => (self.roles[i].lost.unwrap_or("device unavailable"), true),
+            Some((id, false)) if self.listed(&id) == Some(false) => ("device removed", true),
+            Some((id, false)) => match self.roles[i].target.requested {
+                Some(ref requested) if *requested != id && self.listed(requested) == Some(true) => {
+                    ("requested device returned", false)
+                }
+                None if self.provider.default_id(role).is_some_and(|d| d != id) => {
+                    ("default device changed", false)
+                }
+                _ => return,
+            },
+        };
+        self.reopen(i, reason, fatal);
+    }
+
+    /// Replace the role's source. The old one stops before the new one starts, so only
+    /// one stream writes into the role's ring. A `fatal` reason stops it first and falls
+    /// back to the default device; otherwise a device is opened before the old stream
+    /// stops, which is kept if none opens, and the old device is the fallback.
+    fn reopen(&mut self, i: usize, reason: &'static str, fatal: bool) {
+        let role = self.roles[i].target.role;
+        let previous = self.current_device(role);
+        if fatal {
+            self.stop_source(role);
+        }
+
+        let requested = self.roles[i].target.requested.clone();
+        let mut candidates = vec![requested.clone()];
+        if fatal && requested.is_some() {
+            candidates.push(None);
+        } else if !fatal && !previous.is_empty() {
+            candidates.push(Some(previous.clone()));
+        }
+
+        let mut stopped = fatal;
+        for candidate in candidates {
+            let source = match self.provider.open(role, candidate.as_deref()) {
+                Ok(Some(source)) => source,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::debug!("Reopening {:?} on {:?} failed: {}", role, candidate, e);
+                    continue;
+                }
+            };
+            if !stopped {
+                self.stop_source(role);
+                stopped = true;
+            }
+            let target = &self.roles[i].target;
+            let live = match LiveSource::start(source, target.ring.clone(), target.format) {
+                Ok(live) => live,
+                Err(e) => {
+                    tracing::warn!("Starting replacement {:?} stream failed: {}", role, e);
+                    continue;
+                }
+            };
+
+            let current = live.id();
+            if !self.install(role, live) {
+                return;
+            }
+            tracing::info!(
+                "{:?} stream moved from '{}' to '{}' ({})",
+                role,
+                previous,
+                current,
+                reason
+            );
+            self.roles[i].lost = None;
+            self.emit(DeviceChange::Recovered {
+                role,
+                previous,
+                current,
+                reason: reason.to_string(),
+            });
+            return;
+        }
+
+        if stopped && self.roles[i].lost.is_none() {
+            tracing::warn!(
+                "{:?} stream lost ({}), no device to fall back to",
+                role,
+                reason
+            );
+            self.roles[i].lost = Some(reason);
+            self.emit(DeviceChange::Lost {
+                role,
+                previous,
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    /// Stop the role's stream, leaving its slot empty.
+    fn stop_source(&self, role: SourceRole) {
+        if let Some(old) = self
+            .sources
+            .lock()
+            .ok()
+            .and_then(|mut s| s.slot(role).take())
+        {
+            old.stop();
+        }
+    }
+
+    /// Swap `live` into the role's slot unless capture stopped meanwhile.
+    fn install(&self, role: SourceRole, live: LiveSource) -> bool {
+        let mut sources = match self.sources.lock() {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+        if !self.is_running.load(Ordering::SeqCst) {
+            live.stop();
+            return false;
+        }
+        let current = live.id();
+        if let Some(old) = sources.slot(role).replace(live) {
+            old.stop();
+        }
+        if let Ok(mut devices) = self.devices.lock() {
+            match role {
+                SourceRole::Mic => devices.mic = Some(current),
+                SourceRole::Loopback => devices.loopback = Some(current),
+            }
+        }
+        true
+    }
+
+    fn current_device(&self, role: SourceRole) -> String {
+        let devices = self.devices.lock().map(|d| d.clone()).unwrap_or_default();
+        match role {
+            SourceRole::Mic => devices.mic,
+            SourceRole::Loopback => devices.loopback,
+        }
+        .unwrap_or_default()
+    }
+
+    fn emit(&self, change: DeviceChange) {
+        let _ = self.events_tx.try_send(CaptureEvent::DeviceChanged(change));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring::{sample_ring, RingReader};
+    use crossbeam::channel::{bounded, Receiver};
+    use std::sync::atomic::AtomicUsize;
+
+    /// A device that sends one buffer on start and can be made to fail.
+    struct FakeSource {
+        id: String,
+        format: SourceFormat,
+        failed: Arc<AtomicBool>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl AudioSource for FakeSource {
+        fn id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn format(&self) -> SourceFormat {
+            self.format
+        }
+
+        fn has_failed(&self) -> bool {
+            self.failed.load(Ordering::SeqCst)
+        }
+
+        fn start(&mut self, ring: RingWriter, _is_running: Arc<AtomicBool>) -> Result<()> {
+            self.log.lock().unwrap().push(format!("start {}", self.id));
+            let frames = 160 * self.format.channels as usize;
+            anyhow::ensure!(ring.push(&vec![0.5; frames]), "ring full");
+            Ok(())
+        }
+
+        fn stop(&mut self) {
+            self.log.lock().unwrap().push(format!("stop {}", self.id));
+        }
+    }
+
+    /// Devices by id with their formats; `default` is the OS default. Counts how often
+    /// devices are listed and logs streams starting and stopping.
+    #[derive(Clone, Default)]
+    struct FakeDevices {
+        present: Arc<Mutex<Vec<(String, SourceFormat)>>>,
+        default: Arc<Mutex<Option<String>>>,
+        failed: Arc<AtomicBool>,
+        lists: Arc<AtomicUsize>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeDevices {
+        fn plug(&self, id: &str, channels: u16) {
+            let format = SourceFormat {
+                sample_rate: 16000,
+                channels,
+            };
+            self.present.lock().unwrap().push((id.to_string(), format));
+        }
+
+        fn unplug(&self, id: &str) {
+            self.present.lock().unwrap().retain(|(d, _)| d != id);
+        }
+    }
+
+    impl DeviceProvider for FakeDevices {
+        fn list(&self) -> Option<Vec<DeviceInfo>> {
+            self.lists.fetch_add(1, Ordering::SeqCst);
+            let present = self.present.lock().unwrap();
+            Some(
+                present
+                    .iter()
+                    .map(|(id, format)| DeviceInfo {
+                        id: id.clone(),
+                        name: id.clone(),
+                        is_input: true,
+                        is_loopback: false,
+                        sample_rate: format.sample_rate,
+                        channels: format.channels,
+                    })
+                    .collect(),
+            )
+        }
+
+        fn default_id(&self, _role: SourceRole) -> Option<String> {
+            self.default.lock().unwrap().clone()
+        }
+
+        fn open(&self, role: SourceRole, id: Option<&str>) -> Result<Option<Box<dyn AudioSource>>> {
+            let id = match id {
+                Some(id) => id.to_string(),
+                None => self
+                    .default_id(role)
+                    .ok_or_else(|| anyhow::anyhow!("no default"))?,
+            };
+            let present = self.present.lock().unwrap();
+            let (_, format) = present
+                .iter()
+                .find(|(d, _)| *d == id)
+                .ok_or_else(|| anyhow::anyhow!("'{}' not found", id))?;
+            self.failed.store(false, Ordering::SeqCst);
+            Ok(Some(Box::new(FakeSource {
+                id,
+                format: *format,
+                failed: self.failed.clone(),
+                log: self.log.clone(),
+            })))
+        }
+    }
+
+    struct Harness {
+        devices: FakeDevices,
+        watchdog: Watchdog,
+        sources: Arc<Mutex<LiveSources>>,
+        audio: RingReader,
+        events: Receiver<CaptureEvent>,
+        now: Instant,
+    }
+
+    impl Harness {
+        /// Tick `after` the previous tick.
+        fn tick_after(&mut self, after: Duration) {
+            self.now += after;
+            self.watchdog.tick(self.now);
+        }
+
+        /// Tick with a device scan due.
+        fn scan(&mut self) {
+            self.tick_after(SCAN_INTERVAL);
+        }
+    }
+
+    /// Next buffer written to the harness ring.
+    fn recv_audio(audio: &mut RingReader) -> Vec<f32> {
+        let mut buf = vec![0.0; 1024];
+        let n = audio.recv_timeout(&mut buf, Duration::from_secs(1)).unwrap();
+        buf.truncate(n);
+        buf
+    }
+
+    /// Capture started on `headset` (mono) with `requested` as the mic request.
+    fn harness(requested: Option<&str>) -> Harness {
+        let devices = FakeDevices::default();
+        devices.plug("headset", 1);
+        *devices.default.lock().unwrap() = Some("headset".to_string());
+
+        let (ring, mut audio) = sample_ring(1600, 1);
+        let (events_tx, events) = bounded(16);
+        let format = SourceFormat {
+            sample_rate: 16000,
+            channels: 1,
+        };
+        let source = devices
+            .open(SourceRole::Mic, Some("headset"))
+            .unwrap()
+            .unwrap();
+        let sources = Arc::new(Mutex::new(LiveSources::default()));
+        sources.lock().unwrap().mic =
+            Some(LiveSource::start(source, ring.clone(), format).unwrap());
+        recv_audio(&mut audio);
+
+        let watchdog = Watchdog::new(
+            Box::new(devices.clone()),
+            sources.clone(),
+            Arc::new(Mutex::new(DeviceSelection {
+                mic: Some("headset".to_string()),
+                loopback: None,
+            })),
+            events_tx,
+            Arc::new(AtomicBool::new(true)),
+            vec![Target {
+                role: SourceRole::Mic,
+                ring,
+                format,
+                requested: requested.map(str::to_string),
+            }],
+        );
+        Harness {
+            devices,
+            watchdog,
+            sources,
+            audio,
+            events,
+            now: Instant::now(),
+        }
+    }
+
+    fn device_change(events: &Receiver<CaptureEvent>) -> DeviceChange {
+        loop {
+            match events.try_recv().expect("no device event") {
+                CaptureEvent::DeviceChanged(DeviceChange::ListChanged { .. }) => continue,
+                CaptureEvent::DeviceChanged(change) => return change,
+                _ => continue,
+            }
+        }
+    }
+
+    #[test]
+    fn unplugged_mic_moves_to_default_in_original_format() {
+        let mut h = harness(None);
+        h.devices.plug("laptop", 2);
+        h.devices.unplug("headset");
+        *h.devices.default.lock().unwrap() = Some("laptop".to_string());
+        h.devices.failed.store(true, Ordering::SeqCst);
+        h.tick_after(POLL_INTERVAL);
+
+        match device_change(&h.events) {
+            DeviceChange::Recovered {
+                previous, current, ..
+            } => assert_eq!((previous.as_str(), current.as_str()), ("headset", "laptop")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            h.sources.lock().unwrap().mic.as_ref().unwrap().id(),
+            "laptop"
+        );
+        // Stereo replacement is adapted back to the mono stream downstream expects
+        assert_eq!(recv_audio(&mut h.audio).len(), 160);
+    }
+
+    #[test]
+    fn reports_lost_once_then_recovers_when_device_returns() {
+        let mut h = harness(Some("headset"));
+        h.devices.unplug("headset");
+        *h.devices.default.lock().unwrap() = None;
+        h.scan();
+        h.scan();
+
+        assert!(matches!(
+            device_change(&h.events),
+            DeviceChange::Lost { .. }
+        ));
+        assert!(h.sources.lock().unwrap().mic.is_none());
+
+        h.devices.plug("headset", 1);
+        h.scan();
+        match device_change(&h.events) {
+            DeviceChange::Recovered {
+                current, reason, ..
+            } => {
+                assert_eq!(current, "headset");
+                assert_eq!(reason, "device removed");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(h.events.try_recv().is_err());
+    }
+
+    #[test]
+    fn healthy_stream_is_left_alone() {
+        let mut h = harness(Some("headset"));
+        h.scan();
+        assert!(h.events.try_recv().is_err());
+        assert!(h.sources.lock().unwrap().mic.is_some());
+    }
+
+    #[test]
+    fn devices_are_scanned_when_due_or_after_a_stream_error() {
+        let mut h = harness(None);
+        assert_eq!(h.devices.lists.load(Ordering::SeqCst), 1);
+
+        // Streams are polled every second, devices are not
+        h.devices.unplug("headset");
+        for _ in 0..5 {
+            h.tick_after(POLL_INTERVAL);
+        }
+        assert_eq!(h.devices.lists.load(Ordering::SeqCst), 1);
+        assert!(h.events.try_recv().is_err());
+
+        h.devices.failed.store(true, Ordering::SeqCst);
+        h.tick_after(POLL_INTERVAL);
+        assert_eq!(h.devices.lists.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            device_change(&h.events),
+            DeviceChange::Lost { .. }
+        ));
+    }
+
+    #[test]
+    fn returning_device_starts_after_the_old_stream_stops() {
+        let mut h = harness(Some("usb"));
+        h.devices.plug("usb", 1);
+        h.scan();
+
+        match device_change(&h.events) {
+            DeviceChange::Recovered {
+                current, reason, ..
+            } => {
+                assert_eq!(current, "usb");
+                assert_eq!(reason, "requested device returned");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            *h.devices.log.lock().unwrap(),
+            ["start headset", "stop headset", "start usb"]
+        );
+        assert_eq!(recv_audio(&mut h.audio).len(), 160);
+    }
+}
@@ -1,7 +1,19 @@
-use crate::audio::{list_devices, AudioCaptureManager, DeviceInfo};
+use crate::audio::ring::sample_ring;
+use crate::audio::{
+    list_devices, AudioCaptureManager, CaptureEvent, ChannelMap, DeviceInfo, DeviceSelection,
+    DroppedFrames, FileSource, HealthIncident, MixConfig,
+};
+use crate::commands::SttState;
+use crate::storage::AudioIncident;
+use crossbeam::channel::RecvTimeoutError;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::ipc::Channel;
-use tauri::State;
+use tauri::{Emitter, Manager, State};
+
+/// Most samples sent per `on_audio` message.
+const IPC_BLOCK_SAMPLES: usize = 4096;
 
 /// Application state for audio capture.
 pub struct AudioState {
@@ -28,52 +40,183 @@ pub fn list_audio_devices() -> Result<Vec<DeviceInfo>, String> {
     list_devices().map_err(|e| format!("Failed to list devices: {}", e))
 }
 
-/// Start audio capture. Audio chunks (PCM f32 LE bytes) stream via `on_audio` channel.
+/// Start audio capture. Raw audio stays in the backend unless `stream_capture_audio`
+/// asks for it. `device_id` / `loopback_device_id` are `DeviceInfo.id`s from
+/// `list_audio_devices`; omitted IDs use the OS default devices. Loopback is mixed into
+/// the mic stream after its echo is cancelled from the mic (`echo_cancellation`,
+/// default on). The per-source gains (default 1.0 each) apply to what each source
+/// transcribes and to the meeting recording as well as to the mix. `mic_channels` picks
+/// inputs of a multi-channel interface, numbered from 1 (e.g. `[3]`); all channels are
+/// averaged otherwise. Per-source levels are emitted as `audio-levels` events while
+/// capturing, for level meters. Unplugged devices are replaced automatically and
+/// reported as `audio-device-changed` events. Stalls, dropped frames and backlog are
+/// reported as `audio-health` events; each unhealthy stretch is recorded against the
+/// running meeting (`get_audio_incidents`).
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn start_audio_capture(
-    _device_id: Option<String>,
-    on_audio: Channel<Vec<u8>>,
+    device_id: Option<String>,
+    loopback_device_id: Option<String>,
+    mic_gain: Option<f32>,
+    loopback_gain: Option<f32>,
+    mic_channels: Option<Vec<u16>>,
+    echo_cancellation: Option<bool>,
+    app: tauri::AppHandle,
+    state: State<AudioState>,
+) -> Result<String, String> {
+    let mut manager = AudioCaptureManager::new();
+    let defaults = MixConfig::default();
+    manager.set_mix_config(MixConfig {
+        mic_gain: mic_gain.unwrap_or(defaults.mic_gain),
+        loopback_gain: loopback_gain.unwrap_or(defaults.loopback_gain),
+        echo_cancellation: echo_cancellation.unwrap_or(defaults.echo_cancellation),
+        ..defaults
+    });
+    manager.set_mic_channels(ChannelMap(mic_channels.unwrap_or_default()));
+    let requested = DeviceSelection {
+        mic: device_id,
+        loopback: loopback_device_id,
+    };
+
+    run_capture(&state, manager, app, |manager| manager.start(&requested))?;
+    Ok("Audio capture started".to_string())
+}
+
+/// Replay a WAV file through the capture pipeline in place of the microphone.
+/// With `realtime` (default true) the file plays at wall-clock speed; otherwise it is
+/// streamed as fast as the STT pipeline consumes it, starting once a meeting is running.
+#[tauri::command]
+pub fn start_file_capture(
+    path: String,
+    realtime: Option<bool>,
+    app: tauri::AppHandle,
     state: State<AudioState>,
 ) -> Result<String, String> {
+    let source = FileSource::open(Path::new(&path), realtime.unwrap_or(true))
+        .map_err(|e| format!("Failed to open audio file: {}", e))?;
+
+    run_capture(&state, AudioCaptureManager::new(), app, |manager| {
+        manager.start_with_sources(Box::new(source), None)
+    })?;
+    Ok("File replay started".to_string())
+}
+
+/// Start `manager` via `start` and bridge its events to the frontend.
+fn run_capture(
+    state: &AudioState,
+    mut manager: AudioCaptureManager,
+    app: tauri::AppHandle,
+    start: impl FnOnce(&mut AudioCaptureManager) -> anyhow::Result<()>,
+) -> Result<(), String> {
     let mut guard = state.manager.lock().unwrap();
 
     if guard.is_some() {
         return Err("Audio capture already running. Stop first.".to_string());
     }
 
-    let mut manager = AudioCaptureManager::new();
+    start(&mut manager).map_err(|e| format!("Failed to start capture: {}", e))?;
 
-    // Channel bridge: crossbeam → Tauri Channel
-    let (output_tx, output_rx) = crossbeam::channel::bounded::<Vec<u8>>(100);
+    let events = manager.events();
+    *guard = Some(manager);
 
-    manager
-        .start(output_tx)
-        .map_err(|e| format!("Failed to start capture: {}", e))?;
+    // Forward capture events as Tauri events until the manager is dropped
+    std::thread::Builder::new()
+        .name("audio-events-bridge".to_string())
+        .spawn(move || {
+            while let Ok(event) = events.recv() {
+                let result = match event {
+                    CaptureEvent::Levels(ref levels) => app.emit(event.name(), levels),
+                    CaptureEvent::DeviceChanged(ref change) => app.emit(event.name(), change),
+                    CaptureEvent::Health(ref health) => app.emit(event.name(), health),
+                    CaptureEvent::Incident(ref incident) => {
+                        record_incident(&app, incident);
+                        app.emit(event.name(), incident)
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::warn!("Failed to emit {}: {}", event.name(), e);
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start event bridge: {}", e))?;
 
-    *guard = Some(manager);
+    Ok(())
+}
+
+/// Stream the captured audio (mic mixed with loopback) to `on_audio` as PCM f32 LE
+/// bytes in the mic's format, until capture stops or the channel closes. Opt-in:
+/// level meters should use `audio-levels` instead of raw audio.
+#[tauri::command]
+pub fn stream_capture_audio(
+    on_audio: Channel<Vec<u8>>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    let guard = state
+        .manager
+        .lock()
+        .map_err(|e| format!("Audio lock poisoned: {}", e))?;
+    let manager = guard
+        .as_ref()
+        .filter(|m| m.is_active())
+        .ok_or_else(|| "Audio capture not running".to_string())?;
+    let (sample_rate, channels) = manager.mic_format();
+    let (tx, mut output) = sample_ring(sample_rate as usize, channels);
+    manager.set_output(tx);
     let manager_ref = Arc::clone(&state.manager);
 
-    // Forward audio from crossbeam channel to Tauri IPC channel.
-    // When the frontend disconnects, auto-stop the capture manager.
+    // Bytes are built here rather than on the audio path
     std::thread::Builder::new()
         .name("audio-ipc-bridge".to_string())
         .spawn(move || {
-            while let Ok(chunk) = output_rx.recv() {
+            let mut block = vec![0.0f32; IPC_BLOCK_SAMPLES];
+            loop {
+                let n = match output.recv_timeout(&mut block, Duration::from_secs(1)) {
+                    Ok(n) => n,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let chunk: Vec<u8> = block[..n].iter().flat_map(|s| s.to_le_bytes()).collect();
                 if on_audio.send(chunk).is_err() {
-                    tracing::warn!("Frontend channel closed, stopping capture");
-                    if let Ok(mut guard) = manager_ref.lock() {
-                        if let Some(mgr) = guard.as_mut() {
-                            mgr.stop();
-                        }
-                        *guard = None;
+                    tracing::warn!("Frontend audio channel closed, no longer streaming audio");
+                    if let Some(mgr) = manager_ref.lock().ok().as_ref().and_then(|g| g.as_ref()) {
+                        mgr.clear_output();
                     }
                     break;
                 }
             }
         })
         .map_err(|e| format!("Failed to start IPC bridge: {}", e))?;
+    Ok(())
+}
 
-    Ok("Audio capture started".to_string())
+/// Store a finished health incident against the running meeting, if any.
+fn record_incident(app: &tauri::AppHandle, incident: &HealthIncident) {
+    tracing::warn!(
+        "Audio {} {} on {} for {}ms ({} frames dropped)",
+        incident.severity.as_str(),
+        incident.issue.as_str(),
+        incident.role.as_str(),
+        incident.duration_ms,
+        incident.dropped_frames,
+    );
+    let Some(stt_state) = app.try_state::<SttState>() else {
+        return;
+    };
+    let meeting_id = stt_state.meeting_id.lock().ok().and_then(|guard| *guard);
+    if let Some(mid) = meeting_id {
+        if let Err(e) = stt_state.incidents.record(mid, incident) {
+            tracing::error!("{}", e);
+        }
+    }
+}
+
+/// Capture and STT health incidents (stalls, drops, backlog) recorded during a meeting.
+#[tauri::command]
+pub fn get_audio_incidents(
+    meeting_id: i64,
+    stt_state: State<SttState>,
+) -> Result<Vec<AudioIncident>, String> {
+    stt_state.incidents.list(meeting_id)
 }
 
 /// Stop audio capture.
@@ -90,6 +233,20 @@ pub fn stop_audio_capture(state: State<AudioState>) -> Result<String, String> {
     }
 }
 
+/// Frames dropped per stage (mic, loopback, STT, output) since capture started,
+/// because a consumer fell behind.
+#[tauri::command]
+pub fn get_dropped_frames(state: State<AudioState>) -> Result<DroppedFrames, String> {
+    let guard = state
+        .manager
+        .lock()
+        .map_err(|e| format!("Audio lock poisoned: {}", e))?;
+    guard
+        .as_ref()
+        .map(AudioCaptureManager::dropped_frames)
+        .ok_or_else(|| "Audio capture not running".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,118 @@
+use crate::audio::calibration::{RoomTone, RoomToneMeter};
+use crate::audio::ring::{sample_ring, RingReader};
+use crate::audio::{DspConfig, SourceFormat, VadConfig};
+use crate::commands::{AudioState, SttState};
+use crate::storage::VadCalibration;
+use crossbeam::channel::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+/// Room tone listened to when the caller does not say how long.
+const DEFAULT_CALIBRATION_MS: u64 = 3000;
+/// Extra time allowed for DSP warm-up and late audio before giving up.
+const CALIBRATION_SLACK: Duration = Duration::from_secs(2);
+
+/// Listen to a few seconds of room tone on the running capture and store VAD
+/// thresholds for the mic in use; the room should be quiet meanwhile. Meetings on
+/// that mic start from these thresholds. `dsp` and `vad` should match the meeting's
+/// pipeline settings: the VAD sees processed audio, and the threshold sits the VAD's
+/// `noise_margin` above the room tone.
+#[tauri::command]
+pub async fn calibrate_vad(
+    duration_ms: Option<u64>,
+    dsp: Option<DspConfig>,
+    vad: Option<VadConfig>,
+    stt_state: State<'_, SttState>,
+    audio_state: State<'_, AudioState>,
+) -> Result<VadCalibration, String> {
+    let in_meeting = stt_state
+        .pipeline
+        .lock()
+        .map_err(|e| format!("Pipeline lock poisoned: {}", e))?
+        .is_some();
+    if in_meeting {
+        return Err("Cannot calibrate during a meeting. Stop it first.".to_string());
+    }
+    let duration_ms = duration_ms
+        .unwrap_or(DEFAULT_CALIBRATION_MS)
+        .clamp(1000, 30_000);
+    let vad = vad.unwrap_or_default();
+    vad.validate()
+        .map_err(|e| format!("Invalid VAD settings: {}", e))?;
+    let noise_margin = vad.noise_margin;
+
+    // Borrow the STT tap of the capture manager while listening
+    let (format, device_id, mut rx) = {
+        let audio_guard = audio_state
+            .manager
+            .lock()
+            .map_err(|e| format!("Audio lock poisoned: {}", e))?;
+        let manager = audio_guard
+            .as_ref()
+            .ok_or("Audio capture not running. Start audio capture first.")?;
+        let device_id = manager.devices().mic.ok_or("No microphone in use")?;
+        let (rate, channels) = manager.mic_format();
+        let format = SourceFormat {
+            sample_rate: if rate == 0 { 48000 } else { rate },
+            channels: if channels == 0 { 1 } else { channels },
+        };
+        let (tx, rx) = sample_ring(format.sample_rate as usize, format.channels);
+        manager.set_stt_sender(tx);
+        (format, device_id, rx)
+    };
+
+    // A meeting started meanwhile takes the tap over, which ends the listen early;
+    // only a tap still feeding this calibration is removed
+    let (measured, rx) = match RoomToneMeter::new(format, &dsp.unwrap_or_default()) {
+        Ok(meter) => {
+            let task = tauri::async_runtime::spawn_blocking(move || {
+                let tone = listen(meter, &mut rx, duration_ms, noise_margin);
+                (tone, rx)
+            });
+            match task.await {
+                Ok((tone, rx)) => (Ok(tone), Some(rx)),
+                Err(e) => (Err(format!("Calibration task failed: {}", e)), None),
+            }
+        }
+        Err(e) => (Err(format!("Failed to start calibration: {}", e)), Some(rx)),
+    };
+
+    if let (Ok(guard), Some(rx)) = (audio_state.manager.lock(), rx) {
+        if let Some(ref manager) = *guard {
+            manager.clear_stt_sender_for(&rx);
+        }
+    }
+
+    let tone = measured?.ok_or("Calibration interrupted or no audio received")?;
+    tracing::info!(
+        "VAD calibrated for {}: floor {:.4}, threshold {:.4}",
+        device_id,
+        tone.noise_floor,
+        tone.rms_threshold
+    );
+    stt_state.calibrations.save(
+        &device_id,
+        tone.noise_floor as f64,
+        tone.rms_threshold as f64,
+    )
+}
+
+/// Feed capture audio into `meter` until `duration_ms` of room tone is measured,
+/// setting the threshold `noise_margin` above it. None when the tap is taken away first.
+fn listen(
+    mut meter: RoomToneMeter,
+    rx: &mut RingReader,
+    duration_ms: u64,
+    noise_margin: f32,
+) -> Option<RoomTone> {
+    let deadline = Instant::now() + Duration::from_millis(duration_ms) + CALIBRATION_SLACK;
+    let mut buf = vec![0.0; rx.capacity() / 10];
+    while meter.measured_ms() < duration_ms && Instant::now() < deadline {
+        match rx.recv_timeout(&mut buf, Duration::from_millis(100)) {
+            Ok(n) => meter.push(&buf[..n]),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+    meter.finish(noise_margin)
+}
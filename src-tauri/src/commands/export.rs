@@ -134,6 +134,9 @@ fn format_json(
             "source_lang": meeting.source_lang,
             "target_langs": meeting.target_langs,
             "status": meeting.status,
+            "mic_device": meeting.mic_device,
+            "loopback_device": meeting.loopback_device,
+            "audio_path": meeting.audio_path,
         },
         "transcripts": transcripts.iter().map(|t| {
             let translations: HashMap<&str, &str> = t.id
@@ -146,6 +149,8 @@ fn format_json(
                 "translations": translations,
                 "translated_text": t.translated_text, // legacy fallback
                 "speaker": t.speaker,
+                "start_sample": t.start_sample,
+                "end_sample": t.end_sample,
             })
         }).collect::<Vec<_>>(),
     });
@@ -165,6 +170,9 @@ mod tests {
             source_lang: "en".to_string(),
             target_langs: "vi,ja".to_string(),
             status: "stopped".to_string(),
+            mic_device: Some("USB Headset".to_string()),
+            loopback_device: None,
+            audio_path: None,
         }
     }
 
@@ -177,6 +185,10 @@ mod tests {
             translated_text: None,
             timestamp: "00:00:05".to_string(),
             is_final: true,
+            start_sample: Some(80000),
+            end_sample: Some(112000),
+            language: Some("en".to_string()),
+            language_prob: None,
         }]
     }
 
@@ -1,17 +1,25 @@
 mod audio;
+mod calibration;
 mod export;
 mod meeting;
+mod models;
 mod notes;
 mod overlay;
+mod recording;
 mod settings;
+mod speakers;
 mod stt;
 mod translation;
 
 pub use audio::*;
+pub use calibration::*;
 pub use export::*;
 pub use meeting::*;
+pub use models::*;
 pub use notes::*;
 pub use overlay::*;
+pub use recording::*;
 pub use settings::*;
+pub use speakers::*;
 pub use stt::*;
 pub use translation::*;
@@ -0,0 +1,114 @@
+use crate::commands::SttState;
+use crate::stt::model_download::DownloadStatus;
+use crate::stt::{model_manager, ModelEntry};
+use serde::Serialize;
+use tauri::{Emitter, State};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStatus {
+    pub available: bool,
+    pub model_name: String,
+    pub file_size_mb: f64,
+}
+
+/// Check if a whisper model (default the selected one) is downloaded.
+#[tauri::command]
+pub fn check_model_status(
+    model: Option<String>,
+    state: State<SttState>,
+) -> Result<ModelStatus, String> {
+    let name = model.unwrap_or_else(|| state.model_manager.selected_model());
+    Ok(ModelStatus {
+        available: state.model_manager.is_model_available(&name),
+        file_size_mb: state.model_manager.model_size_mb(&name),
+        model_name: name,
+    })
+}
+
+/// List the whisper model catalog and other model files on disk, with sizes,
+/// download state and the current selection.
+#[tauri::command]
+pub fn list_models(state: State<SttState>) -> Vec<ModelEntry> {
+    state.model_manager.list_models()
+}
+
+/// Download a catalog whisper model (default the selected one) from the model mirror.
+/// Emits `model-download-started` with the download's ID, for `cancel_model_download`.
+#[tauri::command]
+pub async fn download_model(
+    model: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, SttState>,
+) -> Result<String, String> {
+    let name = model.unwrap_or_else(|| state.model_manager.selected_model());
+    let downloads = &state.model_manager.downloads;
+    let download = downloads.start(&name).map_err(|e| e.to_string())?;
+    let _ = app.emit("model-download-started", download.status());
+
+    let result = state.model_manager.download_model(&download, app).await;
+    downloads.finish(&download.id);
+    let path = result.map_err(|e| format!("Download failed: {:#}", e))?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Stop a model download. Its partial file is kept, so downloading the model
+/// again resumes where it stopped.
+#[tauri::command]
+pub fn cancel_model_download(download_id: String, state: State<SttState>) -> Result<(), String> {
+    if !state.model_manager.downloads.cancel(&download_id) {
+        return Err(format!("No download with ID {}", download_id));
+    }
+    tracing::info!("Model download cancelled: {}", download_id);
+    Ok(())
+}
+
+/// Model downloads in progress.
+#[tauri::command]
+pub fn list_model_downloads(state: State<SttState>) -> Vec<DownloadStatus> {
+    state.model_manager.downloads.list()
+}
+
+/// Import a local whisper model file (ggml `.bin` or `.gguf`), for networks where
+/// the download mirror is unreachable. Returns the model name to select it by.
+#[tauri::command]
+pub async fn import_model(path: String, state: State<'_, SttState>) -> Result<String, String> {
+    let models_dir = state.model_manager.models_dir().to_path_buf();
+    // Checking and copying a model of several GB must not hold up an async worker
+    tauri::async_runtime::spawn_blocking(move || {
+        model_manager::import_model(&models_dir, std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))?
+    .map_err(|e| format!("Import failed: {:#}", e))
+}
+
+/// Make a downloaded model the default for meetings started without `model`.
+#[tauri::command]
+pub fn select_model(model: String, state: State<SttState>) -> Result<(), String> {
+    state
+        .model_manager
+        .select_model(&model)
+        .map_err(|e| format!("{:#}", e))?;
+    tracing::info!("Whisper model selected: {}", model);
+    Ok(())
+}
+
+/// Delete a downloaded model. A meeting already using it keeps running.
+#[tauri::command]
+pub fn delete_model(model: String, state: State<SttState>) -> Result<(), String> {
+    state
+        .model_manager
+        .delete_model(&model)
+        .map_err(|e| format!("{:#}", e))?;
+
+    // Unload it, so the next meeting does not reuse a deleted model
+    let mut engine_guard = state
+        .engine
+        .lock()
+        .map_err(|e| format!("Engine lock poisoned: {}", e))?;
+    if matches!(*engine_guard, Some((ref name, _)) if *name == model) {
+        *engine_guard = None;
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use crate::audio::recorder::read_clip_wav;
+use crate::commands::SttState;
+use std::path::Path;
+use tauri::ipc::Response;
+use tauri::State;
+
+/// Default context kept around a replayed segment.
+const DEFAULT_PADDING_MS: u32 = 250;
+
+/// Return a transcript segment's audio as WAV bytes (16kHz mono), padded by
+/// `padding_ms` (default 250ms) on each side, for replay next to its text.
+#[tauri::command]
+pub fn get_segment_audio(
+    meeting_id: i64,
+    segment_id: String,
+    padding_ms: Option<u32>,
+    stt_state: State<SttState>,
+) -> Result<Response, String> {
+    let meeting = stt_state.transcript_db.get_meeting(meeting_id)?;
+    let audio_path = meeting
+        .audio_path
+        .ok_or_else(|| format!("Meeting {} has no audio recording", meeting_id))?;
+    let (start, end) = stt_state
+        .transcript_db
+        .get_segment_samples(meeting_id, &segment_id)?
+        .ok_or_else(|| format!("No recorded audio for segment '{}'", segment_id))?;
+
+    let wav = read_clip_wav(
+        Path::new(&audio_path),
+        start.max(0) as u64,
+        end.max(0) as u64,
+        padding_ms.unwrap_or(DEFAULT_PADDING_MS),
+    )
+    .map_err(|e| format!("Failed to read segment audio: {}", e))?;
+
+    Ok(Response::new(wav))
+}
@@ -0,0 +1,43 @@
+use crate::commands::SttState;
+use crate::stt::diarization;
+use tauri::State;
+
+/// Rename a meeting's speaker, e.g. "Speaker 1" to "Alice". While the meeting runs,
+/// segments transcribed later from that speaker get the new name too.
+/// Returns the number of segments relabelled.
+#[tauri::command]
+pub fn rename_speaker(
+    meeting_id: i64,
+    from: String,
+    to: String,
+    stt_state: State<SttState>,
+) -> Result<usize, String> {
+    let to = to.trim();
+    if to.is_empty() {
+        return Err("Speaker name cannot be empty".to_string());
+    }
+
+    let current = *stt_state
+        .meeting_id
+        .lock()
+        .map_err(|e| format!("Meeting ID lock poisoned: {}", e))?;
+    if current == Some(meeting_id) {
+        let mut names = stt_state
+            .speaker_names
+            .lock()
+            .map_err(|e| format!("Speaker names lock poisoned: {}", e))?;
+        diarization::rename_speaker(&mut names, &from, to);
+    }
+
+    let changed = stt_state
+        .transcript_db
+        .rename_speaker(meeting_id, &from, to)?;
+    tracing::info!(
+        "Meeting {}: renamed {} to {} ({} segments)",
+        meeting_id,
+        from,
+        to,
+        changed
+    );
+    Ok(changed)
+}
@@ -1,28 +1,49 @@
+use crate::audio::recorder::{meeting_recording_path, recordings_dir};
+use crate::audio::neural_vad::default_model_path;
+use crate::audio::ring::sample_ring;
+use crate::audio::{
+    create_detector, RecordingMode, SourceFormat, SourceRole, VadBackend, WavRecorder,
+};
 use crate::commands::AudioState;
 use crate::notes::{
     NoteEngine, NoteEngineConfig, NotesErrorPayload, NotesUpdatedPayload, SegmentBuffer,
     SharedNoteEngine, TranscriptSegment,
 };
-use crate::storage::TranscriptDb;
-use crate::stt::pipeline::MicFormat;
-use crate::stt::{ModelManager, SttEngine, SttPipeline, DEFAULT_MODEL};
-use serde::Serialize;
+use crate::storage::{CalibrationStore, IncidentStore, TranscriptDb};
+use crate::stt::diarization::{self, Diarizer, SpeakerNames};
+use crate::stt::lane::LaneInput;
+use crate::stt::pipeline::{PipelineConfig, PipelineSinks};
+use crate::stt::{ModelManager, SttEngine, SttPipeline};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tauri::State;
 
 /// Max note content length (2 KB) to prevent LLM output flooding DB.
 const MAX_NOTE_CONTENT_LEN: usize = 2048;
+/// Capture audio buffered for STT while whisper runs on the pipeline thread.
+const STT_RING_SECONDS: usize = 10;
 
 /// Application state for STT (speech-to-text).
 pub struct SttState {
     pub model_manager: ModelManager,
-    pub engine: Mutex<Option<Arc<SttEngine>>>,
+    /// Loaded whisper model and its engine, kept between meetings.
+    pub engine: Mutex<Option<(String, Arc<SttEngine>)>>,
     pub pipeline: Mutex<Option<SttPipeline>>,
     pub meeting_id: Arc<Mutex<Option<i64>>>,
     pub transcript_db: TranscriptDb,
+    pub calibrations: CalibrationStore,
+    /// Capture health incidents recorded while a meeting runs.
+    pub incidents: IncidentStore,
     pub note_engine: SharedNoteEngine,
     pub note_task_handle: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
     pub segment_buffer: SegmentBuffer,
+    pub recordings_dir: std::path::PathBuf,
+    /// Silero VAD model used when a meeting picks that backend without a path.
+    pub vad_model_path: std::path::PathBuf,
+    /// Speaker-embedding model used when diarization is on without a path.
+    pub speaker_model_path: std::path::PathBuf,
+    /// Speakers renamed during the current meeting (see rename_speaker).
+    pub speaker_names: SpeakerNames,
 }
 
 impl SttState {
@@ -30,56 +51,41 @@ impl SttState {
         let transcript_db = TranscriptDb::open(&app_data_dir)
             .expect("Failed to open transcript database");
         Self {
-            model_manager: ModelManager::new(app_data_dir),
+            recordings_dir: recordings_dir(&app_data_dir),
+            vad_model_path: default_model_path(&app_data_dir),
+            speaker_model_path: diarization::default_model_path(&app_data_dir),
+            model_manager: ModelManager::from_env(app_data_dir),
             engine: Mutex::new(None),
             pipeline: Mutex::new(None),
             meeting_id: Arc::new(Mutex::new(None)),
+            calibrations: CalibrationStore::new(transcript_db.get_connection()),
+            incidents: IncidentStore::new(transcript_db.get_connection()),
             transcript_db,
             note_engine: Arc::new(tokio::sync::Mutex::new(None)),
             note_task_handle: Arc::new(Mutex::new(None)),
             segment_buffer: Arc::new(Mutex::new(Vec::new())),
+            speaker_names: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ModelStatus {
-    pub available: bool,
-    pub model_name: String,
-    pub file_size_mb: f64,
-}
-
-/// Check if default whisper model is downloaded.
-#[tauri::command]
-pub fn check_model_status(state: State<SttState>) -> Result<ModelStatus, String> {
-    let name = DEFAULT_MODEL;
-    Ok(ModelStatus {
-        available: state.model_manager.is_model_available(name),
-        model_name: name.to_string(),
-        file_size_mb: state.model_manager.model_size_mb(name),
-    })
-}
-
-/// Download default whisper model from HuggingFace CDN.
-#[tauri::command]
-pub async fn download_model(
-    app: tauri::AppHandle,
-    state: State<'_, SttState>,
-) -> Result<String, String> {
-    let path = state
-        .model_manager
-        .download_model(DEFAULT_MODEL, app)
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
-
-    Ok(path.to_string_lossy().to_string())
-}
-
 /// Start a meeting: load model, create STT pipeline, start audio capture with STT fork.
+/// `model` is the whisper model file (see list_models), default the selected one; the
+/// engine is reloaded only when it differs from the last meeting's.
+/// `recording` (default off) saves the meeting audio under the app data dir.
+/// `pipeline` overrides STT pipeline tunables (DSP chain, VAD backend, resampler, ...);
+/// omitted fields keep defaults. Mic and system loopback are transcribed in separate
+/// lanes, tagged "Me" and "Remote" as the segment speaker, or "Speaker 1..N" by
+/// voice with `pipeline.diarization.enabled` (see rename_speaker). With
+/// `pipeline.streaming.enabled`, speech in progress is also sent as non-final captions.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn start_meeting(
     src_lang: Option<String>,
     target_langs: Option<Vec<String>>,
+    recording: Option<RecordingMode>,
+    pipeline: Option<PipelineConfig>,
+    model: Option<String>,
     app: tauri::AppHandle,
     stt_state: State<SttState>,
     audio_state: State<AudioState>,
@@ -98,86 +104,200 @@ pub fn start_meeting(
     }
 
     // Ensure model is available
-    if !stt_state.model_manager.is_model_available(DEFAULT_MODEL) {
-        return Err("Whisper model not downloaded. Call download_model first.".to_string());
+    let model_name = model.unwrap_or_else(|| stt_state.model_manager.selected_model());
+    if !stt_state.model_manager.is_model_available(&model_name) {
+        return Err(format!(
+            "Whisper model {} not downloaded. Call download_model first.",
+            model_name
+        ));
     }
 
-    // Load engine if not already loaded
-    {
+    // Load engine unless this model is already loaded; the meeting's language
+    // shares the loaded weights
+    let engine = {
         let mut engine_guard = stt_state
             .engine
             .lock()
             .map_err(|e| format!("Engine lock poisoned: {}", e))?;
-        if engine_guard.is_none() {
-            let model_path = stt_state.model_manager.model_path(DEFAULT_MODEL);
+        let loaded = matches!(*engine_guard, Some((ref name, _)) if *name == model_name);
+        if !loaded {
+            // Free the previous model before loading the next
+            *engine_guard = None;
+            let model_path = stt_state.model_manager.model_path(&model_name);
             let path_str = model_path
                 .to_str()
                 .ok_or_else(|| "Model path contains invalid characters".to_string())?;
-            let lang = src_lang.clone();
-            let engine = SttEngine::new(path_str, lang)?;
-            *engine_guard = Some(Arc::new(engine));
+            let engine = SttEngine::new(path_str, None)?;
+            *engine_guard = Some((model_name.clone(), Arc::new(engine)));
         }
-    }
-
-    let engine = stt_state
-        .engine
-        .lock()
-        .map_err(|e| format!("Engine lock poisoned: {}", e))?
-        .clone()
-        .ok_or("Engine not loaded")?;
-
-    // Create crossbeam channel for audio → STT pipeline
-    let (stt_tx, stt_rx) = crossbeam::channel::bounded::<Vec<f32>>(100);
+        let (_, engine) = engine_guard.as_ref().ok_or("Engine not loaded")?;
+        Arc::new(engine.with_language(src_lang.clone()))
+    };
 
-    // Attach STT sender to audio capture manager and get mic format
-    let mic_format = {
+    // Get mic format + devices in use
+    let (mic_format, has_loopback, devices) = {
         let audio_guard = audio_state
             .manager
             .lock()
             .map_err(|e| format!("Audio lock poisoned: {}", e))?;
         if let Some(ref manager) = *audio_guard {
-            manager.set_stt_sender(stt_tx);
             let (rate, channels) = manager.mic_format();
-            MicFormat {
+            let format = SourceFormat {
                 sample_rate: if rate == 0 { 48000 } else { rate },
                 channels: if channels == 0 { 1 } else { channels },
-            }
+            };
+            (format, manager.has_loopback(), manager.devices())
         } else {
             return Err("Audio capture not running. Start audio capture first.".to_string());
         }
     };
 
+    // Build the voice detectors before attaching to capture, so a missing VAD model
+    // fails cleanly. The mic's calibrated threshold (see calibrate_vad) is the mic
+    // lane's start point; loopback has no room noise and keeps the configured one.
+    let mut pipeline_config = pipeline.unwrap_or_default();
+    pipeline_config
+        .vad
+        .validate()
+        .map_err(|e| format!("Invalid VAD settings: {}", e))?;
+    if pipeline_config.vad.backend == VadBackend::Silero && pipeline_config.vad.model_path.is_none() {
+        pipeline_config.vad.model_path = Some(stt_state.vad_model_path.clone());
+    }
+    let loopback_vad = if has_loopback {
+        Some(
+            create_detector(&pipeline_config.vad)
+                .map_err(|e| format!("Failed to create voice detector: {}", e))?,
+        )
+    } else {
+        None
+    };
+    let mut mic_vad_config = pipeline_config.vad.clone();
+    if let Some(ref mic) = devices.mic {
+        if let Some(cal) = stt_state.calibrations.get(mic)? {
+            mic_vad_config.rms_threshold = cal.rms_threshold as f32;
+        }
+    }
+    let mic_vad = create_detector(&mic_vad_config)
+        .map_err(|e| format!("Failed to create voice detector: {}", e))?;
+
+    let diarizer = if pipeline_config.diarization.enabled {
+        let diarization = &mut pipeline_config.diarization;
+        if diarization.model_path.is_none() {
+            diarization.model_path = Some(stt_state.speaker_model_path.clone());
+        }
+        Some(
+            Diarizer::open(diarization)
+                .map_err(|e| format!("Failed to start diarization: {:#}", e))?,
+        )
+    } else {
+        None
+    };
+
+    // Attach one STT ring per source to the audio capture manager
+    let ring_frames = mic_format.sample_rate as usize * STT_RING_SECONDS;
+    let (stt_tx, stt_rx) = sample_ring(ring_frames, mic_format.channels);
+    let mut lanes = vec![LaneInput {
+        role: SourceRole::Mic,
+        rx: stt_rx,
+        format: mic_format,
+        vad: mic_vad,
+    }];
+    let mut loopback_tx = None;
+    if let Some(vad) = loopback_vad {
+        let (tx, rx) = sample_ring(ring_frames, 1);
+        loopback_tx = Some(tx);
+        lanes.push(LaneInput {
+            role: SourceRole::Loopback,
+            rx,
+            // Forwarded as mono at the mic rate, in step with the mic
+            format: SourceFormat {
+                sample_rate: mic_format.sample_rate,
+                channels: 1,
+            },
+            vad,
+        });
+    }
+
     // Create meeting record in SQLite BEFORE starting pipeline
     // so that early STT segments can reference the meeting_id
     let tgt = target_langs.unwrap_or_else(|| vec!["vi".to_string()]).join(",");
     let db_meeting_id = stt_state
         .transcript_db
-        .create_meeting(src_lang.as_deref().unwrap_or("en"), &tgt)
+        .create_meeting(src_lang.as_deref().unwrap_or("auto"), &tgt, &devices)
         .map_err(|e| format!("Failed to create meeting record: {}", e))?;
 
-    {
-        let mut mid_guard = stt_state
-            .meeting_id
-            .lock()
-            .map_err(|e| format!("Meeting ID lock poisoned: {}", e))?;
-        *mid_guard = Some(db_meeting_id);
+    let recorder = match start_recording(
+        &stt_state,
+        &audio_state,
+        db_meeting_id,
+        recording.unwrap_or_default(),
+    ) {
+        Ok(recorder) => recorder,
+        Err(e) => {
+            discard_meeting(&stt_state, db_meeting_id, None);
+            return Err(e);
+        }
+    };
+
+    // Attach the STT rings last: nothing fallible follows, so a failed start never
+    // leaves capture feeding rings nobody reads
+    let attached = match audio_state.manager.lock() {
+        Ok(audio_guard) => match *audio_guard {
+            Some(ref manager) => {
+                manager.set_stt_sender(stt_tx);
+                if let Some(tx) = loopback_tx {
+                    manager.set_loopback_stt_sender(tx);
+                }
+                Ok(())
+            }
+            None => Err("Audio capture stopped while starting meeting".to_string()),
+        },
+        Err(e) => Err(format!("Audio lock poisoned: {}", e)),
+    };
+    if let Err(e) = attached {
+        discard_meeting(&stt_state, db_meeting_id, recorder);
+        return Err(e);
     }
 
-    // Clear segment buffer for fresh meeting
+    match stt_state.meeting_id.lock() {
+        Ok(mut mid_guard) => *mid_guard = Some(db_meeting_id),
+        Err(e) => {
+            if let Ok(audio_guard) = audio_state.manager.lock() {
+                if let Some(ref manager) = *audio_guard {
+                    manager.clear_stt_sender();
+                    manager.stop_source_recording();
+                }
+            }
+            discard_meeting(&stt_state, db_meeting_id, recorder);
+            return Err(format!("Meeting ID lock poisoned: {}", e));
+        }
+    }
+
+    // Clear segment buffer and speaker names for fresh meeting
     {
         let mut buf = stt_state.segment_buffer.lock().map_err(|e| e.to_string())?;
         buf.clear();
     }
+    {
+        let mut names = stt_state.speaker_names.lock().map_err(|e| e.to_string())?;
+        names.clear();
+    }
 
     // Start STT pipeline with segment buffer (not note engine directly)
+    let sinks = PipelineSinks {
+        transcript_db: stt_state.transcript_db.clone(),
+        meeting_id: stt_state.meeting_id.clone(),
+        segment_buffer: stt_state.segment_buffer.clone(),
+        recorder,
+        speaker_names: stt_state.speaker_names.clone(),
+    };
     let pipeline = SttPipeline::start(
-        stt_rx,
+        lanes,
         engine,
         app.clone(),
-        mic_format,
-        stt_state.transcript_db.clone(),
-        stt_state.meeting_id.clone(),
-        stt_state.segment_buffer.clone(),
+        pipeline_config,
+        sinks,
+        diarizer,
     );
 
     {
@@ -307,6 +427,7 @@ pub fn stop_meeting(
             .map_err(|e| format!("Audio lock poisoned: {}", e))?;
         if let Some(ref manager) = *audio_guard {
             manager.clear_stt_sender();
+            manager.stop_source_recording();
         }
     }
 
@@ -314,6 +435,76 @@ pub fn stop_meeting(
     Ok("Meeting stopped".to_string())
 }
 
+/// Open the meeting recording(s) for `mode` and link the mixed file to the meeting.
+/// Returns the 16kHz recorder the STT pipeline writes to.
+fn start_recording(
+    stt_state: &SttState,
+    audio_state: &AudioState,
+    meeting_id: i64,
+    mode: RecordingMode,
+) -> Result<Option<WavRecorder>, String> {
+    if mode == RecordingMode::Off {
+        return Ok(None);
+    }
+
+    let path = meeting_recording_path(&stt_state.recordings_dir, meeting_id, None);
+    let format = SourceFormat {
+        sample_rate: 16000,
+        channels: 1,
+    };
+    let recorder = WavRecorder::create(&path, format)
+        .map_err(|e| format!("Failed to start recording: {}", e))?;
+
+    if mode == RecordingMode::PerSource {
+        let started = match audio_state.manager.lock() {
+            Ok(audio_guard) => match *audio_guard {
+                Some(ref manager) => manager
+                    .start_source_recording(&stt_state.recordings_dir, meeting_id)
+                    .map_err(|e| format!("Failed to start source recording: {}", e)),
+                None => Ok(()),
+            },
+            Err(e) => Err(format!("Audio lock poisoned: {}", e)),
+        };
+        if let Err(e) = started {
+            remove_recordings(&stt_state.recordings_dir, meeting_id, Some(recorder));
+            return Err(e);
+        }
+    }
+
+    // Only point the meeting at the file once every recording is running
+    if let Err(e) = stt_state
+        .transcript_db
+        .set_meeting_audio_path(meeting_id, &path.to_string_lossy())
+    {
+        if let Ok(audio_guard) = audio_state.manager.lock() {
+            if let Some(ref manager) = *audio_guard {
+                manager.stop_source_recording();
+            }
+        }
+        remove_recordings(&stt_state.recordings_dir, meeting_id, Some(recorder));
+        return Err(e);
+    }
+
+    Ok(Some(recorder))
+}
+
+/// Undo a meeting whose start failed: its row and any recordings begun for it.
+fn discard_meeting(stt_state: &SttState, meeting_id: i64, recorder: Option<WavRecorder>) {
+    remove_recordings(&stt_state.recordings_dir, meeting_id, recorder);
+    if let Err(e) = stt_state.transcript_db.delete_meeting(meeting_id) {
+        tracing::warn!("Failed to remove meeting {}: {}", meeting_id, e);
+    }
+}
+
+/// Delete the recordings of a meeting that never started, closing `recorder` first.
+fn remove_recordings(dir: &std::path::Path, meeting_id: i64, recorder: Option<WavRecorder>) {
+    drop(recorder);
+    for source in [None, Some(SourceRole::Mic), Some(SourceRole::Loopback)] {
+        let source = source.map(SourceRole::as_str);
+        let _ = std::fs::remove_file(meeting_recording_path(dir, meeting_id, source));
+    }
+}
+
 /// Async task loop for note generation.
 /// Drains segment buffer, checks triggers, generates notes, saves to DB, emits events.
 async fn run_note_generation_loop(
@@ -45,6 +45,7 @@ pub async fn ollama_health_check(
 
 /// Translate text to multiple target languages in parallel with streaming events.
 /// Spawns background tasks (one per lang), returns segment_id immediately.
+/// `source_lang` is the segment's language from `stt-partial` (default "auto").
 #[tauri::command]
 pub async fn translate_text(
     app: tauri::AppHandle,
@@ -53,6 +54,7 @@ pub async fn translate_text(
     text: String,
     target_langs: Vec<String>,
     segment_id: String,
+    source_lang: Option<String>,
 ) -> Result<String, String> {
     if text.trim().is_empty() {
         return Err("Empty text".to_string());
@@ -64,6 +66,7 @@ pub async fn translate_text(
     let provider = state.provider.clone();
     let semaphore = state.semaphore.clone();
     let seg_id = segment_id.clone();
+    let source_lang = source_lang.unwrap_or_else(|| "auto".to_string());
     let transcript_db = stt_state.transcript_db.clone();
     let meeting_id = stt_state.meeting_id.clone();
 
@@ -77,6 +80,7 @@ pub async fn translate_text(
             let app = app.clone();
             let seg_id = seg_id.clone();
             let text = text.clone();
+            let source_lang = source_lang.clone();
             let transcript_db = transcript_db.clone();
             let meeting_id = meeting_id.clone();
 
@@ -94,7 +98,7 @@ pub async fn translate_text(
                 // 30s timeout prevents hung Ollama requests from holding permits
                 let translate_result = tokio::time::timeout(
                     std::time::Duration::from_secs(30),
-                    pipeline.translate(&app, &seg_id, &text),
+                    pipeline.translate(&app, &seg_id, &text, &source_lang),
                 )
                 .await;
 
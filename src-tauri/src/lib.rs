@@ -9,11 +9,16 @@ pub mod translation;
 
 use commands::{
     get_app_version, get_settings, health_check,
-    list_audio_devices, start_audio_capture, stop_audio_capture,
-    check_model_status, download_model, start_meeting, stop_meeting,
+    get_audio_incidents, get_dropped_frames, list_audio_devices, start_audio_capture,
+    start_file_capture, stop_audio_capture, stream_capture_audio,
+    calibrate_vad,
+    check_model_status, download_model, list_models, select_model, delete_model,
+    cancel_model_download, list_model_downloads, import_model,
+    start_meeting, stop_meeting,
+    rename_speaker,
     ollama_health_check, translate_text, list_ollama_models,
     pull_ollama_model, delete_ollama_model,
-    export_transcript,
+    export_transcript, get_segment_audio,
     open_overlay_window, close_overlay_window,
     get_notes, update_note, delete_note, generate_memo, export_memo,
     AudioState, SttState, TranslationState, NoteState,
@@ -64,17 +69,30 @@ pub fn run() {
             get_settings,
             list_audio_devices,
             start_audio_capture,
+            start_file_capture,
             stop_audio_capture,
+            stream_capture_audio,
+            get_dropped_frames,
+            get_audio_incidents,
+            calibrate_vad,
             check_model_status,
             download_model,
+            list_models,
+            select_model,
+            delete_model,
+            cancel_model_download,
+            list_model_downloads,
+            import_model,
             start_meeting,
             stop_meeting,
+            rename_speaker,
             ollama_health_check,
             translate_text,
             list_ollama_models,
             pull_ollama_model,
             delete_ollama_model,
             export_transcript,
+            get_segment_audio,
             open_overlay_window,
             close_overlay_window,
             get_notes,
@@ -0,0 +1,86 @@
+use crate::storage::models::VadCalibration;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::{Arc, Mutex};
+
+/// Storage operations for per-device VAD calibrations.
+#[derive(Clone)]
+pub struct CalibrationStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl CalibrationStore {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// Insert or replace the calibration for `device_id`.
+    pub fn save(
+        &self,
+        device_id: &str,
+        noise_floor: f64,
+        rms_threshold: f64,
+    ) -> Result<VadCalibration, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO vad_calibrations (device_id, noise_floor, rms_threshold) \
+             VALUES (?1, ?2, ?3)",
+            params![device_id, noise_floor, rms_threshold],
+        )
+        .map_err(|e| format!("Failed to save VAD calibration: {}", e))?;
+        drop(conn);
+        self.get(device_id)?
+            .ok_or_else(|| "VAD calibration missing after save".to_string())
+    }
+
+    /// Calibration for a device, if it was ever calibrated.
+    pub fn get(&self, device_id: &str) -> Result<Option<VadCalibration>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT device_id, noise_floor, rms_threshold, calibrated_at \
+             FROM vad_calibrations WHERE device_id = ?1",
+            params![device_id],
+            |row| {
+                Ok(VadCalibration {
+                    device_id: row.get(0)?,
+                    noise_floor: row.get(1)?,
+                    rms_threshold: row.get(2)?,
+                    calibrated_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read VAD calibration: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_store() -> CalibrationStore {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE vad_calibrations (
+                device_id TEXT PRIMARY KEY,
+                noise_floor REAL NOT NULL,
+                rms_threshold REAL NOT NULL,
+                calibrated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );",
+        )
+        .unwrap();
+        CalibrationStore::new(Arc::new(Mutex::new(conn)))
+    }
+
+    #[test]
+    fn recalibrating_replaces_device_thresholds() {
+        let store = create_test_store();
+        assert_eq!(store.get("USB Mic").unwrap(), None);
+
+        store.save("USB Mic", 0.001, 0.003).unwrap();
+        let saved = store.save("USB Mic", 0.002, 0.006).unwrap();
+        assert_eq!(saved.rms_threshold, 0.006);
+        assert!(saved.calibrated_at.is_some());
+        assert_eq!(store.get("USB Mic").unwrap(), Some(saved));
+        assert_eq!(store.get("Other").unwrap(), None);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::audio::health::HealthIncident;
+use crate::storage::models::AudioIncident;
+use rusqlite::{params, Connection};
+use std::sync::{Arc, Mutex};
+
+/// Storage operations for capture health incidents.
+#[derive(Clone)]
+pub struct IncidentStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl IncidentStore {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// Record an incident that just ended; it started `duration_ms` ago.
+    pub fn record(&self, meeting_id: i64, incident: &HealthIncident) -> Result<i64, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO audio_incidents \
+             (meeting_id, source, issue, severity, started_at, duration_ms, dropped_frames) \
+             VALUES (?1, ?2, ?3, ?4, datetime('now', ?5), ?6, ?7)",
+            params![
+                meeting_id,
+                incident.role.as_str(),
+                incident.issue.as_str(),
+                incident.severity.as_str(),
+                format!("-{:.3} seconds", incident.duration_ms as f64 / 1000.0),
+                incident.duration_ms as i64,
+                incident.dropped_frames as i64,
+            ],
+        )
+        .map_err(|e| format!("Failed to record audio incident: {}", e))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Incidents of a meeting, oldest first.
+    pub fn list(&self, meeting_id: i64) -> Result<Vec<AudioIncident>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, meeting_id, source, issue, severity, started_at, ended_at, \
+                 duration_ms, dropped_frames \
+                 FROM audio_incidents WHERE meeting_id = ?1 ORDER BY started_at, id",
+            )
+            .map_err(|e| format!("Failed to prepare incident query: {}", e))?;
+        let rows = stmt
+            .query_map(params![meeting_id], |row| {
+                Ok(AudioIncident {
+                    id: row.get(0)?,
+                    meeting_id: row.get(1)?,
+                    source: row.get(2)?,
+                    issue: row.get(3)?,
+                    severity: row.get(4)?,
+                    started_at: row.get(5)?,
+                    ended_at: row.get(6)?,
+                    duration_ms: row.get(7)?,
+                    dropped_frames: row.get(8)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query audio incidents: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read audio incident: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::health::{HealthIssue, HealthState};
+    use crate::audio::types::SourceRole;
+
+    fn create_test_store() -> IncidentStore {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE audio_incidents (
+                id             INTEGER PRIMARY KEY AUTOINCREMENT,
+                meeting_id     INTEGER NOT NULL,
+                source         TEXT NOT NULL,
+                issue          TEXT NOT NULL,
+                severity       TEXT NOT NULL,
+                started_at     TEXT NOT NULL,
+                ended_at       TEXT NOT NULL DEFAULT (datetime('now')),
+                duration_ms    INTEGER NOT NULL,
+                dropped_frames INTEGER NOT NULL DEFAULT 0
+            );",
+        )
+        .unwrap();
+        IncidentStore::new(Arc::new(Mutex::new(conn)))
+    }
+
+    #[test]
+    fn records_incidents_per_meeting_with_their_start() {
+        let store = create_test_store();
+        let stall = HealthIncident {
+            role: SourceRole::Mic,
+            issue: HealthIssue::Stall,
+            severity: HealthState::Critical,
+            duration_ms: 90_000,
+            dropped_frames: 0,
+        };
+        store.record(1, &stall).unwrap();
+        store.record(2, &stall).unwrap();
+
+        let incidents = store.list(1).unwrap();
+        assert_eq!(incidents.len(), 1);
+        let recorded = &incidents[0];
+        assert_eq!(
+            (
+                recorded.source.as_str(),
+                recorded.issue.as_str(),
+                recorded.severity.as_str()
+            ),
+            ("mic", "stall", "critical")
+        );
+        assert_eq!(recorded.duration_ms, 90_000);
+        // Started a minute and a half before it was recorded
+        let span: i64 = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT strftime('%s', ended_at) - strftime('%s', started_at) \
+                 FROM audio_incidents WHERE id = ?1",
+                params![recorded.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!((89..=91).contains(&span));
+    }
+}
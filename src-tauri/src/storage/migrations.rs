@@ -2,7 +2,16 @@ use tauri_plugin_sql::{Migration, MigrationKind};
 
 /// All database migrations, ordered by version.
 pub fn get_migrations() -> Vec<Migration> {
-    vec![migration_v1(), migration_v2(), migration_v3()]
+    vec![
+        migration_v1(),
+        migration_v2(),
+        migration_v3(),
+        migration_v4(),
+        migration_v5(),
+        migration_v6(),
+        migration_v7(),
+        migration_v8(),
+    ]
 }
 
 /// V1: Initial schema -- meetings, transcripts, notes.
@@ -93,3 +102,86 @@ fn migration_v2() -> Migration {
         kind: MigrationKind::Up,
     }
 }
+
+/// V4: Remember which capture devices each meeting used.
+fn migration_v4() -> Migration {
+    Migration {
+        version: 4,
+        description: "add_capture_devices_to_meetings",
+        sql: r#"
+            ALTER TABLE meetings ADD COLUMN mic_device TEXT;
+            ALTER TABLE meetings ADD COLUMN loopback_device TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+/// V5: Meeting recording file and per-transcript 16kHz sample offsets into it.
+fn migration_v5() -> Migration {
+    Migration {
+        version: 5,
+        description: "add_audio_recording",
+        sql: r#"
+            ALTER TABLE meetings ADD COLUMN audio_path TEXT;
+            ALTER TABLE transcripts ADD COLUMN start_sample INTEGER;
+            ALTER TABLE transcripts ADD COLUMN end_sample INTEGER;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+/// V6: Per-device VAD thresholds from `calibrate_vad`.
+fn migration_v6() -> Migration {
+    Migration {
+        version: 6,
+        description: "create_vad_calibrations",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS vad_calibrations (
+                device_id     TEXT PRIMARY KEY,
+                noise_floor   REAL NOT NULL,
+                rms_threshold REAL NOT NULL,
+                calibrated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+/// V7: Capture health incidents (stalls, drops, backlog) per meeting.
+fn migration_v7() -> Migration {
+    Migration {
+        version: 7,
+        description: "create_audio_incidents",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS audio_incidents (
+                id             INTEGER PRIMARY KEY AUTOINCREMENT,
+                meeting_id     INTEGER NOT NULL
+                    REFERENCES meetings(id) ON DELETE CASCADE,
+                source         TEXT NOT NULL,
+                issue          TEXT NOT NULL,
+                severity       TEXT NOT NULL,
+                started_at     TEXT NOT NULL,
+                ended_at       TEXT NOT NULL DEFAULT (datetime('now')),
+                duration_ms    INTEGER NOT NULL,
+                dropped_frames INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_audio_incidents_meeting_id
+                ON audio_incidents(meeting_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+/// V8: Language of each transcript, as configured or detected, with its probability.
+fn migration_v8() -> Migration {
+    Migration {
+        version: 8,
+        description: "add_language_to_transcripts",
+        sql: r#"
+            ALTER TABLE transcripts ADD COLUMN language TEXT;
+            ALTER TABLE transcripts ADD COLUMN language_prob REAL;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
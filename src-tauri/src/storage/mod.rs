@@ -1,8 +1,14 @@
+pub mod calibration_store;
+pub mod incident_store;
 pub mod migrations;
 mod models;
 pub mod note_store;
 pub mod transcript_store;
 
-pub use models::{MeetingRecord, NoteRecord, TranscriptRecord, TranslationRecord};
+pub use calibration_store::CalibrationStore;
+pub use incident_store::IncidentStore;
+pub use models::{
+    AudioIncident, MeetingRecord, NoteRecord, TranscriptRecord, TranslationRecord, VadCalibration,
+};
 pub use note_store::NoteStore;
 pub use transcript_store::TranscriptDb;
@@ -9,6 +9,10 @@ pub struct MeetingRecord {
     pub source_lang: String,
     pub target_langs: String,
     pub status: String,
+    pub mic_device: Option<String>,
+    pub loopback_device: Option<String>,
+    /// Mixed 16kHz mono recording, when the meeting was recorded.
+    pub audio_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +24,12 @@ pub struct TranscriptRecord {
     pub translated_text: Option<String>,
     pub timestamp: String,
     pub is_final: bool,
+    /// Sample range `[start, end)` at 16kHz into the meeting recording.
+    pub start_sample: Option<i64>,
+    pub end_sample: Option<i64>,
+    /// Spoken language, and its detection probability when it was auto-detected.
+    pub language: Option<String>,
+    pub language_prob: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +51,32 @@ pub struct TranslationRecord {
     pub created_at: Option<String>,
 }
 
+/// Room-tone VAD thresholds measured for one capture device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VadCalibration {
+    pub device_id: String,
+    pub noise_floor: f64,
+    pub rms_threshold: f64,
+    pub calibrated_at: Option<String>,
+}
+
+/// A stretch of unhealthy capture or transcription (stall, drops, backlog) during a meeting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioIncident {
+    pub id: Option<i64>,
+    pub meeting_id: i64,
+    /// `mic` or `loopback`.
+    pub source: String,
+    /// `stall`, `drops`, `backlog`, `stt_drops` or `stt_backlog`.
+    pub issue: String,
+    /// Worst state reached: `warning` or `critical`.
+    pub severity: String,
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration_ms: i64,
+    pub dropped_frames: i64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,6 +91,9 @@ mod tests {
             source_lang: "en".to_string(),
             target_langs: "vi".to_string(),
             status: "idle".to_string(),
+            mic_device: None,
+            loopback_device: None,
+            audio_path: None,
         };
         assert_eq!(meeting.title, "Test Meeting");
     }
@@ -1,3 +1,4 @@
+use crate::audio::DeviceSelection;
 use crate::storage::models::{MeetingRecord, TranscriptRecord, TranslationRecord};
 use rusqlite::{params, Connection};
 use std::path::Path;
@@ -35,31 +36,67 @@ impl TranscriptDb {
         })
     }
 
-    /// Create a new meeting record. Returns the meeting_id.
-    pub fn create_meeting(&self, source_lang: &str, target_langs: &str) -> Result<i64, String> {
+    /// Create a new meeting record with the capture devices in use. Returns the meeting_id.
+    pub fn create_meeting(
+        &self,
+        source_lang: &str,
+        target_langs: &str,
+        devices: &DeviceSelection,
+    ) -> Result<i64, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         conn.execute(
-            "INSERT INTO meetings (source_lang, target_langs, status) VALUES (?1, ?2, 'recording')",
-            params![source_lang, target_langs],
+            "INSERT INTO meetings (source_lang, target_langs, status, mic_device, loopback_device) \
+             VALUES (?1, ?2, 'recording', ?3, ?4)",
+            params![source_lang, target_langs, devices.mic, devices.loopback],
         )
         .map_err(|e| format!("Failed to create meeting: {}", e))?;
         Ok(conn.last_insert_rowid())
     }
 
+    /// Point a meeting at its audio recording.
+    pub fn set_meeting_audio_path(&self, meeting_id: i64, path: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE meetings SET audio_path = ?1 WHERE id = ?2",
+            params![path, meeting_id],
+        )
+        .map_err(|e| format!("Failed to set audio path: {}", e))?;
+        Ok(())
+    }
+
     /// Insert a transcript row for a finalized STT segment.
+    /// `samples` is the segment's `[start, end)` range in the 16kHz meeting stream;
+    /// `speaker` is who said it, as far as known (e.g. "Me" / "Remote");
+    /// `language` is the spoken language and its detection probability, if detected.
+    #[allow(clippy::too_many_arguments)]
     pub fn insert_transcript(
         &self,
         meeting_id: i64,
         text: &str,
         segment_id: &str,
+        speaker: Option<&str>,
         timestamp_ms: i64,
+        samples: (i64, i64),
+        language: (&str, Option<f32>),
     ) -> Result<i64, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let ts = format_ms_to_timestamp(timestamp_ms);
         conn.execute(
-            "INSERT INTO transcripts (meeting_id, text, segment_id, timestamp, is_final) \
-             VALUES (?1, ?2, ?3, ?4, 1)",
-            params![meeting_id, text, segment_id, ts],
+            "INSERT INTO transcripts \
+             (meeting_id, text, segment_id, speaker, timestamp, is_final, start_sample, end_sample, \
+             language, language_prob) \
+             VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?7, ?8, ?9)",
+            params![
+                meeting_id,
+                text,
+                segment_id,
+                speaker,
+                ts,
+                samples.0,
+                samples.1,
+                language.0,
+                language.1.map(f64::from)
+            ],
         )
         .map_err(|e| format!("Failed to insert transcript: {}", e))?;
         Ok(conn.last_insert_rowid())
@@ -82,6 +119,16 @@ impl TranscriptDb {
         Ok(())
     }
 
+    /// Relabel a meeting's segments from speaker `from` to `to`. Returns rows changed.
+    pub fn rename_speaker(&self, meeting_id: i64, from: &str, to: &str) -> Result<usize, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE transcripts SET speaker = ?1 WHERE meeting_id = ?2 AND speaker = ?3",
+            params![to, meeting_id, from],
+        )
+        .map_err(|e| format!("Failed to rename speaker: {}", e))
+    }
+
     /// Mark meeting as stopped with ended_at timestamp.
     pub fn end_meeting(&self, meeting_id: i64) -> Result<(), String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
@@ -93,12 +140,25 @@ impl TranscriptDb {
         Ok(())
     }
 
+    /// Delete a meeting and its transcripts, e.g. one whose start failed.
+    pub fn delete_meeting(&self, meeting_id: i64) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "DELETE FROM transcripts WHERE meeting_id = ?1",
+            params![meeting_id],
+        )
+        .map_err(|e| format!("Failed to delete meeting: {}", e))?;
+        conn.execute("DELETE FROM meetings WHERE id = ?1", params![meeting_id])
+            .map_err(|e| format!("Failed to delete meeting: {}", e))?;
+        Ok(())
+    }
+
     /// Get meeting metadata by ID.
     pub fn get_meeting(&self, meeting_id: i64) -> Result<MeetingRecord, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         conn.query_row(
-            "SELECT id, title, started_at, ended_at, source_lang, target_langs, status \
-             FROM meetings WHERE id = ?1",
+            "SELECT id, title, started_at, ended_at, source_lang, target_langs, status, \
+             mic_device, loopback_device, audio_path FROM meetings WHERE id = ?1",
             params![meeting_id],
             |row| {
                 Ok(MeetingRecord {
@@ -109,6 +169,9 @@ impl TranscriptDb {
                     source_lang: row.get(4)?,
                     target_langs: row.get(5)?,
                     status: row.get(6)?,
+                    mic_device: row.get(7)?,
+                    loopback_device: row.get(8)?,
+                    audio_path: row.get(9)?,
                 })
             },
         )
@@ -123,7 +186,8 @@ impl TranscriptDb {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let mut stmt = conn
             .prepare(
-                "SELECT id, meeting_id, speaker, text, translated_text, timestamp, is_final \
+                "SELECT id, meeting_id, speaker, text, translated_text, timestamp, is_final, \
+                 start_sample, end_sample, language, language_prob \
                  FROM transcripts WHERE meeting_id = ?1 AND is_final = 1 \
                  ORDER BY id ASC",
             )
@@ -139,6 +203,10 @@ impl TranscriptDb {
                     translated_text: row.get(4)?,
                     timestamp: row.get(5)?,
                     is_final: row.get::<_, i32>(6)? != 0,
+                    start_sample: row.get(7)?,
+                    end_sample: row.get(8)?,
+                    language: row.get(9)?,
+                    language_prob: row.get(10)?,
                 })
             })
             .map_err(|e| format!("Query failed: {}", e))?;
@@ -185,6 +253,26 @@ impl TranscriptDb {
         }
     }
 
+    /// 16kHz sample range of a transcript row in the meeting recording.
+    /// `None` if the row does not exist or predates recording support.
+    pub fn get_segment_samples(
+        &self,
+        meeting_id: i64,
+        segment_id: &str,
+    ) -> Result<Option<(i64, i64)>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        match conn.query_row(
+            "SELECT start_sample, end_sample FROM transcripts \
+             WHERE meeting_id = ?1 AND segment_id = ?2",
+            params![meeting_id, segment_id],
+            |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<i64>>(1)?)),
+        ) {
+            Ok((Some(start), Some(end))) => Ok(Some((start, end))),
+            Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(format!("Query failed: {}", e)),
+        }
+    }
+
     /// Get all translations for a specific transcript.
     pub fn get_translations_for_transcript(
         &self,
@@ -261,6 +349,127 @@ fn format_ms_to_timestamp(ms: i64) -> String {
 mod tests {
     use super::*;
 
+    fn create_test_db() -> TranscriptDb {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE meetings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL DEFAULT 'Untitled Meeting',
+                started_at TEXT NOT NULL DEFAULT (datetime('now')),
+                ended_at TEXT,
+                source_lang TEXT NOT NULL DEFAULT 'auto',
+                target_langs TEXT NOT NULL DEFAULT 'vi',
+                status TEXT NOT NULL DEFAULT 'idle',
+                mic_device TEXT,
+                loopback_device TEXT,
+                audio_path TEXT
+            );
+            CREATE TABLE transcripts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                meeting_id INTEGER NOT NULL,
+                speaker TEXT,
+                text TEXT NOT NULL,
+                translated_text TEXT,
+                timestamp TEXT NOT NULL DEFAULT (datetime('now')),
+                is_final INTEGER NOT NULL DEFAULT 0,
+                segment_id TEXT,
+                start_sample INTEGER,
+                end_sample INTEGER,
+                language TEXT,
+                language_prob REAL
+            );",
+        )
+        .unwrap();
+        TranscriptDb::new(Arc::new(Mutex::new(conn)))
+    }
+
+    #[test]
+    fn meeting_remembers_capture_devices() {
+        let db = create_test_db();
+        let devices = DeviceSelection {
+            mic: Some("USB Headset".to_string()),
+            loopback: Some("loopback:Speakers".to_string()),
+        };
+        let id = db.create_meeting("en", "vi", &devices).unwrap();
+
+        let meeting = db.get_meeting(id).unwrap();
+        assert_eq!(meeting.mic_device.as_deref(), Some("USB Headset"));
+        assert_eq!(meeting.loopback_device.as_deref(), Some("loopback:Speakers"));
+        assert_eq!(meeting.status, "recording");
+    }
+
+    #[test]
+    fn deletes_meeting_with_its_transcripts() {
+        let db = create_test_db();
+        let kept = db.create_meeting("en", "vi", &DeviceSelection::default()).unwrap();
+        let id = db.create_meeting("en", "vi", &DeviceSelection::default()).unwrap();
+        for meeting in [kept, id] {
+            db.insert_transcript(meeting, "hi", "seg-1-0", None, 0, (0, 1600), ("en", None))
+                .unwrap();
+        }
+
+        db.delete_meeting(id).unwrap();
+        assert!(db.get_meeting(id).is_err());
+        assert!(db.get_meeting_transcripts(id).unwrap().is_empty());
+        assert_eq!(db.get_meeting_transcripts(kept).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn transcripts_keep_recording_offsets() {
+        let db = create_test_db();
+        let id = db.create_meeting("en", "vi", &DeviceSelection::default()).unwrap();
+        db.set_meeting_audio_path(id, "/data/recordings/meeting-1.wav").unwrap();
+        db.insert_transcript(
+            id,
+            "hello",
+            "seg-1-0",
+            Some("Remote"),
+            0,
+            (1600, 17600),
+            ("vi", Some(0.75)),
+        )
+        .unwrap();
+
+        let meeting = db.get_meeting(id).unwrap();
+        assert_eq!(meeting.audio_path.as_deref(), Some("/data/recordings/meeting-1.wav"));
+        let rows = db.get_meeting_transcripts(id).unwrap();
+        assert_eq!(rows[0].speaker.as_deref(), Some("Remote"));
+        assert_eq!(rows[0].start_sample, Some(1600));
+        assert_eq!(rows[0].end_sample, Some(17600));
+        assert_eq!(rows[0].language.as_deref(), Some("vi"));
+        assert_eq!(rows[0].language_prob, Some(0.75));
+        assert_eq!(db.get_segment_samples(id, "seg-1-0").unwrap(), Some((1600, 17600)));
+        assert_eq!(db.get_segment_samples(id, "seg-9-0").unwrap(), None);
+    }
+
+    #[test]
+    fn renames_speaker_within_one_meeting() {
+        let db = create_test_db();
+        let devices = DeviceSelection::default();
+        let (a, b) = (
+            db.create_meeting("en", "vi", &devices).unwrap(),
+            db.create_meeting("en", "vi", &devices).unwrap(),
+        );
+        let insert = |id, seg, speaker| {
+            db.insert_transcript(id, "hi", seg, Some(speaker), 0, (0, 16000), ("en", None))
+                .unwrap()
+        };
+        insert(a, "seg-1-0", "Speaker 1");
+        insert(a, "seg-2-0", "Speaker 2");
+        insert(b, "seg-1-0", "Speaker 1");
+
+        assert_eq!(db.rename_speaker(a, "Speaker 1", "Alice").unwrap(), 1);
+        let speakers = |id| -> Vec<_> {
+            db.get_meeting_transcripts(id)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.speaker.unwrap())
+                .collect()
+        };
+        assert_eq!(speakers(a), vec!["Alice", "Speaker 2"]);
+        assert_eq!(speakers(b), vec!["Speaker 1"]);
+    }
+
     #[test]
     fn format_ms_converts_correctly() {
         assert_eq!(format_ms_to_timestamp(0), "00:00:00");
@@ -0,0 +1,328 @@
+use crate::stt::fbank::Fbank;
+use anyhow::{Context, Result};
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Filterbank bins speaker-embedding models take.
+const FBANK_BINS: usize = 80;
+
+/// Default speaker model location: {app_data}/models/speaker/speaker_embedding.onnx
+pub fn default_model_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir
+        .join("models")
+        .join("speaker")
+        .join("speaker_embedding.onnx")
+}
+
+/// Names given to speaker labels while a meeting runs (label → name), applied to
+/// segments transcribed after a rename.
+pub type SpeakerNames = Arc<Mutex<HashMap<String, String>>>;
+
+/// Record that the speaker shown as `from` (a label or an earlier name) is now `to`.
+pub fn rename_speaker(names: &mut HashMap<String, String>, from: &str, to: &str) {
+    let mut renamed = false;
+    for name in names.values_mut().filter(|n| n.as_str() == from) {
+        *name = to.to_string();
+        renamed = true;
+    }
+    if !renamed {
+        names.insert(from.to_string(), to.to_string());
+    }
+}
+
+/// Speaker diarization tunables, part of `PipelineConfig`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiarizationConfig {
+    /// Label segments "Speaker 1..N" by voice instead of by source ("Me" / "Remote").
+    pub enabled: bool,
+    /// ONNX speaker-embedding model; the app's default location when unset.
+    pub model_path: Option<PathBuf>,
+    /// Cosine similarity to a speaker's centroid needed to join that speaker.
+    pub similarity_threshold: f32,
+    /// New voices past this many speakers join the closest one.
+    pub max_speakers: usize,
+    /// Segments shorter than this are too short to tell voices apart and keep
+    /// the previous label of their utterance.
+    pub min_segment_ms: u32,
+}
+
+impl Default for DiarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_path: None,
+            similarity_threshold: 0.5,
+            max_speakers: 8,
+            min_segment_ms: 800,
+        }
+    }
+}
+
+/// Maps filterbank features (`[frames, bins]`, row-major) to a speaker embedding.
+pub trait SpeakerModel: Send {
+    fn embed(&mut self, features: &[f32], bins: usize) -> Result<Vec<f32>>;
+}
+
+/// ONNX speaker-embedding model on CPU taking `[1, frames, 80]` fbank features and
+/// returning `[1, dim]`, like the WeSpeaker and 3D-Speaker (ResNet, CAM++) exports.
+pub struct OnnxSpeakerModel {
+    session: Session,
+}
+
+impl OnnxSpeakerModel {
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            anyhow::bail!("Speaker model not found: {}", path.display());
+        }
+        // ort panics instead of erroring when the runtime library is missing
+        let session = catch_unwind(AssertUnwindSafe(|| {
+            Session::builder()?
+                .with_intra_threads(1)?
+                .commit_from_file(path)
+        }))
+        .map_err(|_| anyhow::anyhow!("ONNX Runtime library could not be loaded"))?
+        .with_context(|| format!("Failed to load speaker model {}", path.display()))?;
+
+        tracing::info!("Speaker model loaded from {}", path.display());
+        Ok(Self { session })
+    }
+}
+
+impl SpeakerModel for OnnxSpeakerModel {
+    fn embed(&mut self, features: &[f32], bins: usize) -> Result<Vec<f32>> {
+        let frames = features.len() / bins;
+        let input = Tensor::from_array(([1, frames, bins], features.to_vec()))?;
+        let outputs = self.session.run(ort::inputs![input])?;
+        let (_, embedding) = outputs[0].try_extract_tensor::<f32>()?;
+        Ok(embedding.to_vec())
+    }
+}
+
+/// Online clustering of speaker embeddings by cosine similarity to running centroids.
+pub struct SpeakerClusters {
+    /// Sum of the unit embeddings assigned to each speaker.
+    centroids: Vec<Vec<f32>>,
+    threshold: f32,
+    max_speakers: usize,
+}
+
+impl SpeakerClusters {
+    pub fn new(threshold: f32, max_speakers: usize) -> Self {
+        Self {
+            centroids: Vec::new(),
+            threshold,
+            max_speakers: max_speakers.max(1),
+        }
+    }
+
+    /// Index of the speaker `embedding` belongs to, opening a new speaker when it
+    /// is not close enough to any. None for an empty or silent embedding.
+    pub fn assign(&mut self, embedding: &[f32]) -> Option<usize> {
+        let unit = normalized(embedding)?;
+        let index = match self.closest(&unit) {
+            Some((i, similarity))
+                if similarity >= self.threshold || self.centroids.len() >= self.max_speakers =>
+            {
+                i
+            }
+            _ => {
+                self.centroids.push(vec![0.0; unit.len()]);
+                self.centroids.len() - 1
+            }
+        };
+        for (c, u) in self.centroids[index].iter_mut().zip(&unit) {
+            *c += u;
+        }
+        Some(index)
+    }
+
+    /// Known speaker `embedding` is close enough to, leaving the centroids as they
+    /// are. None when no speaker is.
+    pub fn nearest(&self, embedding: &[f32]) -> Option<usize> {
+        let unit = normalized(embedding)?;
+        let (index, similarity) = self.closest(&unit)?;
+        (similarity >= self.threshold).then_some(index)
+    }
+
+    /// Most similar speaker to a unit embedding, with its cosine similarity.
+    fn closest(&self, unit: &[f32]) -> Option<(usize, f32)> {
+        self.centroids
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| Some((i, dot(&normalized(c)?, unit))))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn speakers(&self) -> usize {
+        self.centroids.len()
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(v: &[f32]) -> Option<Vec<f32>> {
+    let norm = dot(v, v).sqrt();
+    (norm > f32::EPSILON).then(|| v.iter().map(|x| x / norm).collect())
+}
+
+/// Labels transcript segments by voice: fbank features → speaker embedding →
+/// online clustering into "Speaker 1..N", numbered in order of first appearance.
+pub struct Diarizer {
+    fbank: Fbank,
+    model: Box<dyn SpeakerModel>,
+    clusters: SpeakerClusters,
+    min_samples: usize,
+    reported_error: bool,
+}
+
+impl Diarizer {
+    /// Open the configured ONNX model (`model_path` must be set).
+    pub fn open(config: &DiarizationConfig) -> Result<Self> {
+        let path = config
+            .model_path
+            .as_deref()
+            .context("No speaker model path configured")?;
+        Ok(Self::new(Box::new(OnnxSpeakerModel::open(path)?), config))
+    }
+
+    pub fn new(model: Box<dyn SpeakerModel>, config: &DiarizationConfig) -> Self {
+        Self {
+            fbank: Fbank::new(FBANK_BINS),
+            model,
+            clusters: SpeakerClusters::new(config.similarity_threshold, config.max_speakers),
+            min_samples: config.min_segment_ms as usize * 16,
+            reported_error: false,
+        }
+    }
+
+    /// Speaker of a segment of 16kHz mono audio, or None when it is too short
+    /// to tell or the model fails.
+    pub fn label(&mut self, audio: &[f32]) -> Option<String> {
+        let embedding = self.embed(audio)?;
+        let speaker = self.clusters.assign(&embedding)?;
+        Some(format!("Speaker {}", speaker + 1))
+    }
+
+    /// Known speaker whose voice `audio` is closest to, without counting it
+    /// towards that speaker: for audio already labelled in parts.
+    pub fn nearest_label(&mut self, audio: &[f32]) -> Option<String> {
+        let embedding = self.embed(audio)?;
+        let speaker = self.clusters.nearest(&embedding)?;
+        Some(format!("Speaker {}", speaker + 1))
+    }
+
+    fn embed(&mut self, audio: &[f32]) -> Option<Vec<f32>> {
+        if audio.len() < self.min_samples {
+            return None;
+        }
+        let features = self.fbank.compute(audio);
+        match self.model.embed(&features, FBANK_BINS) {
+            Ok(embedding) => Some(embedding),
+            Err(e) => {
+                if !self.reported_error {
+                    tracing::warn!("Speaker embedding failed: {}", e);
+                    self.reported_error = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit vector along `axis`, nudged along the next axis.
+    fn voice(axis: usize, nudge: f32) -> Vec<f32> {
+        let mut v = vec![0.0; 8];
+        v[axis] = 1.0;
+        v[(axis + 1) % 8] = nudge;
+        v
+    }
+
+    #[test]
+    fn clusters_voices_in_order_of_appearance() {
+        let mut clusters = SpeakerClusters::new(0.5, 8);
+        let labels: Vec<_> = [voice(0, 0.1), voice(3, 0.2), voice(0, -0.2), voice(3, 0.0)]
+            .iter()
+            .map(|v| clusters.assign(v).unwrap())
+            .collect();
+        assert_eq!(labels, vec![0, 1, 0, 1]);
+        assert_eq!(clusters.assign(&[0.0; 8]), None);
+
+        // Past the cap a new voice joins the closest speaker
+        let mut capped = SpeakerClusters::new(0.5, 1);
+        capped.assign(&voice(0, 0.0));
+        assert_eq!(capped.assign(&voice(5, 0.0)), Some(0));
+        assert_eq!(capped.speakers(), 1);
+    }
+
+    #[test]
+    fn nearest_speaker_leaves_centroids_alone() {
+        let mut clusters = SpeakerClusters::new(0.5, 8);
+        clusters.assign(&voice(0, 0.0));
+        clusters.assign(&voice(3, 0.0));
+        assert_eq!(clusters.nearest(&voice(3, 0.2)), Some(1));
+        assert_eq!(clusters.nearest(&voice(6, 0.0)), None);
+        assert_eq!(clusters.speakers(), 2);
+
+        // Lookups do not drag the first speaker towards axis 1, as assigning them would
+        for _ in 0..20 {
+            assert_eq!(clusters.nearest(&voice(0, 0.9)), Some(0));
+        }
+        assert_eq!(clusters.assign(&voice(1, 0.0)), Some(2));
+    }
+
+    /// Returns scripted embeddings in order.
+    struct Scripted(Vec<Vec<f32>>);
+
+    impl SpeakerModel for Scripted {
+        fn embed(&mut self, features: &[f32], bins: usize) -> Result<Vec<f32>> {
+            assert_eq!(bins, FBANK_BINS);
+            assert!(!features.is_empty());
+            Ok(self.0.remove(0))
+        }
+    }
+
+    #[test]
+    fn labels_segments_long_enough_to_tell() {
+        let model = Scripted(vec![voice(0, 0.0), voice(4, 0.0), voice(0, 0.1)]);
+        let mut diarizer = Diarizer::new(Box::new(model), &DiarizationConfig::default());
+        let second: Vec<f32> = (0..16000).map(|i| (i as f32 * 0.05).sin() * 0.2).collect();
+
+        assert_eq!(diarizer.label(&second[..8000]), None);
+        assert_eq!(diarizer.label(&second).as_deref(), Some("Speaker 1"));
+        assert_eq!(diarizer.label(&second).as_deref(), Some("Speaker 2"));
+        assert_eq!(diarizer.label(&second).as_deref(), Some("Speaker 1"));
+    }
+
+    #[test]
+    fn renames_follow_earlier_renames() {
+        let mut names = HashMap::new();
+        rename_speaker(&mut names, "Speaker 1", "Alice");
+        rename_speaker(&mut names, "Alice", "Alicia");
+        rename_speaker(&mut names, "Remote", "Bob");
+        assert_eq!(names["Speaker 1"], "Alicia");
+        assert_eq!(names["Remote"], "Bob");
+        assert_eq!(names.len(), 2);
+    }
+
+    #[test]
+    fn open_fails_without_model() {
+        let config = DiarizationConfig {
+            model_path: Some(PathBuf::from("/nonexistent/speaker.onnx")),
+            ..DiarizationConfig::default()
+        };
+        let err = Diarizer::open(&config).err().unwrap().to_string();
+        assert!(err.contains("not found"), "{}", err);
+    }
+}
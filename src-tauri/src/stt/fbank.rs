@@ -0,0 +1,172 @@
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+/// Analysis frame (25ms) and hop (10ms) at 16kHz.
+const FRAME: usize = 400;
+const HOP: usize = 160;
+const FFT_SIZE: usize = 512;
+/// Lowest filter edge; the highest is Nyquist.
+const LOW_HZ: f32 = 20.0;
+const PRE_EMPHASIS: f32 = 0.97;
+/// Log floor for silent bins.
+const ENERGY_FLOOR: f32 = f32::EPSILON;
+
+/// Kaldi-style log-mel filterbank of 16kHz audio, the input speaker-embedding
+/// models are trained on: per 25ms frame, DC removal, pre-emphasis, Povey window,
+/// power spectrum and `bins` triangular mel filters. Features are mean-normalized
+/// per call, so loudness and channel colour matter less than the voice.
+pub struct Fbank {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Per mel bin: first FFT bin and its weights.
+    filters: Vec<(usize, Vec<f32>)>,
+}
+
+impl Fbank {
+    pub fn new(bins: usize) -> Self {
+        let window = (0..FRAME)
+            .map(|i| {
+                let hann =
+                    0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (FRAME - 1) as f32).cos();
+                hann.powf(0.85)
+            })
+            .collect();
+        Self {
+            fft: RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE),
+            window,
+            filters: mel_filters(bins, 16000.0),
+        }
+    }
+
+    pub fn bins(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Features of `audio`, row-major `[frames, bins]`. Empty if shorter than one frame.
+    pub fn compute(&self, audio: &[f32]) -> Vec<f32> {
+        let bins = self.bins();
+        let mut features = self.log_mel(audio);
+        let frames = features.len() / bins;
+        // Cepstral mean normalization over the segment
+        for b in 0..bins {
+            let mean = features.iter().skip(b).step_by(bins).sum::<f32>() / frames as f32;
+            for v in features.iter_mut().skip(b).step_by(bins) {
+                *v -= mean;
+            }
+        }
+        features
+    }
+
+    /// Log mel energies per frame, before normalization.
+    fn log_mel(&self, audio: &[f32]) -> Vec<f32> {
+        let bins = self.bins();
+        if audio.len() < FRAME {
+            return Vec::new();
+        }
+        let frames = 1 + (audio.len() - FRAME) / HOP;
+        let mut features = Vec::with_capacity(frames * bins);
+        let mut input = vec![0.0; FFT_SIZE];
+        let mut spectrum = vec![Complex::default(); FFT_SIZE / 2 + 1];
+
+        for f in 0..frames {
+            let frame = &audio[f * HOP..f * HOP + FRAME];
+            let mean = frame.iter().sum::<f32>() / FRAME as f32;
+            input.fill(0.0);
+            for i in 0..FRAME {
+                let prev = if i > 0 { frame[i - 1] } else { frame[0] };
+                let s = (frame[i] - mean) - PRE_EMPHASIS * (prev - mean);
+                input[i] = s * self.window[i] * 32768.0;
+            }
+            if self.fft.process(&mut input, &mut spectrum).is_err() {
+                return Vec::new();
+            }
+            for (start, weights) in &self.filters {
+                let energy: f32 = weights
+                    .iter()
+                    .zip(&spectrum[*start..])
+                    .map(|(w, c)| w * c.norm_sqr())
+                    .sum();
+                features.push(energy.max(ENERGY_FLOOR).ln());
+            }
+        }
+        features
+    }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    1127.0 * (1.0 + hz / 700.0).ln()
+}
+
+/// Triangular filters evenly spaced on the mel scale between `LOW_HZ` and Nyquist.
+fn mel_filters(bins: usize, sample_rate: f32) -> Vec<(usize, Vec<f32>)> {
+    let low = hz_to_mel(LOW_HZ);
+    let high = hz_to_mel(sample_rate / 2.0);
+    let step = (high - low) / (bins + 1) as f32;
+    let fft_bin_hz = sample_rate / FFT_SIZE as f32;
+
+    (0..bins)
+        .map(|m| {
+            let (left, center, right) = (
+                low + m as f32 * step,
+                low + (m + 1) as f32 * step,
+                low + (m + 2) as f32 * step,
+            );
+            let weights: Vec<(usize, f32)> = (0..=FFT_SIZE / 2)
+                .filter_map(|k| {
+                    let mel = hz_to_mel(k as f32 * fft_bin_hz);
+                    let w = if mel > left && mel <= center {
+                        (mel - left) / (center - left)
+                    } else if mel > center && mel < right {
+                        (right - mel) / (right - center)
+                    } else {
+                        0.0
+                    };
+                    (w > 0.0).then_some((k, w))
+                })
+                .collect();
+            let start = weights.first().map_or(0, |(k, _)| *k);
+            (start, weights.into_iter().map(|(_, w)| w).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * freq * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn frames_every_10ms_with_energy_at_the_tone() {
+        let fbank = Fbank::new(80);
+        let loudest = |freq| {
+            let features = fbank.log_mel(&tone(freq, 16000));
+            assert_eq!(features.len(), 98 * 80);
+            (0..80)
+                .max_by(|&a, &b| features[a].total_cmp(&features[b]))
+                .unwrap()
+        };
+        let (low, high) = (loudest(300.0), loudest(3000.0));
+        assert!(low < high && high < 70, "{} {}", low, high);
+    }
+
+    #[test]
+    fn normalizes_each_bin_to_zero_mean() {
+        let mut audio = tone(300.0, 8000);
+        audio.extend(tone(1000.0, 8000));
+        let features = Fbank::new(80).compute(&audio);
+        let frames = features.len() / 80;
+        let mean = features.iter().step_by(80).sum::<f32>() / frames as f32;
+        assert!(mean.abs() < 1e-3);
+    }
+
+    #[test]
+    fn short_audio_has_no_frames() {
+        assert!(Fbank::new(80).compute(&[0.0; 399]).is_empty());
+    }
+}
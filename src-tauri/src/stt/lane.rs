@@ -0,0 +1,340 @@
+use crate::audio::dsp::{DspChain, DspConfig};
+use crate::audio::resampler::{AudioResampler, ResamplerKind};
+use crate::audio::ring::RingReader;
+use crate::audio::types::{SourceFormat, SourceRole};
+use crate::audio::utterance::{Utterance, UtteranceBuilder};
+use crate::audio::vad::VadConfig;
+use crate::audio::voice_detector::VoiceDetector;
+use anyhow::Result;
+use crossbeam::channel::RecvTimeoutError;
+use std::time::Duration;
+
+/// VAD frame: 10ms at 16kHz.
+const FRAME_SIZE: usize = 160;
+/// Longest one lane's recording may run ahead of another before the gap is
+/// written as silence (1s at 16kHz).
+const MAX_RECORDING_LAG: usize = 16000;
+
+/// Speaker stored for segments transcribed from `role`'s lane.
+pub fn speaker_label(role: SourceRole) -> &'static str {
+    match role {
+        SourceRole::Mic => "Me",
+        SourceRole::Loopback => "Remote",
+    }
+}
+
+/// One capture source handed to the STT pipeline.
+pub struct LaneInput {
+    pub role: SourceRole,
+    pub rx: RingReader,
+    /// Format of the samples in `rx`.
+    pub format: SourceFormat,
+    /// Voice detector for this source (see `create_detector`).
+    pub vad: Box<dyn VoiceDetector>,
+}
+
+/// Front end of one source: resample to 16kHz mono → DSP → VAD → utterances.
+/// Each source has its own detector and utterance buffer, so mic and loopback
+/// speech are cut and transcribed separately.
+pub struct Lane {
+    pub role: SourceRole,
+    rx: RingReader,
+    input: Vec<f32>,
+    resampler: Option<AudioResampler>,
+    resample_buf: Vec<f32>,
+    chunk_samples: usize,
+    frame_buf: Vec<f32>,
+    dsp: DspChain,
+    /// Samples a processed frame lags the source: resampler delay plus DSP latency.
+    latency: u64,
+    vad: Box<dyn VoiceDetector>,
+    utterances: UtteranceBuilder,
+    /// Position in the 16kHz stream: next frame's sample offset.
+    stream_pos: u64,
+    /// Raw 16kHz audio not yet written to the meeting recording.
+    recorded: Vec<f32>,
+    /// Resampler delay still to drop from the recording, so it starts where the
+    /// source did and transcript offsets index it.
+    unrecorded_delay: usize,
+}
+
+impl Lane {
+    pub fn new(
+        input: LaneInput,
+        dsp: &DspConfig,
+        vad: &VadConfig,
+        kind: ResamplerKind,
+    ) -> Result<Self> {
+        let LaneInput {
+            role,
+            rx,
+            format,
+            vad: detector,
+        } = input;
+        let channels = format.channels.max(1) as usize;
+        let needs_resample = format.sample_rate != 16000 || channels != 1;
+        let resampler = if needs_resample {
+            let rs = AudioResampler::with_kind(kind, format.sample_rate, 16000, channels)?;
+            tracing::info!(
+                "STT {} resampler ({:?}): {}Hz {}ch -> 16kHz mono",
+                role.as_str(),
+                kind,
+                format.sample_rate,
+                format.channels,
+            );
+            Some(rs)
+        } else {
+            tracing::info!(
+                "STT {} lane already 16kHz mono, no resampling needed",
+                role.as_str()
+            );
+            None
+        };
+
+        // Buffers are sized once here and reused, so the loop does not allocate
+        let chunk_samples = kind.chunk_size() * channels;
+        let dsp = DspChain::new(dsp, 16000);
+        let resampler_delay = resampler.as_ref().map_or(0, |rs| rs.output_delay());
+        Ok(Self {
+            role,
+            rx,
+            input: vec![0.0; chunk_samples],
+            resampler,
+            resample_buf: Vec::with_capacity(chunk_samples * 2),
+            chunk_samples,
+            frame_buf: Vec::with_capacity(chunk_samples + FRAME_SIZE),
+            latency: (resampler_delay + dsp.latency()) as u64,
+            dsp,
+            vad: detector,
+            utterances: UtteranceBuilder::new(vad, 16000),
+            stream_pos: 0,
+            recorded: Vec::with_capacity(chunk_samples + FRAME_SIZE),
+            unrecorded_delay: resampler_delay,
+        })
+    }
+
+    /// Wait up to `timeout` for audio and take it in. Fails like the source ring.
+    pub fn receive(&mut self, timeout: Duration) -> Result<usize, RecvTimeoutError> {
+        let n = self.rx.recv_timeout(&mut self.input, timeout)?;
+        self.take(n);
+        Ok(n)
+    }
+
+    /// Take in all audio buffered so far, without waiting. Returns the sample count.
+    pub fn take_available(&mut self) -> usize {
+        let mut total = 0;
+        loop {
+            let n = self.rx.pop(&mut self.input);
+            if n == 0 {
+                return total;
+            }
+            self.take(n);
+            total += n;
+        }
+    }
+
+    /// Resample `input[..n]` to 16kHz mono, or pass it through.
+    fn take(&mut self, n: usize) {
+        let Some(ref mut rs) = self.resampler else {
+            self.frame_buf.extend_from_slice(&self.input[..n]);
+            return;
+        };
+        self.resample_buf.extend_from_slice(&self.input[..n]);
+        while self.resample_buf.len() >= self.chunk_samples {
+            let chunk = &self.resample_buf[..self.chunk_samples];
+            if let Err(e) = rs.process_into(chunk, &mut self.frame_buf) {
+                tracing::warn!("Resample error: {}", e);
+            }
+            self.resample_buf.drain(..self.chunk_samples);
+        }
+    }
+
+    /// End of input: flush the resampler and pad the last frame with silence.
+    pub fn finish_input(&mut self) {
+        // The partial last chunk holds the tail of the last sentence
+        if let Some(ref mut rs) = self.resampler {
+            if let Err(e) = rs.flush_into(&self.resample_buf, &mut self.frame_buf) {
+                tracing::warn!("Resampler flush error: {}", e);
+            }
+            self.resample_buf.clear();
+        }
+        let padded = self.frame_buf.len().div_ceil(FRAME_SIZE) * FRAME_SIZE;
+        self.frame_buf.resize(padded, 0.0);
+    }
+
+    /// Run complete frames through DSP and VAD; finished utterances go to `out`.
+    pub fn process(&mut self, out: &mut Vec<Utterance>) {
+        let mut frame = [0.0f32; FRAME_SIZE];
+        while self.frame_buf.len() >= FRAME_SIZE {
+            frame.copy_from_slice(&self.frame_buf[..FRAME_SIZE]);
+            self.frame_buf.drain(..FRAME_SIZE);
+            let skip = self.unrecorded_delay.min(FRAME_SIZE);
+            self.unrecorded_delay -= skip;
+            self.recorded.extend_from_slice(&frame[skip..]);
+            self.dsp.process(&mut frame);
+            // The processed frame lags the source by the resampler and DSP
+            let frame_start = self.stream_pos.saturating_sub(self.latency);
+            self.stream_pos += FRAME_SIZE as u64;
+
+            let event = self.vad.process_frame(&frame);
+            out.extend(self.utterances.push(&frame, event, frame_start));
+
+            // Length cap: send what we have up to its quietest recent point,
+            // the rest continues as the next utterance
+            if self.utterances.is_full() {
+                tracing::info!("Utterance at max length, splitting at a quiet point");
+                out.extend(self.utterances.split());
+            }
+        }
+    }
+
+    /// Speech collected so far for the utterance in progress, and its stream offset.
+    pub fn pending(&self) -> Option<(&[f32], u64)> {
+        self.utterances.pending()
+    }
+
+    /// Speech still buffered when the input ends.
+    pub fn flush(&mut self) -> Option<Utterance> {
+        self.utterances.flush()
+    }
+}
+
+/// Sum the lanes' raw 16kHz audio into `mix`, as far as every lane has reached
+/// (everything once `finished`). Lanes share the mic's timeline, so the sum is
+/// the meeting as heard, and transcript offsets of any lane index it.
+pub fn take_recorded(lanes: &mut [Lane], finished: bool, mix: &mut Vec<f32>) {
+    let lens = lanes.iter().map(|l| l.recorded.len());
+    let (min, max) = (lens.clone().min().unwrap_or(0), lens.max().unwrap_or(0));
+    let n = if finished || max - min > MAX_RECORDING_LAG {
+        max
+    } else {
+        min
+    };
+    mix.clear();
+    mix.resize(n, 0.0);
+    for lane in lanes.iter_mut() {
+        let take = n.min(lane.recorded.len());
+        for (m, s) in mix.iter_mut().zip(lane.recorded.drain(..take)) {
+            *m = (*m + s).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring::sample_ring;
+    use crate::audio::voice_detector::create_detector;
+
+    fn lane(role: SourceRole, sample_rate: u32, samples: &[f32]) -> Lane {
+        let (tx, rx) = sample_ring(samples.len(), 1);
+        assert!(tx.push(samples));
+        let format = SourceFormat {
+            sample_rate,
+            channels: 1,
+        };
+        let vad = VadConfig::default();
+        let input = LaneInput {
+            role,
+            rx,
+            format,
+            vad: create_detector(&vad).unwrap(),
+        };
+        Lane::new(input, &DspConfig::default(), &vad, ResamplerKind::Fft).unwrap()
+    }
+
+    /// `silence` seconds, then `tone` seconds of 440Hz, then 1s of silence.
+    fn speech(sample_rate: u32, silence: f32, tone: f32) -> Vec<f32> {
+        let rate = sample_rate as f32;
+        let start = (silence * rate) as usize;
+        let end = start + (tone * rate) as usize;
+        (0..end + sample_rate as usize)
+            .map(|i| {
+                let on = (start..end).contains(&i);
+                if on {
+                    0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lanes_cut_their_own_utterances_on_one_timeline() {
+        let mut lanes = [
+            lane(SourceRole::Mic, 16000, &speech(16000, 0.5, 1.0)),
+            lane(SourceRole::Loopback, 48000, &speech(48000, 2.0, 1.0)),
+        ];
+        let mut found = Vec::new();
+        for lane in lanes.iter_mut() {
+            lane.take_available();
+            lane.finish_input();
+            let mut out = Vec::new();
+            lane.process(&mut out);
+            out.extend(lane.flush());
+            assert_eq!(out.len(), 1, "{:?}", lane.role);
+            found.push((speaker_label(lane.role), out[0].start));
+        }
+
+        // Speech starts at 0.5s ("Me") and 2.0s ("Remote"), less up to 0.5s of pre-roll
+        assert_eq!(found[0].0, "Me");
+        assert!((0..=8000).contains(&found[0].1), "{:?}", found);
+        assert_eq!(found[1].0, "Remote");
+        assert!((24000..=32000).contains(&found[1].1), "{:?}", found);
+    }
+
+    #[test]
+    fn resampled_lane_places_speech_where_it_was_said() {
+        // Stream offset of the tone's first loud sample, at 16kHz and resampled
+        let mut onsets = Vec::new();
+        for rate in [16000, 48000] {
+            let mut lane = lane(SourceRole::Mic, rate, &speech(rate, 1.0, 1.0));
+            lane.take_available();
+            lane.finish_input();
+            let mut out = Vec::new();
+            lane.process(&mut out);
+            out.extend(lane.flush());
+            let loud = out[0].samples.iter().position(|s| s.abs() > 0.1).unwrap();
+            onsets.push(out[0].start + loud as u64);
+        }
+        assert!(onsets[0].abs_diff(onsets[1]) < 16, "{:?}", onsets);
+    }
+
+    #[test]
+    fn transcript_offsets_index_the_recording() {
+        let mut lanes = [lane(SourceRole::Loopback, 48000, &speech(48000, 1.0, 1.0))];
+        lanes[0].take_available();
+        lanes[0].finish_input();
+        let mut out = Vec::new();
+        lanes[0].process(&mut out);
+        out.extend(lanes[0].flush());
+        let mut recording = Vec::new();
+        take_recorded(&mut lanes, true, &mut recording);
+
+        // The tone's first loud sample, found by its transcript offset
+        let loud = out[0].samples.iter().position(|s| s.abs() > 0.1).unwrap();
+        let offset = out[0].start as usize + loud;
+        let onset = recording.iter().position(|s| s.abs() > 0.1).unwrap();
+        assert!(offset.abs_diff(onset) < 16, "{} vs {}", offset, onset);
+    }
+
+    #[test]
+    fn recording_sums_lanes_as_far_as_both_reached() {
+        let mut lanes = [
+            lane(SourceRole::Mic, 16000, &[0.25; 480]),
+            lane(SourceRole::Loopback, 16000, &[0.5; 320]),
+        ];
+        let mut unused = Vec::new();
+        for lane in lanes.iter_mut() {
+            lane.take_available();
+            lane.process(&mut unused);
+        }
+        let mut mix = Vec::new();
+        take_recorded(&mut lanes, false, &mut mix);
+        assert_eq!(mix, vec![0.75; 320]);
+        // The mic's extra frame is written once the lanes finish
+        take_recorded(&mut lanes, true, &mut mix);
+        assert_eq!(mix, vec![0.25; 160]);
+    }
+}
@@ -1,7 +1,14 @@
+pub mod diarization;
+mod fbank;
+pub mod lane;
+pub mod model_catalog;
+pub mod model_download;
+mod model_file;
 pub mod model_manager;
 pub mod pipeline;
+pub mod streaming;
 mod whisper;
 
-pub use model_manager::{ModelManager, DEFAULT_MODEL};
+pub use model_manager::{ModelEntry, ModelManager, DEFAULT_MODEL};
 pub use pipeline::SttPipeline;
 pub use whisper::{SttEngine, TranscriptSegment};
@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+/// A whisper.cpp ggml model published on the download mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CatalogModel {
+    /// File name, also the model's ID in commands (e.g. `ggml-base.en-q5_1.bin`).
+    pub name: &'static str,
+    /// Model size: tiny, base, small, medium, large-v3 or turbo.
+    pub family: &'static str,
+    /// Trained on English only; better at English, cannot transcribe other languages.
+    pub english_only: bool,
+    /// Quantization (`q5_0`, `q5_1`, `q8_0`), or None for full f16 weights.
+    pub quantization: Option<&'static str>,
+    /// Download size in MiB.
+    pub size_mb: u32,
+    /// SHA-256 of the file in the upstream whisper.cpp repository (lowercase hex), which
+    /// downloads are checked against whatever mirror serves them. None until recorded;
+    /// such a model downloads unverified, from another mirror only when allowed.
+    pub sha256: Option<&'static str>,
+}
+
+const fn model(
+    name: &'static str,
+    family: &'static str,
+    english_only: bool,
+    quantization: Option<&'static str>,
+    size_mb: u32,
+) -> CatalogModel {
+    CatalogModel {
+        name,
+        family,
+        english_only,
+        quantization,
+        size_mb,
+        sha256: None,
+    }
+}
+
+/// Downloadable whisper models, smallest first.
+pub const CATALOG: &[CatalogModel] = &[
+    model("ggml-tiny.bin", "tiny", false, None, 75),
+    model("ggml-tiny.en.bin", "tiny", true, None, 75),
+    model("ggml-tiny-q5_1.bin", "tiny", false, Some("q5_1"), 31),
+    model("ggml-tiny.en-q5_1.bin", "tiny", true, Some("q5_1"), 31),
+    model("ggml-tiny-q8_0.bin", "tiny", false, Some("q8_0"), 42),
+    model("ggml-base.bin", "base", false, None, 142),
+    model("ggml-base.en.bin", "base", true, None, 142),
+    model("ggml-base-q5_1.bin", "base", false, Some("q5_1"), 57),
+    model("ggml-base.en-q5_1.bin", "base", true, Some("q5_1"), 57),
+    model("ggml-base-q8_0.bin", "base", false, Some("q8_0"), 78),
+    model("ggml-small.bin", "small", false, None, 466),
+    model("ggml-small.en.bin", "small", true, None, 466),
+    model("ggml-small-q5_1.bin", "small", false, Some("q5_1"), 181),
+    model("ggml-small.en-q5_1.bin", "small", true, Some("q5_1"), 181),
+    model("ggml-small-q8_0.bin", "small", false, Some("q8_0"), 252),
+    model("ggml-medium.bin", "medium", false, None, 1533),
+    model("ggml-medium.en.bin", "medium", true, None, 1533),
+    model("ggml-medium-q5_0.bin", "medium", false, Some("q5_0"), 514),
+    model("ggml-medium.en-q5_0.bin", "medium", true, Some("q5_0"), 514),
+    model("ggml-medium-q8_0.bin", "medium", false, Some("q8_0"), 785),
+    model("ggml-large-v3.bin", "large-v3", false, None, 3095),
+    model("ggml-large-v3-q5_0.bin", "large-v3", false, Some("q5_0"), 1081),
+    model("ggml-large-v3-turbo.bin", "turbo", false, None, 1624),
+    model("ggml-large-v3-turbo-q5_0.bin", "turbo", false, Some("q5_0"), 547),
+    model("ggml-large-v3-turbo-q8_0.bin", "turbo", false, Some("q8_0"), 834),
+];
+
+/// Catalog entry for a model file name.
+pub fn find(name: &str) -> Option<&'static CatalogModel> {
+    CATALOG.iter().find(|m| m.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_every_family_with_variants() {
+        for family in ["tiny", "base", "small", "medium", "large-v3", "turbo"] {
+            let models: Vec<_> = CATALOG.iter().filter(|m| m.family == family).collect();
+            assert!(models.iter().any(|m| m.quantization.is_none()), "{}", family);
+            assert!(models.iter().any(|m| m.quantization.is_some()), "{}", family);
+        }
+        assert!(CATALOG.iter().any(|m| m.english_only));
+        // Quantized variants are smaller than their f16 model
+        let base = find("ggml-base.bin").unwrap();
+        assert!(find("ggml-base-q5_1.bin").unwrap().size_mb < base.size_mb);
+        assert!(find("ggml-unknown.bin").is_none());
+    }
+
+    #[test]
+    fn digests_are_sha256_hex() {
+        for m in CATALOG {
+            if let Some(digest) = m.sha256 {
+                assert_eq!(digest.len(), 64, "{}", m.name);
+                assert!(
+                    digest.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)),
+                    "{}",
+                    m.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn names_are_unique_ggml_files() {
+        for (i, m) in CATALOG.iter().enumerate() {
+            assert!(m.name.starts_with("ggml-") && m.name.ends_with(".bin"));
+            assert!(m.english_only == m.name.contains(".en"), "{}", m.name);
+            assert!(CATALOG[i + 1..].iter().all(|o| o.name != m.name));
+        }
+    }
+}
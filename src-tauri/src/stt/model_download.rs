@@ -0,0 +1,405 @@
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Redirects followed before giving up (HF sends one, to its CDN).
+const MAX_REDIRECTS: usize = 5;
+/// Progress is reported every this many bytes.
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+/// Snapshot of a download in progress.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DownloadStatus {
+    pub id: String,
+    pub model: String,
+    pub downloaded: u64,
+    /// Full file size, or 0 while unknown.
+    pub total: u64,
+}
+
+/// One model download: progress and a cancel flag shared with the commands.
+pub struct Download {
+    pub id: String,
+    pub model: String,
+    cancelled: AtomicBool,
+    downloaded: AtomicU64,
+    total: AtomicU64,
+}
+
+impl Download {
+    fn new(model: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            model: model.to_string(),
+            cancelled: AtomicBool::new(false),
+            downloaded: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> DownloadStatus {
+        DownloadStatus {
+            id: self.id.clone(),
+            model: self.model.clone(),
+            downloaded: self.downloaded.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Downloads in progress, by ID. One download per model at a time, since they
+/// share the model's temp file.
+#[derive(Default)]
+pub struct Downloads {
+    active: Mutex<HashMap<String, Arc<Download>>>,
+}
+
+impl Downloads {
+    /// Register a new download of `model`.
+    pub fn start(&self, model: &str) -> Result<Arc<Download>> {
+        let mut active = self.active.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        if active.values().any(|d| d.model == model) {
+            anyhow::bail!("{} is already downloading", model);
+        }
+        let download = Arc::new(Download::new(model));
+        active.insert(download.id.clone(), download.clone());
+        Ok(download)
+    }
+
+    pub fn finish(&self, id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(id);
+        }
+    }
+
+    /// Ask a download to stop; false if no download has that ID.
+    pub fn cancel(&self, id: &str) -> bool {
+        let active = match self.active.lock() {
+            Ok(active) => active,
+            Err(_) => return false,
+        };
+        active.get(id).map(|d| d.cancel()).is_some()
+    }
+
+    pub fn list(&self) -> Vec<DownloadStatus> {
+        self.active
+            .lock()
+            .map(|active| active.values().map(|d| d.status()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Download `url` to `dest` through `<dest>.tmp`, resuming a previous partial
+/// download with an HTTP Range request, and check the file against `sha256`, the
+/// digest the catalog records for it (None only when verification is turned off).
+/// A cancelled or broken download keeps its temp file to resume from; one that
+/// fails verification is deleted, so a resume cannot keep a bad prefix.
+pub async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    sha256: Option<&str>,
+    download: &Download,
+    mut on_progress: impl FnMut(DownloadStatus),
+) -> Result<()> {
+    let tmp = dest.with_extension("bin.tmp");
+    let partial = tmp.metadata().map(|m| m.len()).unwrap_or(0);
+
+    let mut offset = partial;
+    let response = loop {
+        let response = get(client, url, offset).await?;
+        if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // Temp file is complete or overlong: start over
+            offset = 0;
+            continue;
+        }
+        let response = response
+            .error_for_status()
+            .context("Download returned error status")?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+        break response;
+    };
+    if offset > 0 {
+        tracing::info!("Resuming {} at {} bytes", download.model, offset);
+    }
+
+    let total = response.content_length().map_or(0, |len| offset + len);
+    download.total.store(total, Ordering::Relaxed);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(offset > 0)
+        .write(true)
+        .truncate(offset == 0)
+        .open(&tmp)
+        .context("Failed to create temp file")?;
+    let mut downloaded = offset;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        if download.cancelled.load(Ordering::SeqCst) {
+            anyhow::bail!("Download cancelled");
+        }
+        let chunk = chunk.context("Download stream error")?;
+        file.write_all(&chunk).context("Failed to write chunk")?;
+        downloaded += chunk.len() as u64;
+        download.downloaded.store(downloaded, Ordering::Relaxed);
+        if downloaded % PROGRESS_STEP < chunk.len() as u64 {
+            on_progress(download.status());
+        }
+    }
+    file.flush().context("Failed to write chunk")?;
+    drop(file);
+    if total > 0 && downloaded != total {
+        anyhow::bail!("Download ended early at {} of {} bytes", downloaded, total);
+    }
+
+    match sha256 {
+        Some(expected) => {
+            let path = tmp.clone();
+            let actual = tokio::task::spawn_blocking(move || sha256_file(&path))
+                .await
+                .context("Checksum task failed")??;
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = std::fs::remove_file(&tmp);
+                anyhow::bail!("Checksum mismatch: expected {}, got {}", expected, actual);
+            }
+        }
+        None => tracing::warn!("{} downloaded without verification", download.model),
+    }
+
+    std::fs::rename(&tmp, dest).context("Failed to finalize model file")?;
+    on_progress(download.status());
+    Ok(())
+}
+
+/// GET `url` from byte `offset`.
+async fn get(client: &reqwest::Client, url: &str, offset: u64) -> Result<reqwest::Response> {
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+    request.send().await.context("Failed to start download")
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).context("Failed to open download")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Failed to read download")?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Client for model downloads.
+pub fn client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+
+    /// Local stand-in for the mirror: `/model` redirects to `/cdn/model`, naming
+    /// `digest` in `X-Linked-Etag` as HF does, and `/cdn/model` serves `body` with
+    /// Range support. The first `cut` responses stop halfway. Returns the base URL
+    /// and the Range header of each request.
+    fn mirror(
+        body: Vec<u8>,
+        digest: Option<String>,
+        cut: usize,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let log = ranges.clone();
+        std::thread::spawn(move || {
+            let mut served = 0;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut lines = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                    lines.push(line.trim().to_ascii_lowercase());
+                }
+                if lines.first().is_some_and(|l| l.starts_with("get /model ")) {
+                    let etag = digest
+                        .as_ref()
+                        .map(|d| format!("X-Linked-Etag: \"{}\"\r\n", d))
+                        .unwrap_or_default();
+                    let head = format!(
+                        "HTTP/1.1 302 Found\r\nLocation: /cdn/model\r\n{}\
+                         Content-Length: 0\r\nConnection: close\r\n\r\n",
+                        etag
+                    );
+                    let _ = stream.write_all(head.as_bytes());
+                    continue;
+                }
+                let range = lines.iter().find_map(|l| l.strip_prefix("range: bytes="));
+                log.lock().unwrap().push(range.unwrap_or("").to_string());
+                let from: usize = range
+                    .and_then(|r| r.trim_end_matches('-').parse().ok())
+                    .unwrap_or(0);
+                let status = if from > 0 {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                let rest = &body[from..];
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    rest.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let send = if served < cut {
+                    rest.len() / 2
+                } else {
+                    rest.len()
+                };
+                let _ = stream.write_all(&rest[..send]);
+                served += 1;
+            }
+        });
+        (base, ranges)
+    }
+
+    fn model_bytes() -> Vec<u8> {
+        (0..3_000_000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    fn temp_dest() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rt-download-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("ggml-test.bin")
+    }
+
+    #[tokio::test]
+    async fn resumes_interrupted_download_and_verifies_it() {
+        // The mirror names no digest; the catalog's is what counts
+        let body = model_bytes();
+        let digest = sha256(&body);
+        let (base, ranges) = mirror(body.clone(), None, 1);
+        let dest = temp_dest();
+        let url = format!("{}/model", base);
+        let client = client().unwrap();
+        let downloads = Downloads::default();
+        let download = downloads.start("ggml-test.bin").unwrap();
+        assert!(downloads.start("ggml-test.bin").is_err());
+
+        let err = fetch(&client, &url, &dest, Some(&digest), &download, |_| {})
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("stream error"), "{:#}", err);
+        let partial = dest.with_extension("bin.tmp").metadata().unwrap().len();
+        assert_eq!(partial, 1_500_000);
+
+        let mut last = None;
+        fetch(&client, &url, &dest, Some(&digest), &download, |s| {
+            last = Some(s)
+        })
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert_eq!(*ranges.lock().unwrap(), vec!["", "1500000-"]);
+        assert_eq!(
+            last.map(|s| (s.downloaded, s.total)),
+            Some((3_000_000, 3_000_000))
+        );
+
+        assert_eq!(downloads.list().len(), 1);
+        downloads.finish(&download.id);
+        assert!(downloads.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn deletes_download_that_fails_verification() {
+        // A mirror serving another file vouches for it; the catalog digest does not
+        let body = model_bytes();
+        let tampered = body[..1_000_000].to_vec();
+        let (base, _) = mirror(tampered.clone(), Some(sha256(&tampered)), 0);
+        let dest = temp_dest();
+        let download = Download::new("ggml-test.bin");
+
+        let url = format!("{}/model", base);
+        let expected = sha256(&body);
+        let err = fetch(
+            &client().unwrap(),
+            &url,
+            &dest,
+            Some(&expected),
+            &download,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+        assert!(!dest.exists());
+        assert!(!dest.with_extension("bin.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn cancelled_download_keeps_partial_file() {
+        let body = model_bytes();
+        let digest = sha256(&body);
+        let (base, _) = mirror(body, Some(digest.clone()), 0);
+        let dest = temp_dest();
+        let downloads = Downloads::default();
+        let download = downloads.start("ggml-test.bin").unwrap();
+        assert!(downloads.cancel(&download.id));
+        assert!(!downloads.cancel("no-such-download"));
+
+        let url = format!("{}/model", base);
+        let err = fetch(
+            &client().unwrap(),
+            &url,
+            &dest,
+            Some(&digest),
+            &download,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Download cancelled");
+        assert!(!dest.exists());
+        assert!(dest.with_extension("bin.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn unverified_download_keeps_whatever_the_mirror_serves() {
+        let body = model_bytes();
+        let (base, _) = mirror(body.clone(), None, 0);
+        let dest = temp_dest();
+        let download = Download::new("ggml-test.bin");
+
+        let url = format!("{}/model", base);
+        fetch(&client().unwrap(), &url, &dest, None, &download, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+    }
+}
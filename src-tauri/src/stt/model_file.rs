@@ -0,0 +1,234 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// whisper.cpp's ggml file magic ("ggml" as a little-endian u32).
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// Audio encoder context of every whisper model (30s of 10ms frames, halved).
+const WHISPER_AUDIO_CTX: i32 = 1500;
+/// Text decoder context of every whisper model.
+const WHISPER_TEXT_CTX: i32 = 448;
+/// Longest GGUF key or string value read while looking for the architecture.
+const MAX_GGUF_STRING: u64 = 1 << 20;
+
+/// On-disk format of a whisper model file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModelFormat {
+    Ggml,
+    Gguf,
+}
+
+/// Check that `path` is a whisper model: a whisper.cpp ggml file with whisper's
+/// hyperparameters, or a GGUF file whose architecture is whisper.
+pub fn check(path: &Path) -> Result<ModelFormat> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 4];
+    reader
+        .read_exact(&mut magic)
+        .context("File is too short to be a model")?;
+
+    if u32::from_le_bytes(magic) == GGML_MAGIC {
+        check_ggml(&mut reader)?;
+        Ok(ModelFormat::Ggml)
+    } else if &magic == GGUF_MAGIC {
+        let len = reader.get_ref().metadata()?.len();
+        check_gguf(&mut reader, len)?;
+        Ok(ModelFormat::Gguf)
+    } else {
+        anyhow::bail!("Not a ggml or gguf model file")
+    }
+}
+
+/// ggml hyperparameters follow the magic: n_vocab, n_audio_ctx, n_audio_state,
+/// n_audio_head, n_audio_layer, n_text_ctx, n_text_state, n_text_head,
+/// n_text_layer, n_mels, ftype.
+fn check_ggml(reader: &mut impl Read) -> Result<()> {
+    let mut hparams = [0i32; 11];
+    for value in hparams.iter_mut() {
+        *value = read_i32(reader).context("Truncated ggml header")?;
+    }
+    let [_, audio_ctx, .., text_ctx, _, _, _, n_mels, _] = hparams;
+    if audio_ctx != WHISPER_AUDIO_CTX || text_ctx != WHISPER_TEXT_CTX || !matches!(n_mels, 80 | 128)
+    {
+        anyhow::bail!("ggml file is not a whisper model");
+    }
+    Ok(())
+}
+
+/// Looks for `general.architecture` among the GGUF metadata of a `len`-byte file.
+fn check_gguf(reader: &mut (impl Read + Seek), len: u64) -> Result<()> {
+    let version = read_u32(reader)?;
+    if !(2..=3).contains(&version) {
+        anyhow::bail!("Unsupported gguf version {}", version);
+    }
+    let _tensors = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+    for _ in 0..kv_count {
+        let key = read_string(reader)?;
+        let kind = read_u32(reader)?;
+        if key == "general.architecture" && kind == GGUF_STRING {
+            let arch = read_string(reader)?;
+            if arch != "whisper" {
+                anyhow::bail!("gguf model is {}, not whisper", arch);
+            }
+            return Ok(());
+        }
+        skip_value(reader, kind, len)?;
+    }
+    anyhow::bail!("gguf file does not name its architecture")
+}
+
+const GGUF_STRING: u32 = 8;
+const GGUF_ARRAY: u32 = 9;
+
+/// Seeking past the end of a file succeeds, so every skip is checked against `len`.
+fn skip_value(reader: &mut (impl Read + Seek), kind: u32, len: u64) -> Result<()> {
+    let size: u64 = match kind {
+        0 | 1 | 7 => 1,
+        2 | 3 => 2,
+        4..=6 => 4,
+        10..=12 => 8,
+        GGUF_STRING => read_u64(reader)?,
+        GGUF_ARRAY => {
+            let item = read_u32(reader)?;
+            let count = read_u64(reader)?;
+            // Items take at least a byte each, and arrays do not nest
+            let left = len.saturating_sub(reader.stream_position()?);
+            if item == GGUF_ARRAY || count > left {
+                anyhow::bail!("Invalid gguf metadata");
+            }
+            for _ in 0..count {
+                skip_value(reader, item, len)?;
+            }
+            0
+        }
+        _ => anyhow::bail!("Invalid gguf metadata type {}", kind),
+    };
+    let end = reader.stream_position()?.saturating_add(size);
+    if end > len {
+        anyhow::bail!("Truncated gguf metadata");
+    }
+    reader.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_GGUF_STRING {
+        anyhow::bail!("Invalid gguf metadata");
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut bytes)
+        .context("Truncated gguf metadata")?;
+    String::from_utf8(bytes).context("Invalid gguf metadata")
+}
+
+fn read_i32(reader: &mut impl Read) -> Result<i32> {
+    Ok(read_u32(reader)? as i32)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes).context("Truncated header")?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes).context("Truncated header")?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rt-model-file-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// ggml header of a whisper model with `n_mels` and `audio_ctx`.
+    fn ggml(n_mels: i32, audio_ctx: i32) -> Vec<u8> {
+        let hparams = [51865, audio_ctx, 512, 8, 6, 448, 512, 8, 6, n_mels, 1];
+        let mut bytes = GGML_MAGIC.to_le_bytes().to_vec();
+        bytes.extend(hparams.iter().flat_map(|v: &i32| v.to_le_bytes()));
+        bytes
+    }
+
+    fn gguf_string(bytes: &mut Vec<u8>, s: &str) {
+        bytes.extend((s.len() as u64).to_le_bytes());
+        bytes.extend(s.as_bytes());
+    }
+
+    /// GGUF header with a u32 array before `general.architecture`.
+    fn gguf(arch: &str) -> Vec<u8> {
+        let mut bytes = b"GGUF".to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(2u64.to_le_bytes());
+        gguf_string(&mut bytes, "whisper.mels");
+        bytes.extend(GGUF_ARRAY.to_le_bytes());
+        bytes.extend(4u32.to_le_bytes());
+        bytes.extend(2u64.to_le_bytes());
+        bytes.extend([80u32, 128].iter().flat_map(|v| v.to_le_bytes()));
+        gguf_string(&mut bytes, "general.architecture");
+        bytes.extend(GGUF_STRING.to_le_bytes());
+        gguf_string(&mut bytes, arch);
+        bytes
+    }
+
+    #[test]
+    fn accepts_whisper_ggml_and_gguf() {
+        let path = write_temp("ggml-custom.bin", &ggml(80, 1500));
+        assert_eq!(check(&path).unwrap(), ModelFormat::Ggml);
+        let path = write_temp("large-v3.bin", &ggml(128, 1500));
+        assert_eq!(check(&path).unwrap(), ModelFormat::Ggml);
+        let path = write_temp("whisper.gguf", &gguf("whisper"));
+        assert_eq!(check(&path).unwrap(), ModelFormat::Gguf);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let llama = write_temp("llama.gguf", &gguf("llama"));
+        assert!(check(&llama).unwrap_err().to_string().contains("llama"));
+        let other_ggml = write_temp("other.bin", &ggml(80, 2048));
+        assert!(check(&other_ggml).is_err());
+        let truncated = write_temp("short.bin", &ggml(80, 1500)[..20]);
+        assert!(check(&truncated).is_err());
+        let text = write_temp("notes.bin", b"not a model at all");
+        assert!(check(&text).is_err());
+    }
+
+    #[test]
+    fn rejects_gguf_metadata_past_the_end_of_the_file() {
+        let header = |kind: u32, value: &[u8]| {
+            let mut bytes = b"GGUF".to_vec();
+            bytes.extend(3u32.to_le_bytes());
+            bytes.extend(0u64.to_le_bytes());
+            bytes.extend(1u64.to_le_bytes());
+            gguf_string(&mut bytes, "tokenizer.ggml.tokens");
+            bytes.extend(kind.to_le_bytes());
+            bytes.extend(value);
+            bytes
+        };
+        // An array claiming u64::MAX items must fail without walking them
+        let mut array = 4u32.to_le_bytes().to_vec();
+        array.extend(u64::MAX.to_le_bytes());
+        let huge = write_temp("huge.gguf", &header(GGUF_ARRAY, &array));
+        assert!(check(&huge).is_err());
+
+        let mut string = 64u64.to_le_bytes().to_vec();
+        string.extend([0u8; 8]);
+        let cut = write_temp("cut.gguf", &header(GGUF_STRING, &string));
+        let err = check(&cut).unwrap_err().to_string();
+        assert!(err.contains("Truncated"), "{}", err);
+    }
+}
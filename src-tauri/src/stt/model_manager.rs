@@ -1,23 +1,129 @@
+use crate::stt::model_catalog::{self, CatalogModel, CATALOG};
+use crate::stt::model_download::{self, Download, Downloads};
+use crate::stt::model_file;
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use tauri::Emitter;
 
 /// Default whisper model for STT inference.
 pub const DEFAULT_MODEL: &str = "ggml-base.bin";
 
+/// File in the app's own models directory remembering the selected model.
+const SELECTED_FILE: &str = "selected-model";
+/// Environment variable overriding the models directory, e.g. a shared drive.
+const MODELS_DIR_ENV: &str = "RT_MODELS_DIR";
+/// Environment variable overriding the download mirror, e.g. an internal one.
+const MIRROR_URL_ENV: &str = "RT_MODEL_MIRROR_URL";
+/// Environment variable allowing downloads of models with no known SHA-256.
+const ALLOW_UNVERIFIED_ENV: &str = "RT_MODEL_ALLOW_UNVERIFIED";
+
+/// A whisper model the app knows of: from the catalog, or a file found in the
+/// models directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelEntry {
+    pub name: String,
+    /// Catalog model size (tiny ... turbo); None for files not in the catalog.
+    pub family: Option<String>,
+    pub english_only: bool,
+    pub quantization: Option<String>,
+    /// Download size for catalog models, else the file size.
+    pub size_mb: f64,
+    pub downloaded: bool,
+    pub selected: bool,
+}
+
 /// Hugging Face CDN base URL for whisper.cpp models.
 const HF_MODEL_URL: &str =
     "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
 
-/// Manages whisper model files (download, path resolution, status check).
+/// Manages whisper model files (download, import, path resolution, status check).
 pub struct ModelManager {
     models_dir: PathBuf,
+    /// Base URL catalog models are downloaded from (`{mirror_url}/{name}`).
+    mirror_url: String,
+    /// Download catalog models the catalog has no SHA-256 for, unverified.
+    allow_unverified: bool,
+    /// Kept in the app data dir even when the models live elsewhere, so a shared
+    /// models directory can be read-only.
+    selected_file: PathBuf,
+    /// Downloads in progress, cancellable by ID.
+    pub downloads: Downloads,
 }
 
 impl ModelManager {
     pub fn new(app_data_dir: PathBuf) -> Self {
         let models_dir = app_data_dir.join("models").join("whisper");
-        Self { models_dir }
+        Self {
+            selected_file: models_dir.join(SELECTED_FILE),
+            models_dir,
+            mirror_url: HF_MODEL_URL.to_string(),
+            allow_unverified: false,
+            downloads: Downloads::default(),
+        }
+    }
+
+    /// Models directory and mirror from `RT_MODELS_DIR` and `RT_MODEL_MIRROR_URL`
+    /// when set, else the app data dir and Hugging Face. `RT_MODEL_ALLOW_UNVERIFIED=1`
+    /// lets models without a recorded SHA-256 download from such a mirror.
+    pub fn from_env(app_data_dir: PathBuf) -> Self {
+        let mut manager = Self::new(app_data_dir);
+        if let Some(dir) = std::env::var_os(MODELS_DIR_ENV).filter(|d| !d.is_empty()) {
+            manager = manager.with_models_dir(PathBuf::from(dir));
+        }
+        if let Ok(url) = std::env::var(MIRROR_URL_ENV) {
+            manager = manager.with_mirror_url(&url);
+        }
+        if let Ok(allow) = std::env::var(ALLOW_UNVERIFIED_ENV) {
+            manager = manager.with_unverified_downloads(matches!(allow.trim(), "1" | "true"));
+        }
+        tracing::info!(
+            "Whisper models in {:?}, downloaded from {}",
+            manager.models_dir,
+            manager.mirror_url
+        );
+        manager
+    }
+
+    pub fn with_models_dir(mut self, models_dir: PathBuf) -> Self {
+        self.models_dir = models_dir;
+        self
+    }
+
+    /// Download from a mirror of the whisper.cpp model repository instead of
+    /// Hugging Face; an empty URL keeps the current one.
+    pub fn with_mirror_url(mut self, url: &str) -> Self {
+        let url = url.trim().trim_end_matches('/');
+        if !url.is_empty() {
+            self.mirror_url = url.to_string();
+        }
+        self
+    }
+
+    /// Download catalog models from a mirror even when the catalog records no
+    /// SHA-256 to check them against.
+    pub fn with_unverified_downloads(mut self, allow: bool) -> Self {
+        self.allow_unverified = allow;
+        self
+    }
+
+    /// SHA-256 a download of `model` must match. A model without a recorded digest
+    /// downloads unverified from the upstream repository itself, but from another
+    /// mirror only when unverified downloads are allowed.
+    fn expected_digest(&self, model: &CatalogModel) -> Result<Option<&'static str>> {
+        let upstream = self.mirror_url == HF_MODEL_URL;
+        if model.sha256.is_none() && !upstream && !self.allow_unverified {
+            anyhow::bail!(
+                "No SHA-256 is known for {} to check the mirror's copy; set {}=1 to download it unverified",
+                model.name,
+                ALLOW_UNVERIFIED_ENV
+            );
+        }
+        Ok(model.sha256)
+    }
+
+    pub fn models_dir(&self) -> &Path {
+        &self.models_dir
     }
 
     /// Full path for a model file.
@@ -39,71 +145,160 @@ impl ModelManager {
             .unwrap_or(0.0)
     }
 
-    /// Download model from Hugging Face CDN with progress events.
+    /// Catalog models, then other model files in the models directory, with
+    /// which are downloaded and which one is selected.
+    pub fn list_models(&self) -> Vec<ModelEntry> {
+        let selected = self.selected_model();
+        let mut entries: Vec<ModelEntry> = CATALOG
+            .iter()
+            .map(|m| ModelEntry {
+                name: m.name.to_string(),
+                family: Some(m.family.to_string()),
+                english_only: m.english_only,
+                quantization: m.quantization.map(str::to_string),
+                size_mb: m.size_mb as f64,
+                downloaded: self.is_model_available(m.name),
+                selected: m.name == selected,
+            })
+            .collect();
+
+        let mut local: Vec<String> = std::fs::read_dir(&self.models_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| is_model_file(n) && model_catalog::find(n).is_none())
+            .collect();
+        local.sort();
+        entries.extend(local.into_iter().map(|name| ModelEntry {
+            english_only: name.contains(".en"),
+            size_mb: self.model_size_mb(&name),
+            downloaded: self.is_model_available(&name),
+            selected: name == selected,
+            family: None,
+            quantization: None,
+            name,
+        }));
+        entries
+    }
+
+    /// Model used by meetings that do not pick one; `DEFAULT_MODEL` until selected.
+    pub fn selected_model(&self) -> String {
+        std::fs::read_to_string(&self.selected_file)
+            .map(|s| s.trim().to_string())
+            .ok()
+            .filter(|name| check_name(name).is_ok())
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string())
+    }
+
+    /// Make a downloaded model the default for meetings.
+    pub fn select_model(&self, model_name: &str) -> Result<()> {
+        check_name(model_name)?;
+        if !self.is_model_available(model_name) {
+            anyhow::bail!("Model not downloaded: {}", model_name);
+        }
+        if let Some(dir) = self.selected_file.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create models directory")?;
+        }
+        std::fs::write(&self.selected_file, model_name).context("Failed to save model selection")
+    }
+
+    /// Delete a model file. Deleting the selected model reverts to `DEFAULT_MODEL`.
+    pub fn delete_model(&self, model_name: &str) -> Result<()> {
+        check_name(model_name)?;
+        let path = self.model_path(model_name);
+        if !path.exists() {
+            anyhow::bail!("Model not downloaded: {}", model_name);
+        }
+        std::fs::remove_file(&path).context("Failed to delete model file")?;
+        if self.selected_model() == model_name {
+            let _ = std::fs::remove_file(&self.selected_file);
+        }
+        tracing::info!("Model deleted: {:?}", path);
+        Ok(())
+    }
+
+    /// Download a catalog model from the mirror (Hugging Face CDN by default), resuming
+    /// an earlier partial download, with progress events.
     pub async fn download_model(
         &self,
-        model_name: &str,
+        download: &Download,
         app: tauri::AppHandle,
     ) -> Result<PathBuf> {
-        use futures_util::StreamExt;
+        let model_name = download.model.as_str();
+        let model = model_catalog::find(model_name)
+            .with_context(|| format!("Unknown model: {}", model_name))?;
+        let sha256 = self.expected_digest(model)?;
 
         // Ensure directory exists
         std::fs::create_dir_all(&self.models_dir)
             .context("Failed to create models directory")?;
 
-        let url = format!("{}/{}", HF_MODEL_URL, model_name);
+        let url = format!("{}/{}", self.mirror_url, model_name);
         let dest = self.model_path(model_name);
-        let tmp = dest.with_extension("bin.tmp");
 
         tracing::info!("Downloading model from: {}", url);
 
-        let client = reqwest::Client::new();
-        let response = client
-            .get(&url)
-            .send()
-            .await
-            .context("Failed to start download")?
-            .error_for_status()
-            .context("Download returned error status")?;
-
-        let total = response.content_length().unwrap_or(0);
-        let mut stream = response.bytes_stream();
-        let mut file = std::fs::File::create(&tmp)
-            .context("Failed to create temp file")?;
-        let mut downloaded: u64 = 0;
-
-        use std::io::Write;
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.context("Download stream error")?;
-            file.write_all(&chunk)
-                .context("Failed to write chunk")?;
-            downloaded += chunk.len() as u64;
-
-            // Emit progress every ~1MB
-            if downloaded % (1024 * 1024) < chunk.len() as u64 {
-                let _ = app.emit(
-                    "model-download-progress",
-                    serde_json::json!({
-                        "model": model_name,
-                        "downloaded": downloaded,
-                        "total": total,
-                    }),
-                );
-            }
-        }
-
-        // Rename temp to final
-        std::fs::rename(&tmp, &dest).context("Failed to finalize model file")?;
-        tracing::info!("Model downloaded: {:?} ({:.1} MB)", dest, downloaded as f64 / 1_048_576.0);
+        let client = model_download::client()?;
+        model_download::fetch(&client, &url, &dest, sha256, download, |status| {
+            let _ = app.emit("model-download-progress", status);
+        })
+        .await?;
+        tracing::info!("Model downloaded: {:?}", dest);
 
         Ok(dest)
     }
 }
 
+/// Copy a local whisper model file (ggml or gguf), e.g. from a shared drive, into
+/// `models_dir` under its file name; returns that name. Blocks for the whole copy.
+pub fn import_model(models_dir: &Path, source: &Path) -> Result<String> {
+    let name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Invalid model file: {}", source.display()))?
+        .to_string();
+    check_name(&name)?;
+    model_file::check(source)?;
+
+    let dest = models_dir.join(&name);
+    if dest.exists() {
+        anyhow::bail!("A model named {} already exists", name);
+    }
+    std::fs::create_dir_all(models_dir).context("Failed to create models directory")?;
+    // Copy under a name list_models skips until the copy is complete
+    let tmp = models_dir.join(format!("{}.import", name));
+    std::fs::copy(source, &tmp).context("Failed to copy model file")?;
+    std::fs::rename(&tmp, &dest).context("Failed to finalize model file")?;
+
+    tracing::info!("Model imported: {:?} from {:?}", dest, source);
+    Ok(name)
+}
+
+/// Whisper model files: ggml `.bin` or `.gguf`, not partial downloads.
+fn is_model_file(name: &str) -> bool {
+    name.ends_with(".bin") || name.ends_with(".gguf")
+}
+
+/// Model names are plain file names inside the models directory.
+fn check_name(name: &str) -> Result<()> {
+    if name.contains(['/', '\\']) || name.starts_with('.') || !is_model_file(name) {
+        anyhow::bail!("Invalid model name: {}", name);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn temp_manager() -> ModelManager {
+        let dir = std::env::temp_dir().join(format!("rt-models-{}", uuid::Uuid::new_v4()));
+        let mgr = ModelManager::new(dir);
+        std::fs::create_dir_all(&mgr.models_dir).unwrap();
+        mgr
+    }
+
     #[test]
     fn model_path_resolution() {
         let mgr = ModelManager::new(PathBuf::from("/tmp/app"));
@@ -116,4 +311,105 @@ mod tests {
         let mgr = ModelManager::new(PathBuf::from("/tmp/nonexistent"));
         assert!(!mgr.is_model_available("ggml-base.bin"));
     }
+
+    #[test]
+    fn selects_and_deletes_downloaded_models() {
+        let mgr = temp_manager();
+        assert_eq!(mgr.selected_model(), DEFAULT_MODEL);
+        assert!(mgr.select_model("ggml-small.bin").is_err());
+
+        std::fs::write(mgr.model_path("ggml-small.bin"), b"ggml").unwrap();
+        mgr.select_model("ggml-small.bin").unwrap();
+        assert_eq!(mgr.selected_model(), "ggml-small.bin");
+
+        mgr.delete_model("ggml-small.bin").unwrap();
+        assert!(!mgr.is_model_available("ggml-small.bin"));
+        assert_eq!(mgr.selected_model(), DEFAULT_MODEL);
+        assert!(mgr.delete_model("ggml-small.bin").is_err());
+    }
+
+    #[test]
+    fn lists_catalog_then_local_files() {
+        let mgr = temp_manager();
+        std::fs::write(mgr.model_path("ggml-tiny.bin"), b"ggml").unwrap();
+        std::fs::write(mgr.model_path("custom-model.gguf"), b"gguf").unwrap();
+        std::fs::write(mgr.model_path("ggml-base.bin.tmp"), b"partial").unwrap();
+
+        let models = mgr.list_models();
+        assert_eq!(models.len(), CATALOG.len() + 1);
+        let tiny = models.iter().find(|m| m.name == "ggml-tiny.bin").unwrap();
+        assert!(tiny.downloaded && !tiny.selected);
+        assert_eq!(tiny.size_mb, 75.0);
+        let base = models.iter().find(|m| m.name == DEFAULT_MODEL).unwrap();
+        assert!(!base.downloaded && base.selected);
+        let custom = models.last().unwrap();
+        assert_eq!(custom.name, "custom-model.gguf");
+        assert_eq!(custom.family, None);
+    }
+
+    #[test]
+    fn rejects_paths_as_model_names() {
+        let mgr = temp_manager();
+        for name in ["../ggml-base.bin", "a/b.bin", "", ".bin", "notes.txt"] {
+            assert!(mgr.select_model(name).is_err(), "{}", name);
+            assert!(mgr.delete_model(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn imports_whisper_files_into_configured_dir() {
+        let app = temp_manager();
+        let shared = std::env::temp_dir().join(format!("rt-shared-{}", uuid::Uuid::new_v4()));
+        let mgr = ModelManager::new(app.models_dir.clone())
+            .with_models_dir(shared.clone())
+            .with_mirror_url("https://mirror.example/whisper/");
+        assert_eq!(mgr.models_dir(), shared);
+        assert_eq!(mgr.mirror_url, "https://mirror.example/whisper");
+
+        // ggml header of a whisper base model
+        let hparams: [i32; 11] = [51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 1];
+        let mut header = b"lmgg".to_vec();
+        header.extend(hparams.iter().flat_map(|v| v.to_le_bytes()));
+        let source = app.models_dir.join("whisper-base-custom.bin");
+        std::fs::write(&source, &header).unwrap();
+
+        let name = import_model(mgr.models_dir(), &source).unwrap();
+        assert_eq!(name, "whisper-base-custom.bin");
+        assert!(mgr.is_model_available(&name));
+        let err = import_model(mgr.models_dir(), &source)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("exists"), "{}", err);
+        mgr.select_model(&name).unwrap();
+        assert_eq!(mgr.selected_model(), name);
+
+        let bogus = app.models_dir.join("bogus.bin");
+        std::fs::write(&bogus, b"not a whisper model").unwrap();
+        assert!(import_model(mgr.models_dir(), &bogus).is_err());
+        assert!(!mgr.model_path("bogus.bin").exists());
+    }
+
+    #[test]
+    fn mirror_downloads_need_a_digest_unless_unverified_ones_are_allowed() {
+        let base = *model_catalog::find("ggml-base.bin").unwrap();
+        let known = CatalogModel {
+            sha256: Some("ab".repeat(32).leak()),
+            ..base
+        };
+        let unknown = CatalogModel {
+            sha256: None,
+            ..base
+        };
+        let upstream = temp_manager();
+        assert_eq!(upstream.expected_digest(&known).unwrap(), known.sha256);
+        assert_eq!(upstream.expected_digest(&unknown).unwrap(), None);
+
+        let mirror = temp_manager().with_mirror_url("https://mirror.example/whisper");
+        assert_eq!(mirror.expected_digest(&known).unwrap(), known.sha256);
+        let err = mirror.expected_digest(&unknown).unwrap_err();
+        assert!(err.to_string().contains(ALLOW_UNVERIFIED_ENV), "{}", err);
+
+        let mirror = mirror.with_unverified_downloads(true);
+        assert_eq!(mirror.expected_digest(&unknown).unwrap(), None);
+    }
 }
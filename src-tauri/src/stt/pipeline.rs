@@ -1,58 +1,88 @@
-use crate::audio::resampler::AudioResampler;
-use crate::audio::vad::{EnergyVad, SpeechBuffer, VadConfig, VadEvent};
+use crate::audio::dsp::DspConfig;
+use crate::audio::recorder::WavRecorder;
+use crate::audio::resampler::ResamplerKind;
+use crate::audio::utterance::Utterance;
+use crate::audio::vad::VadConfig;
 use crate::notes::{SegmentBuffer, TranscriptSegment};
 use crate::storage::TranscriptDb;
+use crate::stt::diarization::{DiarizationConfig, Diarizer, SpeakerNames};
+use crate::stt::lane::{speaker_label, take_recorded, Lane, LaneInput};
+use crate::stt::streaming::{StreamingConfig, UtteranceStream};
 use crate::stt::whisper::SttEngine;
-use crossbeam::channel::Receiver;
+use crossbeam::channel::RecvTimeoutError;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Instant;
 use tauri::Emitter;
 
 /// Orchestrates VAD → STT → event emission pipeline.
-/// Runs on a dedicated thread, receives f32 audio from AudioCaptureManager.
+/// Runs on a dedicated thread, receives f32 audio per source from AudioCaptureManager.
 pub struct SttPipeline {
     is_running: Arc<AtomicBool>,
     thread_handle: Option<JoinHandle<()>>,
 }
 
-/// Audio format info for resampling raw mic input to 16kHz mono.
-pub struct MicFormat {
-    pub sample_rate: u32,
-    pub channels: u16,
+/// Tunables for a meeting's STT pipeline, passed from `start_meeting`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Pre-processing between the resampler and the VAD.
+    pub dsp: DspConfig,
+    /// Detector backend, speech thresholds and utterance padding (pre-roll / hangover).
+    pub vad: VadConfig,
+    /// Resampling to 16kHz: `fft` (default) or low-latency `sinc` for live captions.
+    pub resampler: ResamplerKind,
+    /// Label segments by voice ("Speaker 1..N") instead of by source.
+    pub diarization: DiarizationConfig,
+    /// Non-final captions re-decoded from the utterance in progress.
+    pub streaming: StreamingConfig,
+}
+
+/// Destinations for pipeline output besides `stt-partial` events.
+pub struct PipelineSinks {
+    pub transcript_db: TranscriptDb,
+    pub meeting_id: Arc<Mutex<Option<i64>>>,
+    pub segment_buffer: SegmentBuffer,
+    /// Records the lanes summed at 16kHz mono; transcript sample offsets index this file.
+    pub recorder: Option<WavRecorder>,
+    /// Renamed speakers, applied to segments as they are transcribed.
+    pub speaker_names: SpeakerNames,
 }
 
 impl SttPipeline {
     /// Start the pipeline on a dedicated thread.
-    /// `audio_rx`: receives f32 audio chunks from the audio processor thread.
+    /// `lanes`: one per capture source, mic first; each gets its own VAD and
+    /// utterance buffer, and its segments are tagged with the source as speaker.
     /// `engine`: shared whisper-rs STT engine.
     /// `app`: Tauri AppHandle for emitting events.
-    /// `mic_format`: mic sample rate + channels for resampling to 16kHz mono.
+    /// `config`: DSP, VAD and resampler tunables.
+    /// `sinks`: transcript DB, note segment buffer and optional meeting recorder.
+    /// `diarizer`: when set, speakers are told apart by voice across all lanes.
     pub fn start(
-        audio_rx: Receiver<Vec<f32>>,
+        lanes: Vec<LaneInput>,
         engine: Arc<SttEngine>,
         app: tauri::AppHandle,
-        mic_format: MicFormat,
-        transcript_db: TranscriptDb,
-        meeting_id: Arc<Mutex<Option<i64>>>,
-        segment_buffer: SegmentBuffer,
+        config: PipelineConfig,
+        mut sinks: PipelineSinks,
+        diarizer: Option<Diarizer>,
     ) -> Self {
         let is_running = Arc::new(AtomicBool::new(true));
         let flag = is_running.clone();
+        let recorder = sinks.recorder.take();
+        let transcriber = Transcriber {
+            engine,
+            app,
+            sinks,
+            diarizer,
+            segment_counter: 0,
+        };
 
         let handle = std::thread::Builder::new()
             .name("stt-pipeline".to_string())
             .spawn(move || {
-                pipeline_loop(
-                    audio_rx,
-                    engine,
-                    app,
-                    flag,
-                    mic_format,
-                    transcript_db,
-                    meeting_id,
-                    segment_buffer,
-                );
+                pipeline_loop(lanes, transcriber, recorder, flag, config);
             })
             .expect("Failed to spawn stt-pipeline thread");
 
@@ -82,233 +112,330 @@ impl Default for SttPipeline {
     }
 }
 
-/// Main pipeline loop: resample → VAD frames → accumulate speech → STT on silence → emit events.
+/// Main pipeline loop: per lane, resample → DSP → VAD frames → accumulate speech →
+/// STT on silence → emit events. Lanes share the engine and run on this thread.
 fn pipeline_loop(
-    audio_rx: Receiver<Vec<f32>>,
-    engine: Arc<SttEngine>,
-    app: tauri::AppHandle,
+    inputs: Vec<LaneInput>,
+    mut transcriber: Transcriber,
+    mut recorder: Option<WavRecorder>,
     is_running: Arc<AtomicBool>,
-    mic_format: MicFormat,
-    transcript_db: TranscriptDb,
-    meeting_id: Arc<Mutex<Option<i64>>>,
-    segment_buffer: SegmentBuffer,
+    config: PipelineConfig,
 ) {
-    let mut vad = EnergyVad::new(VadConfig::default());
-    let mut buffer = SpeechBuffer::new(16000, 30);
-    let mut segment_counter: u32 = 0;
-    let start_time = std::time::Instant::now();
-
-    // Setup resampler if mic isn't already 16kHz mono
-    let needs_resample = mic_format.sample_rate != 16000 || mic_format.channels != 1;
-    let chunk_size = 1024_usize;
-    let mut resampler = if needs_resample {
-        match AudioResampler::new(
-            mic_format.sample_rate,
-            16000,
-            mic_format.channels as usize,
-            chunk_size,
-        ) {
-            Ok(r) => {
-                tracing::info!(
-                    "STT resampler: {}Hz {}ch -> 16kHz mono",
-                    mic_format.sample_rate,
-                    mic_format.channels,
-                );
-                Some(r)
-            }
-            Err(e) => {
-                tracing::error!("Failed to create resampler: {}", e);
-                return;
-            }
+    let lanes: Result<Vec<Lane>, _> = inputs
+        .into_iter()
+        .map(|input| Lane::new(input, &config.dsp, &config.vad, config.resampler))
+        .collect();
+    let mut lanes = match lanes {
+        Ok(lanes) if !lanes.is_empty() => lanes,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::error!("Failed to create resampler: {}", e);
+            return;
         }
-    } else {
-        tracing::info!("Mic already 16kHz mono, no resampling needed");
-        None
     };
+    let mut streams: Vec<UtteranceStream> = lanes
+        .iter()
+        .map(|_| UtteranceStream::new(&config.streaming))
+        .collect();
+    let mut utterances = Vec::new();
+    let mut mix = Vec::new();
 
-    // Resampler input buffer: accumulate enough frames for a full chunk
-    let input_chunk_samples = chunk_size * mic_format.channels as usize;
-    let mut resample_buf: Vec<f32> = Vec::with_capacity(input_chunk_samples * 2);
-
-    // Process audio in 10ms frames (160 samples at 16kHz)
-    const FRAME_SIZE: usize = 160;
-    let mut frame_buf: Vec<f32> = Vec::with_capacity(FRAME_SIZE * 4);
-
-    while is_running.load(Ordering::SeqCst) {
-        match audio_rx.recv_timeout(std::time::Duration::from_millis(50)) {
-            Ok(samples) => {
-                // Resample to 16kHz mono if needed, otherwise pass through
-                let mono_16k = if let Some(ref mut rs) = resampler {
-                    resample_buf.extend_from_slice(&samples);
-                    let mut out = Vec::new();
-                    while resample_buf.len() >= input_chunk_samples {
-                        let chunk: Vec<f32> =
-                            resample_buf.drain(..input_chunk_samples).collect();
-                        let resampled = if mic_format.channels >= 2 {
-                            rs.process_stereo_to_mono(&chunk)
-                        } else {
-                            rs.process_mono(&chunk)
-                        };
-                        match resampled {
-                            Ok(data) => out.extend(data),
-                            Err(e) => {
-                                tracing::warn!("Resample error: {}", e);
-                                continue;
-                            }
-                        }
-                    }
-                    out
-                } else {
-                    samples
-                };
-
-                frame_buf.extend_from_slice(&mono_16k);
-
-                // Process complete frames
-                while frame_buf.len() >= FRAME_SIZE {
-                    let frame: Vec<f32> =
-                        frame_buf.drain(..FRAME_SIZE).collect();
-
-                    let event = vad.process_frame(&frame);
-                    match event {
-                        VadEvent::Speech => {
-                            buffer.push(&frame);
-                        }
-                        VadEvent::Silence => {
-                            // Keep padding a bit of silence for natural trailing
-                        }
-                        VadEvent::SpeechEnd => {
-                            if let Some(audio) = buffer.take() {
-                                let base_ms = start_time.elapsed().as_millis() as u64;
-                                run_stt_and_emit(
-                                    &engine,
-                                    &app,
-                                    &audio,
-                                    base_ms,
-                                    &mut segment_counter,
-                                    &transcript_db,
-                                    &meeting_id,
-                                    &segment_buffer,
-                                );
-                            }
-                        }
-                    }
+    loop {
+        let mut last = false;
+        if is_running.load(Ordering::SeqCst) {
+            // The first lane paces the loop; the others are forwarded in step with it
+            match lanes[0].receive(std::time::Duration::from_millis(50)) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    tracing::info!("STT audio channel disconnected");
+                    last = true;
+                }
+            }
+            for lane in lanes.iter_mut().skip(1) {
+                lane.take_available();
+            }
+        } else {
+            // Stopping: take what is still buffered before flushing
+            for lane in lanes.iter_mut() {
+                lane.take_available();
+            }
+            last = true;
+        }
 
-                    // Safety cap: force STT if buffer too long
-                    if buffer.is_full() {
-                        tracing::warn!("Speech buffer at max cap, forcing STT");
-                        if let Some(audio) = buffer.take() {
-                            let base_ms = start_time.elapsed().as_millis() as u64;
-                            run_stt_and_emit(
-                                &engine,
-                                &app,
-                                &audio,
-                                base_ms,
-                                &mut segment_counter,
-                                &transcript_db,
-                                &meeting_id,
-                                &segment_buffer,
-                            );
-                        }
-                        vad.reset();
+        for (lane, stream) in lanes.iter_mut().zip(&mut streams) {
+            if last {
+                lane.finish_input();
+            }
+            lane.process(&mut utterances);
+            let speaker = speaker_label(lane.role);
+            for utterance in utterances.drain(..) {
+                stream.reset();
+                transcriber.run(speaker, &utterance);
+            }
+            if config.streaming.enabled && !last {
+                if let Some((audio, start)) = lane.pending() {
+                    if stream.due(start, audio.len()) {
+                        transcriber.partial(speaker, audio, start, stream);
                     }
                 }
             }
-            Err(crossbeam::channel::RecvTimeoutError::Timeout) => continue,
-            Err(crossbeam::channel::RecvTimeoutError::Disconnected) => {
-                tracing::info!("STT audio channel disconnected");
-                break;
-            }
+        }
+        take_recorded(&mut lanes, last, &mut mix);
+        record_frame(&mut recorder, &mix);
+        if last {
+            break;
         }
     }
 
     // Process any remaining buffer
-    if let Some(audio) = buffer.take() {
-        let base_ms = start_time.elapsed().as_millis() as u64;
-        run_stt_and_emit(
-            &engine,
-            &app,
-            &audio,
-            base_ms,
-            &mut segment_counter,
-            &transcript_db,
-            &meeting_id,
-            &segment_buffer,
-        );
+    for lane in lanes.iter_mut() {
+        if let Some(utterance) = lane.flush() {
+            transcriber.run(speaker_label(lane.role), &utterance);
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.finish() {
+            tracing::error!("{}", e);
+        }
     }
 
     tracing::info!("STT pipeline loop exiting");
 }
 
-/// Run whisper inference, emit results as Tauri events, and insert into DB.
-fn run_stt_and_emit(
-    engine: &SttEngine,
-    app: &tauri::AppHandle,
-    audio: &[f32],
-    base_time_ms: u64,
-    segment_counter: &mut u32,
-    transcript_db: &TranscriptDb,
-    meeting_id: &Arc<Mutex<Option<i64>>>,
-    segment_buffer: &SegmentBuffer,
-) {
-    if audio.is_empty() {
+/// Append 16kHz audio to the meeting recording, dropping the recorder on I/O errors.
+fn record_frame(recorder: &mut Option<WavRecorder>, frame: &[f32]) {
+    if frame.is_empty() {
         return;
     }
+    if let Some(rec) = recorder {
+        if let Err(e) = rec.write(frame) {
+            tracing::error!("Meeting recording failed, stopping it: {}", e);
+            *recorder = None;
+        }
+    }
+}
 
-    tracing::debug!(
-        "Running STT on {} samples ({:.1}s)",
-        audio.len(),
-        audio.len() as f64 / 16000.0,
-    );
-
-    match engine.transcribe_sync(audio, base_time_ms) {
-        Ok(segments) => {
-            for seg in segments {
-                *segment_counter += 1;
-                // Build canonical segment_id — single source of truth
-                let seg_id = format!("seg-{}-{}", *segment_counter, seg.start_ms);
-
-                let payload = serde_json::json!({
-                    "text": seg.text,
-                    "language": seg.lang,
-                    "start_ms": seg.start_ms,
-                    "end_ms": seg.end_ms,
-                    "is_final": seg.is_final,
-                    "segment_id": seg_id,
-                });
-
-                if let Err(e) = app.emit("stt-partial", payload) {
-                    tracing::warn!("Failed to emit stt-partial: {}", e);
-                }
+/// Map a whisper segment (ms within the utterance) to 16kHz sample offsets in the
+/// stream, clamped to the utterance span `[start, end)`. Utterances are contiguous
+/// audio, so offsets map directly; the last segment extends to `end` to cover the hangover.
+fn segment_samples(span: (u64, u64), seg_ms: (u64, u64), is_last: bool) -> (u64, u64) {
+    let (start, end) = span;
+    let seg_start = (start + seg_ms.0 * 16).min(end);
+    let seg_end = if is_last {
+        end
+    } else {
+        (start + seg_ms.1 * 16).clamp(seg_start, end)
+    };
+    (seg_start, seg_end)
+}
 
-                // Insert final segments into DB and feed to NoteEngine
-                if seg.is_final {
-                    if let Ok(guard) = meeting_id.lock() {
-                        if let Some(mid) = *guard {
-                            if let Err(e) = transcript_db.insert_transcript(
-                                mid,
-                                &seg.text,
-                                &seg_id,
-                                seg.start_ms as i64,
-                            ) {
-                                tracing::error!("Failed to insert transcript: {}", e);
-                            }
+/// Whisper inference and everything downstream of it: speaker labels,
+/// `stt-partial` events, transcript rows and note segments.
+struct Transcriber {
+    engine: Arc<SttEngine>,
+    app: tauri::AppHandle,
+    sinks: PipelineSinks,
+    diarizer: Option<Diarizer>,
+    segment_counter: u32,
+}
+
+impl Transcriber {
+    /// Run whisper inference, emit results as Tauri events, and insert into DB.
+    /// Sample offsets are relative to the 16kHz stream the utterance was cut from.
+    /// `lane` labels the source the utterance came from ("Me" / "Remote").
+    fn run(&mut self, lane: &str, utterance: &Utterance) {
+        let audio = &utterance.samples;
+        let span = (utterance.start, utterance.end);
+        if audio.is_empty() {
+            return;
+        }
+        let base_time_ms = span.0 / 16;
+
+        tracing::debug!(
+            "Running STT on {} samples ({:.1}s)",
+            audio.len(),
+            audio.len() as f64 / 16000.0,
+        );
+
+        let segments = match self.engine.transcribe_sync(audio, base_time_ms) {
+            Ok(segments) => segments,
+            Err(e) => {
+                tracing::error!("STT inference failed: {}", e);
+                return;
+            }
+        };
+        let count = segments.len();
+        let mut previous = None;
+        for (i, seg) in segments.into_iter().enumerate() {
+            self.segment_counter += 1;
+            // Build canonical segment_id — single source of truth
+            let seg_id = format!("seg-{}-{}", self.segment_counter, seg.start_ms);
+            let (start_sample, end_sample) = segment_samples(
+                span,
+                (
+                    seg.start_ms.saturating_sub(base_time_ms),
+                    seg.end_ms.saturating_sub(base_time_ms),
+                ),
+                i + 1 == count,
+            );
+            let seg_audio =
+                &audio[(start_sample - span.0) as usize..(end_sample - span.0) as usize];
+            let speaker = self.speaker(lane, seg_audio, audio, &mut previous);
+
+            let payload = serde_json::json!({
+                "text": seg.text,
+                "language": seg.lang,
+                "language_prob": seg.lang_prob,
+                "start_ms": seg.start_ms,
+                "end_ms": seg.end_ms,
+                "is_final": seg.is_final,
+                "segment_id": seg_id,
+                "speaker": speaker,
+            });
+
+            if let Err(e) = self.app.emit("stt-partial", payload) {
+                tracing::warn!("Failed to emit stt-partial: {}", e);
+            }
+
+            // Insert final segments into DB and feed to NoteEngine
+            if seg.is_final {
+                let sinks = &self.sinks;
+                if let Ok(guard) = sinks.meeting_id.lock() {
+                    if let Some(mid) = *guard {
+                        if let Err(e) = sinks.transcript_db.insert_transcript(
+                            mid,
+                            &seg.text,
+                            &seg_id,
+                            Some(&speaker),
+                            seg.start_ms as i64,
+                            (start_sample as i64, end_sample as i64),
+                            (&seg.lang, seg.lang_prob),
+                        ) {
+                            tracing::error!("Failed to insert transcript: {}", e);
                         }
                     }
+                }
 
-                    // Feed segment to buffer (std::sync::Mutex — fast, no async)
-                    if let Ok(mut buf) = segment_buffer.lock() {
-                        buf.push(TranscriptSegment {
-                            text: seg.text.clone(),
-                            timestamp_ms: seg.start_ms as i64,
-                            segment_id: seg_id.clone(),
-                        });
-                    }
+                // Feed segment to buffer (std::sync::Mutex — fast, no async)
+                if let Ok(mut buf) = sinks.segment_buffer.lock() {
+                    buf.push(TranscriptSegment {
+                        text: seg.text.clone(),
+                        timestamp_ms: seg.start_ms as i64,
+                        segment_id: seg_id.clone(),
+                    });
                 }
             }
         }
-        Err(e) => {
-            tracing::error!("STT inference failed: {}", e);
+    }
+
+    /// Re-decode the utterance in progress (`audio` from stream offset `start`) and
+    /// emit it as one non-final `stt-partial`: the prefix agreed by successive
+    /// decodes, then the tentative rest. Partials are not stored; the utterance's
+    /// final segments replace them once speech ends.
+    fn partial(&mut self, lane: &str, audio: &[f32], start: u64, stream: &mut UtteranceStream) {
+        let base_time_ms = start / 16;
+        let began = Instant::now();
+        let segments = match self.engine.transcribe_sync(audio, base_time_ms) {
+            Ok(segments) => segments,
+            Err(e) => {
+                tracing::warn!("Partial STT failed: {}", e);
+                return;
+            }
+        };
+        stream.decoded(audio.len(), began.elapsed());
+        let Some(first) = segments.first() else {
+            return;
+        };
+        let hypothesis: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        let (stable, tentative) = stream.agreement.update(&hypothesis.join(" "));
+        let text = [stable.as_str(), tentative.as_str()]
+            .into_iter()
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let payload = serde_json::json!({
+            "text": text,
+            "stable_text": stable,
+            "language": first.lang,
+            "language_prob": first.lang_prob,
+            "start_ms": base_time_ms,
+            "end_ms": base_time_ms + audio.len() as u64 / 16,
+            "is_final": false,
+            "segment_id": format!("partial-{}-{}", lane, base_time_ms),
+            "speaker": self.display_name(lane),
+        });
+        if let Err(e) = self.app.emit("stt-partial", payload) {
+            tracing::warn!("Failed to emit stt-partial: {}", e);
+        }
+    }
+
+    /// Speaker of a segment, as renamed. Diarizing, a segment too short to tell
+    /// keeps the previous voice of its utterance, or the known voice nearest the
+    /// utterance as a whole (which does not count as another sample of it);
+    /// otherwise (or if even that fails) it is the lane's label.
+    fn speaker(
+        &mut self,
+        lane: &str,
+        segment: &[f32],
+        utterance: &[f32],
+        previous: &mut Option<String>,
+    ) -> String {
+        if let Some(ref mut diarizer) = self.diarizer {
+            let label = diarizer
+                .label(segment)
+                .or_else(|| previous.clone())
+                .or_else(|| diarizer.nearest_label(utterance));
+            if label.is_some() {
+                *previous = label;
+            }
         }
+        self.display_name(previous.as_deref().unwrap_or(lane))
+    }
+
+    /// `label` as renamed during the meeting.
+    fn display_name(&self, label: &str) -> String {
+        match self.sinks.speaker_names.lock() {
+            Ok(names) => names
+                .get(label)
+                .cloned()
+                .unwrap_or_else(|| label.to_string()),
+            Err(_) => label.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_samples_map_into_utterance_span() {
+        // Utterance spans samples 16000..48000 (1s..3s)
+        let span = (16000, 48000);
+        assert_eq!(segment_samples(span, (0, 500), false), (16000, 24000));
+        // Last segment stretches to the utterance end
+        assert_eq!(segment_samples(span, (500, 900), true), (24000, 48000));
+        // Whisper overshoot is clamped
+        assert_eq!(segment_samples(span, (1900, 2500), false), (46400, 48000));
+        assert_eq!(segment_samples(span, (5000, 6000), false), (48000, 48000));
+    }
+
+    #[test]
+    fn streaming_is_opt_in() {
+        let config: PipelineConfig =
+            serde_json::from_str(r#"{"streaming":{"enabled":true}}"#).unwrap();
+        assert!(config.streaming.enabled);
+        assert_eq!(config.streaming.interval_ms, 1000);
+        assert!(!PipelineConfig::default().streaming.enabled);
+    }
+
+    #[test]
+    fn config_selects_resampler_by_name() {
+        let config: PipelineConfig = serde_json::from_str(r#"{"resampler":"sinc"}"#).unwrap();
+        assert_eq!(config.resampler, ResamplerKind::Sinc);
+        assert_eq!(config.vad, VadConfig::default());
     }
 }
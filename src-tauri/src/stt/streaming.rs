@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Streaming captions, part of `PipelineConfig`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamingConfig {
+    /// Re-decode the utterance in progress and emit non-final `stt-partial` events.
+    pub enabled: bool,
+    /// Audio between re-decodes of a growing utterance.
+    pub interval_ms: u32,
+    /// Speech buffered before the first partial.
+    pub min_speech_ms: u32,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: 1000,
+            min_speech_ms: 1000,
+        }
+    }
+}
+
+/// Stable prefix of successive hypotheses of one utterance (LocalAgreement-2):
+/// a word is committed once two consecutive decodes agree on it and on all words
+/// before it. Committed words are never retracted, so the start of a caption stops
+/// changing while the end is still being decoded.
+#[derive(Default)]
+pub struct LocalAgreement {
+    committed: Vec<String>,
+    previous: Vec<String>,
+}
+
+impl LocalAgreement {
+    /// Take in the latest hypothesis; returns the committed text and the tentative
+    /// rest of the hypothesis after it.
+    pub fn update(&mut self, hypothesis: &str) -> (String, String) {
+        let words: Vec<String> = hypothesis.split_whitespace().map(str::to_string).collect();
+        let agreed = words
+            .iter()
+            .zip(&self.previous)
+            .take_while(|(a, b)| same_word(a, b))
+            .count();
+        if agreed > self.committed.len() {
+            let from = self.committed.len();
+            self.committed.extend_from_slice(&words[from..agreed]);
+        }
+        let tentative = words
+            .get(self.committed.len()..)
+            .unwrap_or_default()
+            .join(" ");
+        self.previous = words;
+        (self.committed.join(" "), tentative)
+    }
+
+    pub fn reset(&mut self) {
+        self.committed.clear();
+        self.previous.clear();
+    }
+}
+
+/// Words match ignoring case and punctuation, which whisper revises freely as
+/// more context arrives ("hello" → "Hello,").
+fn same_word(a: &str, b: &str) -> bool {
+    let letters = |w: &str| {
+        w.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    letters(a) == letters(b)
+}
+
+/// Re-decode schedule and agreement state of one lane's utterance in progress.
+pub struct UtteranceStream {
+    pub agreement: LocalAgreement,
+    /// Stream offset of the utterance being followed.
+    start: Option<u64>,
+    /// Utterance length (samples) at which the next re-decode is due.
+    next: usize,
+    interval: usize,
+    min_speech: usize,
+}
+
+impl UtteranceStream {
+    pub fn new(config: &StreamingConfig) -> Self {
+        let min_speech = config.min_speech_ms as usize * 16;
+        Self {
+            agreement: LocalAgreement::default(),
+            start: None,
+            next: min_speech,
+            interval: config.interval_ms.max(100) as usize * 16,
+            min_speech,
+        }
+    }
+
+    /// Whether the utterance starting at `start`, now `len` samples long, is due
+    /// for a re-decode. A new start means a new utterance and clears the state.
+    pub fn due(&mut self, start: u64, len: usize) -> bool {
+        if self.start != Some(start) {
+            self.reset();
+            self.start = Some(start);
+        }
+        len >= self.next
+    }
+
+    /// Schedule the next re-decode after decoding `len` samples in `took`. A decode
+    /// slower than the interval stretches it, so partials never starve the finals.
+    pub fn decoded(&mut self, len: usize, took: Duration) {
+        let took = (took.as_millis() as usize * 16).saturating_mul(2);
+        self.next = len + self.interval.max(took);
+    }
+
+    /// Utterance ended; forget it.
+    pub fn reset(&mut self) {
+        self.agreement.reset();
+        self.start = None;
+        self.next = self.min_speech;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_words_two_decodes_agree_on() {
+        let mut la = LocalAgreement::default();
+        assert_eq!(la.update("so the"), (String::new(), "so the".to_string()));
+        assert_eq!(
+            la.update("So, the plan is"),
+            ("So, the".to_string(), "plan is".to_string())
+        );
+        // A revised tail does not move the committed prefix
+        assert_eq!(
+            la.update("so the planet is"),
+            ("So, the".to_string(), "planet is".to_string())
+        );
+        assert_eq!(
+            la.update("so the planet is round"),
+            ("So, the planet is".to_string(), "round".to_string())
+        );
+        // Committed words stay even if a later decode drops them
+        assert_eq!(
+            la.update("so"),
+            ("So, the planet is".to_string(), String::new())
+        );
+
+        la.reset();
+        assert_eq!(la.update("next"), (String::new(), "next".to_string()));
+    }
+
+    #[test]
+    fn re_decodes_on_cadence_and_backs_off_when_slow() {
+        let config = StreamingConfig {
+            enabled: true,
+            interval_ms: 500,
+            min_speech_ms: 1000,
+        };
+        let mut stream = UtteranceStream::new(&config);
+        assert!(!stream.due(0, 15999));
+        assert!(stream.due(0, 16000));
+        stream.decoded(16000, Duration::from_millis(100));
+        assert!(!stream.due(0, 23999));
+        assert!(stream.due(0, 24000));
+
+        // 1s decodes push the next one 2s out
+        stream.decoded(24000, Duration::from_secs(1));
+        assert!(!stream.due(0, 24000 + 31999));
+        assert!(stream.due(0, 24000 + 32000));
+
+        // A new utterance starts over
+        assert!(!stream.due(64000, 8000));
+    }
+}
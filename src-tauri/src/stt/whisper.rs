@@ -1,12 +1,18 @@
 use serde::Serialize;
 use std::sync::Arc;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    get_lang_str, FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters,
+    WhisperState,
+};
 
 /// Result of a speech-to-text transcription.
 #[derive(Debug, Clone, Serialize)]
 pub struct TranscriptSegment {
     pub text: String,
+    /// Configured language, or the one whisper detected in the utterance.
     pub lang: String,
+    /// Detection probability of `lang`; None when the language was configured.
+    pub lang_prob: Option<f32>,
     pub start_ms: u64,
     pub end_ms: u64,
     pub is_final: bool,
@@ -40,6 +46,14 @@ impl SttEngine {
         self.language = lang;
     }
 
+    /// Engine sharing this one's loaded model, for `language` (None = auto-detect).
+    pub fn with_language(&self, language: Option<String>) -> Self {
+        Self {
+            ctx: self.ctx.clone(),
+            language,
+        }
+    }
+
     /// Run transcription synchronously (call from dedicated thread, NOT tokio runtime).
     /// `audio` must be 16kHz mono f32. `base_time_ms` offsets segment timestamps.
     pub fn transcribe_sync(
@@ -47,29 +61,41 @@ impl SttEngine {
         audio: &[f32],
         base_time_ms: u64,
     ) -> Result<Vec<TranscriptSegment>, String> {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-
-        if let Some(ref lang) = self.language {
-            params.set_language(Some(lang));
-        }
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_no_timestamps(false);
-
         let mut state = self
             .ctx
             .create_state()
             .map_err(|e| format!("Failed to create whisper state: {:?}", e))?;
 
+        // Auto-detect up front rather than inside `full`, which keeps no probability
+        let (lang_str, lang_prob) = match self.language {
+            Some(ref lang) => (lang.clone(), None),
+            None => match detect_language(&mut state, audio) {
+                Ok((lang, prob)) => (lang, Some(prob)),
+                Err(e) => {
+                    tracing::warn!("Language detection failed, leaving it to whisper: {}", e);
+                    ("auto".to_string(), None)
+                }
+            },
+        };
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(&lang_str));
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_no_timestamps(false);
+
         state
             .full(params, audio)
             .map_err(|e| format!("Whisper inference failed: {:?}", e))?;
 
         let n_segments = state.full_n_segments();
-        let lang_str = self
-            .language
-            .clone()
-            .unwrap_or_else(|| "en".to_string());
+        let lang_str = if lang_str == "auto" {
+            get_lang_str(state.full_lang_id_from_state())
+                .unwrap_or("auto")
+                .to_string()
+        } else {
+            lang_str
+        };
 
         let mut segments = Vec::new();
         for i in 0..n_segments {
@@ -87,6 +113,7 @@ impl SttEngine {
             segments.push(TranscriptSegment {
                 text: text.trim().to_string(),
                 lang: lang_str.clone(),
+                lang_prob,
                 start_ms: base_time_ms + t0 * 10, // centiseconds -> ms
                 end_ms: base_time_ms + t1 * 10,
                 is_final: true,
@@ -117,6 +144,21 @@ impl SttEngine {
     }
 }
 
+/// Whisper's guess at the language of `audio` (its first 30s) and the guess's
+/// probability.
+fn detect_language(state: &mut WhisperState, audio: &[f32]) -> Result<(String, f32), String> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(4));
+    state
+        .pcm_to_mel(audio, threads)
+        .map_err(|e| format!("Failed to compute mel: {:?}", e))?;
+    let (id, probs) = state
+        .lang_detect(0, threads)
+        .map_err(|e| format!("Language detection failed: {:?}", e))?;
+    let lang = get_lang_str(id).ok_or_else(|| format!("Unknown language id {}", id))?;
+    let prob = probs.get(id as usize).copied().unwrap_or(0.0);
+    Ok((lang.to_string(), prob))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,7 +175,8 @@ mod tests {
     fn transcript_segment_serialization() {
         let seg = TranscriptSegment {
             text: "Hello world".to_string(),
-            lang: "en".to_string(),
+            lang: "ja".to_string(),
+            lang_prob: Some(0.93),
             start_ms: 1000,
             end_ms: 2000,
             is_final: true,
@@ -142,5 +185,7 @@ mod tests {
         assert!(json.contains("\"text\":\"Hello world\""));
         assert!(json.contains("\"start_ms\":1000"));
         assert!(json.contains("\"is_final\":true"));
+        assert!(json.contains("\"lang\":\"ja\""));
+        assert!(json.contains("\"lang_prob\":0.93"));
     }
 }
@@ -29,11 +29,13 @@ impl TranslationPipeline {
     /// Translate a single text segment with streaming events.
     /// Emits `translation-update` events for each partial chunk,
     /// and a final event with `is_final: true`.
+    /// `source_lang` is the segment's language as detected by STT, or "auto".
     pub async fn translate(
         &self,
         app: &tauri::AppHandle,
         segment_id: &str,
         text: &str,
+        source_lang: &str,
     ) -> anyhow::Result<TranslationResult> {
         let messages = vec![
             ChatMessage {
@@ -89,7 +91,7 @@ impl TranslationPipeline {
                 Ok(TranslationResult {
                     source_text: text.to_string(),
                     translated_text: full_text,
-                    source_lang: "auto".to_string(),
+                    source_lang: source_lang.to_string(),
                     target_lang: self.target_lang.clone(),
                     segment_id: segment_id.to_string(),
                 })
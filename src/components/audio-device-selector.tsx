@@ -8,6 +8,35 @@ import {
   SelectValue,
 } from "@/components/ui/select";
 import { Mic } from "lucide-react";
+import type { SourceLevel } from "@/types";
+
+/** Lowest level shown on the meter. */
+const METER_FLOOR_DB = -60;
+
+/** Position of an amplitude on a dBFS meter, 0–100%. */
+function meterPercent(amplitude: number): number {
+  if (amplitude <= 0) return 0;
+  const db = 20 * Math.log10(amplitude);
+  return Math.min(100, Math.max(0, (1 - db / METER_FLOOR_DB) * 100));
+}
+
+function LevelMeter({ label, level }: { label: string; level: SourceLevel }) {
+  return (
+    <div className="flex items-center gap-2 text-xs">
+      <span className="w-16 text-muted-foreground">{label}</span>
+      <div className="relative h-2 flex-1 overflow-hidden rounded bg-muted">
+        <div
+          className={`h-full ${level.clipping ? "bg-red-500" : "bg-green-500"}`}
+          style={{ width: `${meterPercent(level.rms)}%` }}
+        />
+        <div
+          className="absolute top-0 h-full w-0.5 bg-foreground/60"
+          style={{ left: `${meterPercent(level.peak)}%` }}
+        />
+      </div>
+    </div>
+  );
+}
 
 export function AudioDeviceSelector() {
   const {
@@ -15,6 +44,7 @@ export function AudioDeviceSelector() {
     selectedDeviceId,
     isCapturing,
     error,
+    levels,
     startCapture,
     stopCapture,
     setSelectedDevice,
@@ -94,9 +124,12 @@ export function AudioDeviceSelector() {
       </div>
 
       {isCapturing && (
-        <p className="text-sm text-green-600 dark:text-green-400">
-          Capturing audio... (check console for data)
-        </p>
+        <div className="flex flex-col gap-1">
+          {levels?.mic && <LevelMeter label="Mic" level={levels.mic} />}
+          {levels?.loopback && (
+            <LevelMeter label="System" level={levels.loopback} />
+          )}
+        </div>
       )}
     </div>
   );
@@ -1,18 +1,22 @@
-import { invoke, Channel } from "@tauri-apps/api/core";
-import { useCallback, useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect } from "react";
+import { useTauriEvent } from "@/hooks/use-tauri-events";
 import { useAppStore } from "@/stores/app-store";
-import type { DeviceInfo } from "@/types";
+import type { AudioLevels, DeviceInfo } from "@/types";
 
 export function useAudioCapture() {
-  const channelRef = useRef<Channel<number[]> | null>(null);
   const {
     audio,
     setAudioDevices,
     setCapturing,
     setAudioError,
+    setAudioLevels,
     setSelectedDevice,
   } = useAppStore();
 
+  // Level meters are fed by the backend's metering, not raw audio
+  useTauriEvent<AudioLevels>("audio-levels", setAudioLevels);
+
   const fetchDevices = useCallback(async () => {
     try {
       const devices = await invoke<DeviceInfo[]>("list_audio_devices");
@@ -39,7 +43,6 @@ export function useAudioCapture() {
       invoke("stop_audio_capture").catch(() => {
         // Ignore errors — capture may not be running
       });
-      channelRef.current = null;
     };
   }, []);
 
@@ -47,21 +50,8 @@ export function useAudioCapture() {
     try {
       setAudioError(null);
 
-      const channel = new Channel<number[]>();
-      channelRef.current = channel;
-
-      channel.onmessage = (audioBytes: number[]) => {
-        const bytes = new Uint8Array(audioBytes);
-        const floats = new Float32Array(bytes.buffer);
-        console.log(
-          `Audio chunk: ${floats.length} samples`,
-          floats.slice(0, 5),
-        );
-      };
-
       await invoke("start_audio_capture", {
         deviceId: audio.selectedDeviceId,
-        onAudio: channel,
       });
 
       setCapturing(true);
@@ -75,17 +65,18 @@ export function useAudioCapture() {
     try {
       await invoke("stop_audio_capture");
       setCapturing(false);
-      channelRef.current = null;
+      setAudioLevels(null);
     } catch (err) {
       setAudioError(`Failed to stop capture: ${err}`);
     }
-  }, [setAudioError, setCapturing]);
+  }, [setAudioError, setAudioLevels, setCapturing]);
 
   return {
     devices: audio.devices,
     selectedDeviceId: audio.selectedDeviceId,
     isCapturing: audio.isCapturing,
     error: audio.error,
+    levels: audio.levels,
     fetchDevices,
     startCapture,
     stopCapture,
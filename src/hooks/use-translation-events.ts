@@ -32,6 +32,7 @@ export function useAutoTranslation() {
       text: payload.text.trim(),
       targetLangs,
       segmentId,
+      sourceLang: payload.language,
     }).catch((err) => {
       console.error("Translation invoke failed:", err);
     });
@@ -1,5 +1,6 @@
 import { create } from "zustand";
 import type {
+  AudioLevels,
   AudioState,
   DeviceInfo,
   OverlaySettings,
@@ -34,6 +35,7 @@ interface AppState {
   setSelectedDevice: (deviceId: string | null) => void;
   setCapturing: (isCapturing: boolean) => void;
   setAudioError: (error: string | null) => void;
+  setAudioLevels: (levels: AudioLevels | null) => void;
 
   // STT state
   stt: SttSlice;
@@ -79,6 +81,7 @@ export const useAppStore = create<AppState>((set) => ({
     selectedDeviceId: null,
     isCapturing: false,
     error: null,
+    levels: null,
   },
   setAudioDevices: (devices) =>
     set((state) => ({ audio: { ...state.audio, devices } })),
@@ -90,6 +93,8 @@ export const useAppStore = create<AppState>((set) => ({
     set((state) => ({ audio: { ...state.audio, isCapturing } })),
   setAudioError: (error) =>
     set((state) => ({ audio: { ...state.audio, error } })),
+  setAudioLevels: (levels) =>
+    set((state) => ({ audio: { ...state.audio, levels } })),
 
   stt: {
     transcript: [],
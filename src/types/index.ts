@@ -32,20 +32,40 @@ export interface DeviceInfo {
   channels: number;
 }
 
+/** Signal level of one source over the last metering window (0.0–1.0). */
+export interface SourceLevel {
+  rms: number;
+  peak: number;
+  clipping: boolean;
+}
+
+/** Payload of the `audio-levels` event; null for sources not being captured. */
+export interface AudioLevels {
+  mic: SourceLevel | null;
+  loopback: SourceLevel | null;
+}
+
 export interface AudioState {
   devices: DeviceInfo[];
   selectedDeviceId: string | null;
   isCapturing: boolean;
   error: string | null;
+  levels: AudioLevels | null;
 }
 
 export interface SttEventPayload {
   text: string;
   language: string;
+  /** Detection probability of `language`; null when it was configured */
+  language_prob: number | null;
   start_ms: number;
   end_ms: number;
   is_final: boolean;
   segment_id: string;
+  /** Source lane: "Me" (mic) or "Remote" (system loopback) */
+  speaker: string;
+  /** Partials only: leading text that will no longer change */
+  stable_text?: string;
 }
 
 export interface TranscriptEntry {